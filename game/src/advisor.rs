@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy::tasks::block_on;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::AsyncComputeTaskPool;
+use bevy::tasks::Task;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::bots::play_out;
+use crate::bots::HeuristicStrategy;
+use crate::focus::FocusedSession;
+use crate::game::Phase;
+use crate::game::Round;
+use crate::game::SeatView;
+use crate::hotseat::HotSeat;
+use crate::play::TableGame;
+use crate::rules::score;
+use crate::rules::RuleSet;
+use crate::Card;
+use crate::SeatKind;
+
+/// Layouts of the unseen cards played out for each piece of advice.
+const SAMPLES: usize = 400;
+
+/// Which table, round and bids some advice was worked out for.
+type Situation = (Entity, usize, Vec<Option<usize>>);
+
+/// A panel suggesting what to bid while a human at the focused table is bidding, B hides and
+/// shows it.
+pub struct AdvisorPlugin;
+impl Plugin for AdvisorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, update_advisor_text);
+    }
+}
+
+/// How likely a hand is to take each number of tricks, and the bid worth the most on average.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BidAdvice {
+    /// The chance of taking exactly `i` tricks.
+    pub chances: Vec<f32>,
+    /// Average points for bidding `i`, `None` where the bid isn't allowed.
+    pub expected_points: Vec<Option<f32>>,
+    pub best_bid: usize,
+}
+
+impl BidAdvice {
+    pub fn render(&self) -> String {
+        let mut text = "Bid advisor\nTricks  Chance  Points if bid\n".to_owned();
+        for (tricks, chance) in self.chances.iter().enumerate() {
+            let points = match self.expected_points[tricks] {
+                Some(points) => format!("{points:.1}"),
+                None => "hooked".to_owned(),
+            };
+            let best = match tricks == self.best_bid {
+                true => "  <- best bid",
+                false => "",
+            };
+            text += &format!(
+                "{tricks:>6}  {:>5.0}%  {points:>13}{best}\n",
+                chance * 100.0
+            );
+        }
+        text
+    }
+}
+
+/// Advise the seat of `view` on its bid, before any card is played.
+///
+/// The cards it can't see are dealt out at random `samples` times, and each layout is played
+/// out with every seat bidding and playing like the heuristic bot.
+pub fn advise_bid(view: &SeatView, rules: &RuleSet, samples: usize, rng: &mut StdRng) -> BidAdvice {
+    let unseen = Card::get_new_deck()
+        .into_iter()
+        .filter(|card| !view.hand.contains(card) && Some(*card) != view.trump_card)
+        .collect_vec();
+
+    let mut counts = vec![0; view.hand_size + 1];
+    for _ in 0..samples {
+        let mut deck = unseen.clone();
+        deck.shuffle(rng);
+        let hands = (0..view.num_players)
+            .map(|seat| match seat == view.seat {
+                true => view.hand.clone(),
+                false => deck.split_off(deck.len() - view.hand_size),
+            })
+            .collect_vec();
+        let mut round = Round::from_hands(hands, view.trump_card, view.dealer);
+        round.bids = view.bids.clone();
+        play_out(&HeuristicStrategy, &mut round, rules, rng);
+        counts[round.taken[view.seat]] += 1;
+    }
+
+    let chances = counts
+        .iter()
+        .map(|count| *count as f32 / samples.max(1) as f32)
+        .collect_vec();
+    let bids_in_order = (1..=view.num_players)
+        .map(|i| (view.dealer + i) % view.num_players)
+        .map_while(|seat| view.bids[seat])
+        .collect_vec();
+    let expected_points = (0..=view.hand_size)
+        .map(|bid| {
+            rules
+                .validate_bid(bid, view.hand_size, &bids_in_order, view.num_players)
+                .ok()
+                .map(|()| chances[bid] * score(bid, bid, view.hand_size) as f32)
+        })
+        .collect_vec();
+    // Lowest bid on ties
+    let best_bid = expected_points
+        .iter()
+        .enumerate()
+        .rev()
+        .filter_map(|(bid, points)| Some((bid, (*points)?)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(bid, _)| bid)
+        .unwrap_or_default();
+
+    BidAdvice {
+        chances,
+        expected_points,
+        best_bid,
+    }
+}
+
+#[derive(Component)]
+struct AdvisorText;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            right: Val::Px(5.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+        AdvisorText,
+        Name::new("Bid Advisor"),
+    ));
+}
+
+/// Advice being worked out in the background, and the situation it's for.
+#[derive(Default)]
+struct Advising(Option<(Situation, Task<BidAdvice>)>);
+
+/// Advise the human bidding at the focused table, working it out again only when the bids change.
+///
+/// At a hot-seat table it waits until the bidder has clicked ready, so nobody else sees it.
+///
+/// The playouts take a while, so they run on the async compute pool, and not at all while
+/// the panel is hidden.
+fn update_advisor_text(
+    input: Res<ButtonInput<KeyCode>>,
+    mut hidden: Local<bool>,
+    mut advised: Local<Option<Situation>>,
+    mut advising: Local<Advising>,
+    focused: Res<FocusedSession>,
+    game_query: Query<(&TableGame, Option<&HotSeat>)>,
+    seat_kind_query: Query<&SeatKind>,
+    mut advisor_text_query: Query<(&mut Text, &mut Visibility), With<AdvisorText>>,
+) {
+    if input.just_pressed(KeyCode::KeyB) {
+        *hidden = !*hidden;
+    }
+
+    let bidding_human = focused.0.and_then(|session_id| {
+        let (table_game, hot_seat) = game_query.get(session_id).ok()?;
+        let (seat, player_id) = table_game.to_act()?;
+        let bidding = matches!(table_game.game.phase(), Phase::Bidding { .. });
+        let human = seat_kind_query.get(player_id) == Ok(&SeatKind::Human);
+        (bidding && human && HotSeat::lets_act(hot_seat, seat))
+            .then_some((session_id, seat, table_game))
+    });
+
+    for (mut text, mut visibility) in advisor_text_query.iter_mut() {
+        let Some((session_id, seat, table_game)) = bidding_human else {
+            *visibility = Visibility::Hidden;
+            *advised = None;
+            // Dropping the task cancels it
+            advising.0 = None;
+            continue;
+        };
+        if *hidden {
+            *visibility = Visibility::Hidden;
+            advising.0 = None;
+            continue;
+        }
+        *visibility = Visibility::Visible;
+
+        let game = &table_game.game;
+        let situation = (session_id, game.round_index, game.round.bids.clone());
+        if advised.as_ref() == Some(&situation) {
+            continue;
+        }
+        match &mut advising.0 {
+            Some((advising_for, task)) if *advising_for == situation => {
+                if let Some(advice) = block_on(future::poll_once(task)) {
+                    text.sections[0].value = advice.render();
+                    *advised = Some(situation);
+                    advising.0 = None;
+                }
+            }
+            _ => {
+                let view = game.round.view(seat);
+                let rules = game.rules.clone();
+                let mut rng = StdRng::seed_from_u64(game.round_index as u64);
+                let task = AsyncComputeTaskPool::get()
+                    .spawn(async move { advise_bid(&view, &rules, SAMPLES, &mut rng) });
+                text.sections[0].value = "Bid advisor\nWorking it out...".to_owned();
+                advising.0 = Some((situation, task));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
+
+    use super::*;
+    use crate::game::Action;
+    use crate::game::Game;
+    use crate::properties::games;
+    use crate::properties::no_check;
+    use crate::properties::play_game;
+
+    proptest! {
+        #[test]
+        fn the_advised_bid_is_worth_the_most((num_players, seed, moves_seed, rules) in games()) {
+            let mut game = Game::new(rules, num_players, seed);
+            let mut rng = StdRng::seed_from_u64(moves_seed);
+            // Advice for the first round's bids, it's the same from then on
+            let choose = |game: &Game, seat: usize| {
+                if game.round_index > 0 || !matches!(game.phase(), Phase::Bidding { .. }) {
+                    return Ok(game.legal_actions()[0]);
+                }
+                let view = game.round.view(seat);
+                let advice = advise_bid(&view, &game.rules, 20, &mut rng);
+                prop_assert!((advice.chances.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+                let legal = game.round.legal_bids(&game.rules);
+                let best = advice.expected_points[advice.best_bid]
+                    .ok_or_else(|| TestCaseError::fail("the best bid is hooked"))?;
+                for (bid, points) in advice.expected_points.iter().enumerate() {
+                    prop_assert_eq!(points.is_some(), legal.contains(&bid), "bid {}", bid);
+                    if let Some(points) = points {
+                        let made = score(bid, bid, view.hand_size) as f32;
+                        prop_assert_eq!(*points, advice.chances[bid] * made, "bid {}", bid);
+                        prop_assert!(*points <= best, "bid {} beats {}", bid, advice.best_bid);
+                    }
+                }
+                Ok(Action::Bid(advice.best_bid))
+            };
+            play_game(&mut game, choose, no_check)?;
+        }
+    }
+}
@@ -0,0 +1,553 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use itertools::Itertools;
+
+use crate::Card;
+use crate::CardLocation;
+use crate::Player;
+use crate::Session;
+use crate::SessionRef;
+
+/// Checks every session each frame for states the game should never be in.
+///
+/// Added in debug builds, and by tests that want to catch corrupted state early.
+pub struct AuditPlugin;
+impl Plugin for AuditPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AuditViolationEvent>();
+        app.add_systems(
+            PostUpdate,
+            (audit_sessions, report_audit_violations).chain(),
+        );
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum AuditViolation {
+    WrongCardCount {
+        count: usize,
+    },
+    DuplicateCard(Card),
+    MissingCard {
+        card_id: Entity,
+    },
+    MissingLocation {
+        card_id: Entity,
+    },
+    DeckGap {
+        indices: Vec<usize>,
+    },
+    HandGap {
+        player_id: Entity,
+        indices: Vec<usize>,
+    },
+    TrickGap {
+        indices: Vec<usize>,
+    },
+    UnknownPlayer {
+        card_id: Entity,
+        player_id: Entity,
+    },
+    WrongSessionRef {
+        entity: Entity,
+        session_ref: Option<Entity>,
+    },
+    NotInSession {
+        entity: Entity,
+    },
+}
+
+impl fmt::Display for AuditViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditViolation::WrongCardCount { count } => {
+                write!(f, "session has {count} cards instead of 52")
+            }
+            AuditViolation::DuplicateCard(card) => write!(f, "{card:?} is in the session twice"),
+            AuditViolation::MissingCard { card_id } => {
+                write!(f, "card {card_id:?} is in the session but doesn't exist")
+            }
+            AuditViolation::MissingLocation { card_id } => {
+                write!(f, "card {card_id:?} has no location")
+            }
+            AuditViolation::DeckGap { indices } => {
+                write!(f, "deck indices aren't contiguous: {indices:?}")
+            }
+            AuditViolation::HandGap { player_id, indices } => write!(
+                f,
+                "hand indices of player {player_id:?} aren't contiguous: {indices:?}"
+            ),
+            AuditViolation::TrickGap { indices } => {
+                write!(f, "trick indices aren't contiguous: {indices:?}")
+            }
+            AuditViolation::UnknownPlayer { card_id, player_id } => write!(
+                f,
+                "card {card_id:?} is with player {player_id:?} who isn't in the session"
+            ),
+            AuditViolation::WrongSessionRef {
+                entity,
+                session_ref,
+            } => write!(f, "{entity:?} refers to session {session_ref:?}"),
+            AuditViolation::NotInSession { entity } => write!(
+                f,
+                "{entity:?} refers to the session but the session doesn't know about it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AuditViolation {}
+
+/// Sent once when a violation first appears in a session.
+#[derive(Event, Debug, Clone)]
+pub struct AuditViolationEvent {
+    pub session_id: Entity,
+    pub violation: AuditViolation,
+}
+
+/// Everything the audit needs to know about one card.
+struct AuditedCard<'a> {
+    card_id: Entity,
+    card: &'a Card,
+    location: Option<&'a CardLocation>,
+    session_ref: Option<Entity>,
+}
+
+/// Check one session, given its cards and every entity that refers to it.
+fn audit_session(
+    session_id: Entity,
+    session: &Session,
+    cards: &[AuditedCard],
+    missing_card_ids: &[Entity],
+    members: &[(Entity, bool, bool)],
+    session_refs: &HashMap<Entity, Entity>,
+) -> Vec<AuditViolation> {
+    let mut violations = Vec::new();
+
+    if session.card_ids.len() != 52 {
+        violations.push(AuditViolation::WrongCardCount {
+            count: session.card_ids.len(),
+        });
+    }
+    for card_id in missing_card_ids {
+        violations.push(AuditViolation::MissingCard { card_id: *card_id });
+    }
+    for (card, count) in cards.iter().counts_by(|card| *card.card) {
+        if count > 1 {
+            violations.push(AuditViolation::DuplicateCard(card));
+        }
+    }
+
+    let mut deck = Vec::new();
+    let mut hands: BTreeMap<Entity, Vec<usize>> = session
+        .player_ids
+        .iter()
+        .map(|player_id| (*player_id, Vec::new()))
+        .collect();
+    let mut trick = Vec::new();
+    for card in cards {
+        if card.session_ref != Some(session_id) {
+            violations.push(AuditViolation::WrongSessionRef {
+                entity: card.card_id,
+                session_ref: card.session_ref,
+            });
+        }
+        let Some(location) = card.location else {
+            violations.push(AuditViolation::MissingLocation {
+                card_id: card.card_id,
+            });
+            continue;
+        };
+        let player_id = match location {
+            CardLocation::Deck { index_from_bottom } => {
+                deck.push(*index_from_bottom);
+                None
+            }
+            CardLocation::Hand {
+                player,
+                index_from_left,
+            } => {
+                hands.entry(*player).or_default().push(*index_from_left);
+                Some(*player)
+            }
+            CardLocation::Trick { index, played_by } => {
+                trick.push(*index);
+                Some(*played_by)
+            }
+            CardLocation::Taken { player, .. } => Some(*player),
+            CardLocation::TrumpReveal => None,
+        };
+        if let Some(player_id) = player_id
+            && !session.player_ids.contains(&player_id)
+        {
+            violations.push(AuditViolation::UnknownPlayer {
+                card_id: card.card_id,
+                player_id,
+            });
+        }
+    }
+
+    if let Some(indices) = gaps(deck) {
+        violations.push(AuditViolation::DeckGap { indices });
+    }
+    for (player_id, hand) in hands {
+        if let Some(indices) = gaps(hand) {
+            violations.push(AuditViolation::HandGap { player_id, indices });
+        }
+    }
+    if let Some(indices) = gaps(trick) {
+        violations.push(AuditViolation::TrickGap { indices });
+    }
+
+    for entity in session
+        .player_ids
+        .iter()
+        .chain(std::iter::once(&session.table_id))
+    {
+        let session_ref = session_refs.get(entity).copied();
+        if session_ref != Some(session_id) {
+            violations.push(AuditViolation::WrongSessionRef {
+                entity: *entity,
+                session_ref,
+            });
+        }
+    }
+    for (entity, is_card, is_player) in members {
+        let known = match (is_card, is_player) {
+            (true, _) => session.card_ids.contains(entity),
+            (_, true) => session.player_ids.contains(entity),
+            _ => true,
+        };
+        if !known {
+            violations.push(AuditViolation::NotInSession { entity: *entity });
+        }
+    }
+
+    violations
+}
+
+/// The sorted indices if they aren't exactly `0..n`.
+fn gaps(mut indices: Vec<usize>) -> Option<Vec<usize>> {
+    indices.sort();
+    match indices.iter().copied().eq(0..indices.len()) {
+        true => None,
+        false => Some(indices),
+    }
+}
+
+fn audit_sessions(
+    session_query: Query<(Entity, &Session)>,
+    card_query: Query<(&Card, Option<&CardLocation>, Option<&SessionRef>)>,
+    member_query: Query<(Entity, &SessionRef, Has<Card>, Has<Player>)>,
+    mut reported: Local<HashMap<Entity, Vec<AuditViolation>>>,
+    mut violation_events: EventWriter<AuditViolationEvent>,
+) {
+    let session_refs: HashMap<Entity, Entity> = member_query
+        .iter()
+        .map(|(entity, session_ref, ..)| (entity, **session_ref))
+        .collect();
+    let mut members: HashMap<Entity, Vec<(Entity, bool, bool)>> = HashMap::default();
+    for (entity, session_ref, is_card, is_player) in member_query.iter() {
+        members
+            .entry(**session_ref)
+            .or_default()
+            .push((entity, is_card, is_player));
+    }
+
+    reported.retain(|session_id, _| session_query.contains(*session_id));
+    for (session_id, session) in session_query.iter() {
+        let mut cards = Vec::new();
+        let mut missing_card_ids = Vec::new();
+        for card_id in session.card_ids.iter() {
+            match card_query.get(*card_id) {
+                Ok((card, location, session_ref)) => cards.push(AuditedCard {
+                    card_id: *card_id,
+                    card,
+                    location,
+                    session_ref: session_ref.map(|session_ref| **session_ref),
+                }),
+                Err(_) => missing_card_ids.push(*card_id),
+            }
+        }
+
+        let violations = audit_session(
+            session_id,
+            session,
+            &cards,
+            &missing_card_ids,
+            members.get(&session_id).map_or(&[], |members| members),
+            &session_refs,
+        );
+        let previous = reported.entry(session_id).or_default();
+        for violation in violations.iter() {
+            if !previous.contains(violation) {
+                violation_events.send(AuditViolationEvent {
+                    session_id,
+                    violation: violation.clone(),
+                });
+            }
+        }
+        *previous = violations;
+    }
+}
+
+fn report_audit_violations(mut violation_events: EventReader<AuditViolationEvent>) {
+    for event in violation_events.read() {
+        error!(
+            "Session {:?} is corrupted: {}",
+            event.session_id, event.violation
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashSet;
+
+    use super::*;
+    use crate::rules::RuleSet;
+
+    /// A table of two with the whole deck stacked in the middle, nothing wrong with it yet.
+    struct Audited {
+        app: App,
+        session_id: Entity,
+        table_id: Entity,
+        player_ids: Vec<Entity>,
+        /// From the bottom of the deck up.
+        card_ids: Vec<Entity>,
+    }
+
+    impl Audited {
+        fn new() -> Self {
+            let mut app = App::new();
+            app.add_plugins(AuditPlugin);
+            let world = &mut app.world;
+            let session_id = world.spawn_empty().id();
+            let table_id = world.spawn(SessionRef(session_id)).id();
+            let player_ids = (0..2)
+                .map(|_| world.spawn((Player, SessionRef(session_id))).id())
+                .collect_vec();
+            let card_ids = Card::get_new_deck()
+                .into_iter()
+                .enumerate()
+                .map(|(i, card)| {
+                    world
+                        .spawn((
+                            card,
+                            CardLocation::Deck {
+                                index_from_bottom: i,
+                            },
+                            SessionRef(session_id),
+                        ))
+                        .id()
+                })
+                .collect_vec();
+            world.entity_mut(session_id).insert(Session {
+                number: 1,
+                table_id,
+                player_ids: player_ids.iter().copied().collect::<HashSet<_>>(),
+                card_ids: card_ids.iter().copied().collect::<HashSet<_>>(),
+                rules: RuleSet::default(),
+                seed: 0,
+                decks: Vec::new(),
+                moves: Vec::new(),
+            });
+            Audited {
+                app,
+                session_id,
+                table_id,
+                player_ids,
+                card_ids,
+            }
+        }
+
+        fn top_card(&self) -> Entity {
+            *self.card_ids.last().unwrap()
+        }
+
+        fn session(&mut self) -> Mut<'_, Session> {
+            self.app.world.get_mut::<Session>(self.session_id).unwrap()
+        }
+
+        fn violations(&mut self) -> Vec<AuditViolation> {
+            self.app.update();
+            let session_id = self.session_id;
+            self.app
+                .world
+                .resource_mut::<Events<AuditViolationEvent>>()
+                .drain()
+                .inspect(|event| assert_eq!(event.session_id, session_id))
+                .map(|event| event.violation)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn a_sound_session_passes() {
+        assert_eq!(Audited::new().violations(), Vec::new());
+    }
+
+    #[test]
+    fn a_violation_is_reported_once() {
+        let mut audited = Audited::new();
+        let card_id = audited.top_card();
+        audited
+            .app
+            .world
+            .entity_mut(card_id)
+            .remove::<CardLocation>();
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::MissingLocation { card_id }]
+        );
+        assert_eq!(audited.violations(), Vec::new());
+    }
+
+    #[test]
+    fn a_card_the_session_lost_is_caught() {
+        let mut audited = Audited::new();
+        let card_id = audited.top_card();
+        audited.session().card_ids.remove(&card_id);
+        assert_eq!(
+            audited.violations(),
+            vec![
+                AuditViolation::WrongCardCount { count: 51 },
+                AuditViolation::NotInSession { entity: card_id },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_card_in_the_deck_twice_is_caught() {
+        let mut audited = Audited::new();
+        let card = *audited.app.world.get::<Card>(audited.card_ids[0]).unwrap();
+        let card_id = audited.top_card();
+        audited.app.world.entity_mut(card_id).insert(card);
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::DuplicateCard(card)]
+        );
+    }
+
+    #[test]
+    fn a_despawned_card_is_caught() {
+        let mut audited = Audited::new();
+        let card_id = audited.top_card();
+        audited.app.world.despawn(card_id);
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::MissingCard { card_id }]
+        );
+    }
+
+    #[test]
+    fn gaps_in_the_deck_hands_and_trick_are_caught() {
+        let mut audited = Audited::new();
+        let card_id = audited.card_ids[0];
+        audited
+            .app
+            .world
+            .entity_mut(card_id)
+            .insert(CardLocation::Deck {
+                index_from_bottom: 60,
+            });
+        let mut indices = (1..52).collect_vec();
+        indices.push(60);
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::DeckGap { indices }]
+        );
+
+        let mut audited = Audited::new();
+        let player_id = audited.player_ids[0];
+        let card_id = audited.top_card();
+        audited
+            .app
+            .world
+            .entity_mut(card_id)
+            .insert(CardLocation::Hand {
+                player: player_id,
+                index_from_left: 1,
+            });
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::HandGap {
+                player_id,
+                indices: vec![1],
+            }]
+        );
+
+        let mut audited = Audited::new();
+        let played_by = audited.player_ids[1];
+        let card_id = audited.top_card();
+        audited
+            .app
+            .world
+            .entity_mut(card_id)
+            .insert(CardLocation::Trick {
+                index: 1,
+                played_by,
+            });
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::TrickGap { indices: vec![1] }]
+        );
+    }
+
+    #[test]
+    fn a_card_with_a_stranger_is_caught() {
+        let mut audited = Audited::new();
+        let player_id = audited.app.world.spawn(Player).id();
+        let card_id = audited.top_card();
+        audited
+            .app
+            .world
+            .entity_mut(card_id)
+            .insert(CardLocation::Taken {
+                player: player_id,
+                trick: 0,
+                index: 0,
+            });
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::UnknownPlayer { card_id, player_id }]
+        );
+    }
+
+    #[test]
+    fn a_table_that_points_elsewhere_is_caught() {
+        let mut audited = Audited::new();
+        let other_session_id = audited.app.world.spawn_empty().id();
+        let table_id = audited.table_id;
+        audited
+            .app
+            .world
+            .entity_mut(table_id)
+            .insert(SessionRef(other_session_id));
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::WrongSessionRef {
+                entity: table_id,
+                session_ref: Some(other_session_id),
+            }]
+        );
+    }
+
+    #[test]
+    fn a_player_the_session_doesnt_know_is_caught() {
+        let mut audited = Audited::new();
+        let session_id = audited.session_id;
+        let entity = audited
+            .app
+            .world
+            .spawn((Player, SessionRef(session_id)))
+            .id();
+        assert_eq!(
+            audited.violations(),
+            vec![AuditViolation::NotInSession { entity }]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+
+use crate::focus::FocusedSession;
+use crate::game::Game;
+use crate::game::Phase;
+use crate::play::RejectedActionEvent;
+use crate::play::TableGame;
+use crate::rules::BidBalance;
+
+/// A panel with the focused table's bids, the bid the hook rule keeps from the dealer and
+/// whether the table is over or under once everyone has bid.
+///
+/// A move the rules turn down is shown there too, until the next move at the table.
+pub struct BiddingPlugin;
+impl Plugin for BiddingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, update_bidding_text);
+    }
+}
+
+#[derive(Component)]
+struct BiddingText;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+        BiddingText,
+        Name::new("Bidding Panel"),
+    ));
+}
+
+/// The bids of the round `game` is in, a seat a line in bidding order.
+pub fn describe_bidding(game: &Game) -> String {
+    let round = &game.round;
+    let mut text = format!("Bids for {} tricks\n", round.hand_size);
+    for seat in round.bidding_order() {
+        let bid = match round.bids[seat] {
+            Some(bid) => bid.to_string(),
+            None => "-".to_owned(),
+        };
+        let dealer = match seat == round.dealer {
+            true => " (dealer)",
+            false => "",
+        };
+        text += &format!("Seat {}{dealer}: {bid}\n", seat + 1);
+    }
+    match game.phase() {
+        Phase::Bidding { .. } => {
+            let forbidden = game.rules.forbidden_bid(
+                round.hand_size,
+                &round.bids_in_order(),
+                round.num_players,
+            );
+            if let Some(forbidden) = forbidden {
+                text += &format!("Hooked, the dealer can't bid {forbidden}\n");
+            }
+        }
+        _ => {
+            let bids = round.bids.iter().flatten().copied().collect::<Vec<_>>();
+            text += &format!("Bids are {}\n", BidBalance::of(&bids, round.hand_size));
+        }
+    }
+    text
+}
+
+/// Show the focused table's bidding, and the last move turned down there since its last move.
+fn update_bidding_text(
+    focused: Res<FocusedSession>,
+    mut rejected_events: EventReader<RejectedActionEvent>,
+    mut rejected: Local<Option<(RejectedActionEvent, usize)>>,
+    game_query: Query<&TableGame>,
+    mut bidding_text_query: Query<(&mut Text, &mut Visibility), With<BiddingText>>,
+) {
+    let table_game = focused
+        .0
+        .and_then(|session_id| game_query.get(session_id).ok());
+    for event in rejected_events.read() {
+        if let Ok(table_game) = game_query.get(event.session_id) {
+            *rejected = Some((event.clone(), table_game.game.log.len()));
+        }
+    }
+    if let Some((event, moves)) = &*rejected
+        && !table_game.is_some_and(|table_game| {
+            focused.0 == Some(event.session_id) && table_game.game.log.len() == *moves
+        })
+    {
+        *rejected = None;
+    }
+
+    for (mut text, mut visibility) in bidding_text_query.iter_mut() {
+        let Some(table_game) = table_game.filter(|table_game| !table_game.game.is_over()) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        let mut value = describe_bidding(&table_game.game);
+        if let Some((event, _)) = &*rejected {
+            value += &format!("{event}\n");
+        }
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
@@ -0,0 +1,632 @@
+use std::fmt;
+
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::game::Action;
+use crate::game::Game;
+use crate::game::Phase;
+use crate::game::Round;
+use crate::game::SeatView;
+use crate::rules::RuleSet;
+use crate::BotStyle;
+use crate::Card;
+use crate::Personality;
+use crate::Rank;
+
+/// How a bot picks its bids and cards.
+///
+/// Strategies only ever see a [`SeatView`], never the other hands.
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// A bid from `legal`, with why if the strategy can say.
+    fn bid(&self, view: &SeatView, legal: &[usize], rng: &mut StdRng)
+        -> (usize, Option<Rationale>);
+    /// A card from `legal`, with why if the strategy can say.
+    fn play(&self, view: &SeatView, legal: &[Card], rng: &mut StdRng) -> (Card, Option<Rationale>);
+    /// Called after any seat acts at the table, with `round` as it was before the action.
+    fn observe(&self, _round: &Round, _seat: usize, _action: Action) {}
+}
+
+pub const STRATEGY_NAMES: [&str; 4] = ["random", "heuristic", "cautious", "greedy"];
+
+pub fn strategy_by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "random" => Some(Box::new(RandomStrategy)),
+        "heuristic" => Some(Box::new(HeuristicStrategy)),
+        "cautious" => Some(Box::new(CautiousStrategy)),
+        "greedy" => Some(Box::new(GreedyStrategy)),
+        _ => None,
+    }
+}
+
+/// Ask `strategy` for the next action in `game` and why, or `None` if nobody is to act.
+pub fn choose_action(
+    strategy: &dyn Strategy,
+    game: &Game,
+    rng: &mut StdRng,
+) -> Option<(usize, Action, Option<Rationale>)> {
+    match game.phase() {
+        Phase::Bidding { seat } => {
+            let legal = game.round.legal_bids(&game.rules);
+            let (bid, rationale) = strategy.bid(&game.round.view(seat), &legal, rng);
+            Some((seat, Action::Bid(bid), rationale))
+        }
+        Phase::Playing { seat } => {
+            let legal = game.round.legal_plays(seat);
+            let (card, rationale) = strategy.play(&game.round.view(seat), &legal, rng);
+            Some((seat, Action::Play(card), rationale))
+        }
+        Phase::RoundOver | Phase::GameOver => None,
+    }
+}
+
+/// Finish `round` with every seat bidding and playing like `strategy`.
+pub fn play_out(strategy: &dyn Strategy, round: &mut Round, rules: &RuleSet, rng: &mut StdRng) {
+    loop {
+        match round.phase() {
+            Phase::Bidding { seat } => {
+                let legal = round.legal_bids(rules);
+                let (bid, _) = strategy.bid(&round.view(seat), &legal, rng);
+                round.bid(seat, bid, rules).expect("strategies bid legally");
+            }
+            Phase::Playing { seat } => {
+                let legal = round.legal_plays(seat);
+                let (card, _) = strategy.play(&round.view(seat), &legal, rng);
+                round.play(seat, card).expect("strategies play legally");
+            }
+            Phase::RoundOver | Phase::GameOver => return,
+        }
+    }
+}
+
+/// The legal bid closest to `target`, preferring the lower one on ties.
+pub fn closest_bid(target: f32, legal: &[usize]) -> usize {
+    legal
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            let a = (*a as f32 - target).abs();
+            let b = (*b as f32 - target).abs();
+            a.total_cmp(&b)
+        })
+        .unwrap_or_default()
+}
+
+/// Rough count of the tricks a hand takes: high trumps, aces and some kings.
+pub fn estimate_tricks(view: &SeatView) -> f32 {
+    let trump = view.trump();
+    let estimate: f32 = view
+        .hand
+        .iter()
+        .map(|card| match (Some(card.suit) == trump, card.rank) {
+            (true, Rank::Ace | Rank::King | Rank::Queen | Rank::Jack) => 1.0,
+            (true, _) => 0.5,
+            (false, Rank::Ace) => 0.8,
+            (false, Rank::King) => 0.4,
+            (false, _) => 0.0,
+        })
+        .sum();
+    // More players means more chances of being beaten
+    let crowding = 4.0 / view.num_players.max(4) as f32;
+    (estimate * crowding).min(view.hand_size as f32)
+}
+
+/// Why a bot bid or played what it did, like
+/// `2 sure trumps + AC ≈ 2.8 tricks; need 1 more, ducking`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rationale {
+    /// The cards expected to take tricks, like `2 sure trumps` or `AC`.
+    pub counted: Vec<String>,
+    /// The tricks they're expected to take, see [`estimate_tricks`].
+    pub estimate: f32,
+    /// Tricks still needed to make the bid, `None` while bidding.
+    pub needed: Option<usize>,
+    pub intent: Intent,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Intent {
+    /// Bidding about the estimate.
+    Estimate,
+    Underbid,
+    /// Bidding over the estimate, e.g. to take every trick.
+    Overbid,
+    /// Taking the trick.
+    Win,
+    /// Leading a card nobody can beat.
+    Cash,
+    /// Losing the trick, or saving winners for later.
+    Duck,
+    /// Not what the bot meant to do, see [`BotDifficulty`](crate::BotDifficulty).
+    Slip,
+}
+
+impl fmt::Display for Rationale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counted = match self.counted.is_empty() {
+            true => "nothing high".to_owned(),
+            false => self.counted.join(" + "),
+        };
+        write!(f, "{counted} ≈ {:.1} tricks; ", self.estimate)?;
+        match self.needed {
+            Some(0) => write!(f, "bid made, ")?,
+            Some(needed) => write!(f, "need {needed} more, ")?,
+            None => {}
+        }
+        let intent = match self.intent {
+            Intent::Estimate => "bidding it",
+            Intent::Underbid => "bidding under",
+            Intent::Overbid => "bidding over",
+            Intent::Win => "winning",
+            Intent::Cash => "cashing a sure winner",
+            Intent::Duck => "ducking",
+            Intent::Slip => "oops",
+        };
+        write!(f, "{intent}")
+    }
+}
+
+/// What [`estimate_tricks`] counts, trumps by number and the rest by card.
+fn counted_cards(view: &SeatView) -> Vec<String> {
+    let (trumps, others): (Vec<Card>, Vec<Card>) = view
+        .hand
+        .iter()
+        .copied()
+        .partition(|card| Some(card.suit) == view.trump());
+    let sure = trumps
+        .iter()
+        .filter(|card| matches!(card.rank, Rank::Ace | Rank::King | Rank::Queen | Rank::Jack))
+        .count();
+    let plural = |count: usize, what: &str| match count {
+        1 => format!("1 {what}"),
+        _ => format!("{count} {what}s"),
+    };
+    let mut counted = Vec::new();
+    if sure > 0 {
+        counted.push(plural(sure, "sure trump"));
+    }
+    if trumps.len() > sure {
+        counted.push(plural(trumps.len() - sure, "low trump"));
+    }
+    counted.extend(
+        others
+            .iter()
+            .filter(|card| matches!(card.rank, Rank::Ace | Rank::King))
+            .map(|card| card.to_string()),
+    );
+    counted
+}
+
+impl Rationale {
+    /// Bidding `bid`, over, under or about the estimate for the hand.
+    fn bid(view: &SeatView, bid: usize) -> Self {
+        let estimate = estimate_tricks(view);
+        let intent = match bid as f32 - estimate {
+            over if over > 0.5 => Intent::Overbid,
+            under if under < -0.5 => Intent::Underbid,
+            _ => Intent::Estimate,
+        };
+        Self {
+            counted: counted_cards(view),
+            estimate,
+            needed: None,
+            intent,
+        }
+    }
+
+    /// Playing a card meaning to `intent`, with the tricks still to take for the bid.
+    fn play(view: &SeatView, intent: Intent) -> Self {
+        let bid = view.bids[view.seat].unwrap_or_default();
+        Self {
+            counted: counted_cards(view),
+            estimate: estimate_tricks(view),
+            needed: Some(bid.saturating_sub(view.taken[view.seat])),
+            intent,
+        }
+    }
+}
+
+/// Trumps above everything, then by rank.
+fn strength(view: &SeatView, card: Card) -> (bool, u8) {
+    (Some(card.suit) == view.trump(), card.rank.value())
+}
+
+/// Play towards the bid: win cheaply while tricks are needed, otherwise shed high losers.
+fn play_to_bid(view: &SeatView, legal: &[Card]) -> (Card, Intent) {
+    let bid = view.bids[view.seat].unwrap_or_default();
+    let wants_tricks = view.taken[view.seat] < bid;
+    let leading = view.trick.cards.is_empty();
+    let last_to_play = view.trick.cards.len() + 1 == view.num_players;
+
+    let (winners, losers): (Vec<Card>, Vec<Card>) = legal
+        .iter()
+        .copied()
+        .partition(|card| !leading && view.would_win(*card));
+    let by_strength = |cards: &[Card]| {
+        cards
+            .iter()
+            .copied()
+            .sorted_by_key(|card| strength(view, *card))
+            .collect_vec()
+    };
+
+    match (wants_tricks, leading) {
+        (true, true) => {
+            let card = *by_strength(legal).last().expect("legal plays");
+            match is_boss(view, card) {
+                true => (card, Intent::Cash),
+                false => (card, Intent::Win),
+            }
+        }
+        (false, true) => (
+            *by_strength(legal).first().expect("legal plays"),
+            Intent::Duck,
+        ),
+        (true, false) => match winners.is_empty() {
+            true => (
+                *by_strength(&losers).first().expect("legal plays"),
+                Intent::Duck,
+            ),
+            false if last_to_play => (
+                *by_strength(&winners).first().expect("winners"),
+                Intent::Win,
+            ),
+            false => (*by_strength(&winners).last().expect("winners"), Intent::Win),
+        },
+        (false, false) => match losers.is_empty() {
+            true => (
+                *by_strength(&winners).last().expect("legal plays"),
+                Intent::Win,
+            ),
+            false => (*by_strength(&losers).last().expect("losers"), Intent::Duck),
+        },
+    }
+}
+
+/// Picks uniformly from the legal moves.
+pub struct RandomStrategy;
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+    fn bid(
+        &self,
+        _view: &SeatView,
+        legal: &[usize],
+        rng: &mut StdRng,
+    ) -> (usize, Option<Rationale>) {
+        (
+            *legal.choose(rng).expect("there is always a legal bid"),
+            None,
+        )
+    }
+    fn play(
+        &self,
+        _view: &SeatView,
+        legal: &[Card],
+        rng: &mut StdRng,
+    ) -> (Card, Option<Rationale>) {
+        (
+            *legal.choose(rng).expect("there is always a legal play"),
+            None,
+        )
+    }
+}
+
+/// Bids its estimate and plays to make it.
+pub struct HeuristicStrategy;
+impl Strategy for HeuristicStrategy {
+    fn name(&self) -> &'static str {
+        "heuristic"
+    }
+    fn bid(
+        &self,
+        view: &SeatView,
+        legal: &[usize],
+        _rng: &mut StdRng,
+    ) -> (usize, Option<Rationale>) {
+        let bid = closest_bid(estimate_tricks(view), legal);
+        (bid, Some(Rationale::bid(view, bid)))
+    }
+    fn play(
+        &self,
+        view: &SeatView,
+        legal: &[Card],
+        _rng: &mut StdRng,
+    ) -> (Card, Option<Rationale>) {
+        let (card, intent) = play_to_bid(view, legal);
+        (card, Some(Rationale::play(view, intent)))
+    }
+}
+
+/// Rounds its estimate down, then plays to make it.
+pub struct CautiousStrategy;
+impl Strategy for CautiousStrategy {
+    fn name(&self) -> &'static str {
+        "cautious"
+    }
+    fn bid(
+        &self,
+        view: &SeatView,
+        legal: &[usize],
+        _rng: &mut StdRng,
+    ) -> (usize, Option<Rationale>) {
+        let bid = closest_bid(estimate_tricks(view).floor(), legal);
+        (bid, Some(Rationale::bid(view, bid)))
+    }
+    fn play(
+        &self,
+        view: &SeatView,
+        legal: &[Card],
+        _rng: &mut StdRng,
+    ) -> (Card, Option<Rationale>) {
+        let (card, intent) = play_to_bid(view, legal);
+        (card, Some(Rationale::play(view, intent)))
+    }
+}
+
+/// Bids its estimate but takes every trick it can.
+pub struct GreedyStrategy;
+impl Strategy for GreedyStrategy {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+    fn bid(
+        &self,
+        view: &SeatView,
+        legal: &[usize],
+        _rng: &mut StdRng,
+    ) -> (usize, Option<Rationale>) {
+        let bid = closest_bid(estimate_tricks(view).ceil(), legal);
+        (bid, Some(Rationale::bid(view, bid)))
+    }
+    fn play(
+        &self,
+        view: &SeatView,
+        legal: &[Card],
+        _rng: &mut StdRng,
+    ) -> (Card, Option<Rationale>) {
+        let card = legal
+            .iter()
+            .copied()
+            .max_by_key(|card| strength(view, *card))
+            .expect("there is always a legal play");
+        // Its best card, which may still not be good enough
+        let intent = match view.trick.cards.is_empty() {
+            true if is_boss(view, card) => Intent::Cash,
+            true => Intent::Win,
+            false if view.would_win(card) => Intent::Win,
+            false => Intent::Duck,
+        };
+        (card, Some(Rationale::play(view, intent)))
+    }
+}
+
+/// Whether nobody else can hold a higher card of `card`'s suit, going by the cards seen.
+fn is_boss(view: &SeatView, card: Card) -> bool {
+    Card::get_new_deck()
+        .into_iter()
+        .filter(|other| other.suit == card.suit && other.rank.value() > card.rank.value())
+        .all(|other| view.hand.contains(&other) || view.played.contains(&other))
+}
+
+/// `view` with the finished tricks forgotten past the last `memory` cards.
+fn remembered(view: &SeatView, memory: Option<usize>) -> SeatView {
+    let Some(memory) = memory else {
+        return view.clone();
+    };
+    let finished = view.played.len() - view.trick.cards.len();
+    let mut view = view.clone();
+    view.played.drain(..finished.saturating_sub(memory));
+    view
+}
+
+impl BotStyle {
+    /// What the personality bids before any noise.
+    fn bid_target(&self, view: &SeatView) -> f32 {
+        let estimate = estimate_tricks(view);
+        match self.personality {
+            Personality::Balanced => estimate,
+            Personality::Conservative => (estimate - 0.5).floor().max(0.0),
+            // Taking every trick scores 20 instead of 10
+            Personality::Aggressive if estimate >= view.hand_size as f32 * 0.6 => {
+                view.hand_size as f32
+            }
+            Personality::Aggressive => estimate.ceil(),
+        }
+    }
+
+    /// The card played when not misplaying, and why.
+    fn planned_play(&self, view: &SeatView, legal: &[Card]) -> (Card, Intent) {
+        let view = remembered(view, self.difficulty.memory());
+        let bid = view.bids[view.seat].unwrap_or_default();
+        let wants_tricks = view.taken[view.seat] < bid;
+        let leading = view.trick.cards.is_empty();
+        let (bosses, others): (Vec<Card>, Vec<Card>) = legal
+            .iter()
+            .copied()
+            .partition(|card| is_boss(&view, *card));
+        let weakest = |cards: &[Card]| {
+            cards
+                .iter()
+                .copied()
+                .min_by_key(|card| strength(&view, *card))
+        };
+        // Counting cards only changes the lead: cash a sure winner, or keep it back when ducking
+        let counted = match (wants_tricks, leading) {
+            (true, true) => weakest(&bosses).map(|card| (card, Intent::Cash)),
+            (false, true) => weakest(&others).map(|card| (card, Intent::Duck)),
+            (_, false) => None,
+        };
+        counted.unwrap_or_else(|| play_to_bid(&view, legal))
+    }
+}
+
+/// The bots at the table, see [`BotDifficulty`](crate::BotDifficulty) and [`Personality`].
+impl Strategy for BotStyle {
+    fn name(&self) -> &'static str {
+        match self.personality {
+            Personality::Balanced => "balanced",
+            Personality::Conservative => "conservative",
+            Personality::Aggressive => "aggressive",
+        }
+    }
+    fn bid(
+        &self,
+        view: &SeatView,
+        legal: &[usize],
+        rng: &mut StdRng,
+    ) -> (usize, Option<Rationale>) {
+        let target = self.bid_target(view);
+        let noise = self.difficulty.bid_noise();
+        let noise = match noise > 0.0 {
+            true => rng.gen_range(-noise..=noise),
+            false => 0.0,
+        };
+        let bid = closest_bid(target + noise, legal);
+        let rationale = Rationale::bid(view, bid);
+        let rationale = match bid == closest_bid(target, legal) {
+            true => rationale,
+            false => Rationale {
+                intent: Intent::Slip,
+                ..rationale
+            },
+        };
+        (bid, Some(rationale))
+    }
+    fn play(&self, view: &SeatView, legal: &[Card], rng: &mut StdRng) -> (Card, Option<Rationale>) {
+        let (planned, intent) = self.planned_play(view, legal);
+        let card = match rng.gen_bool(self.difficulty.misplay_chance()) {
+            true => *legal.choose(rng).expect("there is always a legal play"),
+            false => planned,
+        };
+        let intent = match card == planned {
+            true => intent,
+            false => Intent::Slip,
+        };
+        (card, Some(Rationale::play(view, intent)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
+    use rand::SeedableRng;
+
+    use super::*;
+    // proptest has a `Strategy` too, these tests mean the bots'
+    use super::Strategy;
+    use crate::properties::bot_styles;
+    use crate::properties::games;
+    use crate::properties::no_check;
+    use crate::properties::play_game;
+    use crate::BotDifficulty;
+
+    proptest! {
+        #[test]
+        fn personalities_bias_bids(
+            (num_players, seed, moves_seed, rules) in games(),
+            styles in bot_styles(),
+        ) {
+            let mut game = Game::new(rules, num_players, seed);
+            let mut rng = StdRng::seed_from_u64(moves_seed);
+            let choose = |game: &Game, seat: usize| {
+                if let Phase::Bidding { .. } = game.phase() {
+                    let view = game.round.view(seat);
+                    let legal = game.round.legal_bids(&game.rules);
+                    let mut bid = |personality| {
+                        let style = BotStyle { difficulty: BotDifficulty::Expert, personality };
+                        style.bid(&view, &legal, &mut rng).0
+                    };
+                    let conservative = bid(Personality::Conservative);
+                    let balanced = bid(Personality::Balanced);
+                    let aggressive = bid(Personality::Aggressive);
+                    prop_assert!(
+                        conservative <= balanced && balanced <= aggressive,
+                        "bids {} {} {}", conservative, balanced, aggressive
+                    );
+                }
+                Ok(choose_action(&styles[seat], game, &mut rng).expect("a seat to act").1)
+            };
+            play_game(&mut game, choose, no_check)?;
+        }
+
+        #[test]
+        fn expert_bots_explain_every_action_without_slips(
+            (num_players, seed, moves_seed, rules) in games(),
+            styles in bot_styles(),
+        ) {
+            let mut game = Game::new(rules, num_players, seed);
+            let mut rng = StdRng::seed_from_u64(moves_seed);
+            let choose = |game: &Game, seat: usize| {
+                let style = BotStyle { difficulty: BotDifficulty::Expert, ..styles[seat] };
+                let (_, action, rationale) =
+                    choose_action(&style, game, &mut rng).expect("a seat to act");
+                let rationale = rationale.ok_or_else(|| TestCaseError::fail("no rationale"))?;
+                prop_assert_ne!(rationale.intent, Intent::Slip, "{}", rationale);
+                prop_assert_eq!(rationale.needed.is_none(), matches!(action, Action::Bid(_)));
+                Ok(action)
+            };
+            play_game(&mut game, choose, no_check)?;
+        }
+
+        #[test]
+        fn strategies_mean_what_their_cards_do(
+            (num_players, seed, moves_seed, rules) in games(),
+            name in prop::sample::select(&["heuristic", "cautious", "greedy"][..]),
+        ) {
+            let strategy = strategy_by_name(name).expect("a strategy");
+            let mut game = Game::new(rules, num_players, seed);
+            let mut rng = StdRng::seed_from_u64(moves_seed);
+            let choose = |game: &Game, seat: usize| {
+                let (_, action, rationale) =
+                    choose_action(&*strategy, game, &mut rng).expect("a seat to act");
+                let rationale = rationale.ok_or_else(|| TestCaseError::fail("no rationale"))?;
+                let view = game.round.view(seat);
+                if let Action::Play(card) = action {
+                    match (view.trick.cards.is_empty(), rationale.intent) {
+                        (true, Intent::Cash) => prop_assert!(is_boss(&view, card), "{}", card),
+                        (true, Intent::Win | Intent::Duck) => {}
+                        (false, Intent::Win) => prop_assert!(view.would_win(card), "{}", card),
+                        (false, Intent::Duck) => prop_assert!(!view.would_win(card), "{}", card),
+                        (_, intent) => prop_assert!(false, "{:?} playing {}", intent, card),
+                    }
+                }
+                Ok(action)
+            };
+            play_game(&mut game, choose, no_check)?;
+        }
+
+        #[test]
+        fn beginners_own_up_to_their_slips(
+            (num_players, seed, moves_seed, rules) in games(),
+            styles in bot_styles(),
+        ) {
+            let mut game = Game::new(rules, num_players, seed);
+            let mut rng = StdRng::seed_from_u64(moves_seed);
+            let choose = |game: &Game, seat: usize| {
+                let style = BotStyle { difficulty: BotDifficulty::Beginner, ..styles[seat] };
+                let view = game.round.view(seat);
+                let (_, action, rationale) =
+                    choose_action(&style, game, &mut rng).expect("a seat to act");
+                let rationale = rationale.ok_or_else(|| TestCaseError::fail("no rationale"))?;
+                let planned = match action {
+                    Action::Bid(_) => {
+                        let legal = game.round.legal_bids(&game.rules);
+                        Action::Bid(closest_bid(style.bid_target(&view), &legal))
+                    }
+                    Action::Play(_) => {
+                        Action::Play(style.planned_play(&view, &game.round.legal_plays(seat)).0)
+                    }
+                };
+                prop_assert_eq!(rationale.intent == Intent::Slip, action != planned, "{}", rationale);
+                Ok(action)
+            };
+            play_game(&mut game, choose, no_check)?;
+        }
+    }
+}
@@ -0,0 +1,737 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::bots::STRATEGY_NAMES;
+use crate::dataset::DatasetConfig;
+use crate::protocol::DEFAULT_ANSWER_TIMEOUT;
+use crate::rules::RuleSet;
+use crate::rules::MAX_PLAYERS;
+use crate::solver::AnalysisConfig;
+use crate::tournament::OutputFormat;
+use crate::tournament::TournamentConfig;
+use crate::tutorial;
+use crate::BotDifficulty;
+use crate::BotStyle;
+use crate::Card;
+use crate::Personality;
+use crate::SeatKind;
+use crate::SpawnSessionEvent;
+use crate::Suit;
+
+pub const USAGE: &str = "\
+Usage: poche [options]
+       poche stats [--stats <file>] [--profiles <file>]
+       poche tournament [--strategies <a,b,..>] [--games <n>] [--parallel <n>]
+                        [--seed <u64>] [--rules <preset>] [--out <file>] [--format csv|json]
+       poche scorekeeper --names <a,b,..> [--rules <preset>]
+       poche analyse [--hands <cards/cards/..>] [--trump <suit>] [--leader <seat>]
+                     [--players <n>] [--cards <n>] [--seed <u64>]
+       poche tutorial
+       poche bot [--seed <u64>]
+       poche selfplay [--strategy <name>] [--games <n>] [--players <n>] [--seed <u64>]
+                      [--rules <preset>] [--out <file>]
+
+Commands:
+    stats                 Print the leaderboard and exit
+    tournament            Play bot strategies against each other headless and report results
+    scorekeeper           Keep score for a game played with real cards, names clockwise from the first dealer
+    analyse               Solve a deal with every hand face up, a random one unless --hands is given
+    tutorial              Learn to play at a table with scripted deals
+    bot                   Play as the heuristic bot over stdin and stdout, the reference external bot
+    selfplay              Write a line of JSON for every decision of bot games, to train bots on
+
+Options:
+    --tables <n>          Number of tables to spawn (default 1)
+    --players <n>         Players per table, 2 to 7 (default 5)
+    --seats <hbpm..>      Seat kinds clockwise, h = human, b = bot, p = external bot, m = model bot
+                          (default h then bots), several h take turns at the screen
+    --seed <u64>          Seed for deck shuffles, tables use seed, seed+1, ...
+    --rules <preset>      Rule preset: family, hook (default family)
+    --difficulty <level>  Bot difficulty: beginner, family, expert (default family)
+    --personality <name>  Bot personality: balanced, conservative, aggressive (default balanced)
+    --bots <style,..>     Style of each b seat in order, like beginner,expert:aggressive,
+                          the last one for any left over (default --difficulty and --personality)
+    --headless            Run without opening a window
+    --no-advisor          Serious game, no bid advisor (otherwise B shows it)
+    --ranked              Ranked game, no bid advisor and no taking back moves with Backspace
+    --turn-time <secs>    Time limit per turn, then the lowest bid or card is played (default none)
+    --bot <command>       Program run for a p seat, the first for the first p seat and so on,
+                          the last one for any left over
+    --model <file>        Trained policy played by m seats, see model.rs for the format
+    --bot-timeout <secs>  Time an external bot has to answer before the heuristic bot's move
+                          is made for it (default 1)
+    --profiles <file>     Player profiles file (default profiles.json)
+    --stats <file>        Lifetime stats file (default stats.json)
+    --reviews <dir>       Folder for saved game reviews (default reviews)
+    --logs <dir>          Folder for saved game logs (default logs)
+    --resume <file>       Pick up the games saved in this file, and keep saving them there as
+                          they're played, starting new ones if there are none
+    --help                Print this message
+
+Tournament options:
+    --strategies <a,b,..> Strategies to seat, 2 to 7 of: random, heuristic, cautious, greedy,
+                          exe:<command> for an external bot, like \"exe:poche bot\",
+                          or model:<file> for a trained policy
+    --games <n>           Games to play (default 1000)
+    --parallel <n>        Tables playing at once (default 64)
+    --out <file>          Write results here instead of stdout
+    --format <format>     csv or json (default csv)
+
+Analyse options:
+    --hands <cards/..>    Hands clockwise from seat 1, like \"AS 10H 2C/KD QD 3S\"
+    --trump <suit>        Trump for --hands: spades, hearts, diamonds, clubs or none (default none)
+    --leader <seat>       Seat leading the first trick (default 1)
+    --cards <n>           Hand size of a random deal (default 7)
+
+Self-play options:
+    --strategy <name>     Strategy every seat plays: random, heuristic, cautious, greedy (default heuristic)
+    --games <n>           Games to play, game i is dealt from seed + i (default 100)
+    --out <file>          Write records here instead of stdout";
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum Command {
+    #[default]
+    Play,
+    Stats,
+    Tournament,
+    Scorekeeper,
+    Analyse,
+    Tutorial,
+    Bot,
+    SelfPlay,
+}
+
+/// How the game was launched.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct LaunchConfig {
+    pub command: Command,
+    pub num_tables: usize,
+    pub seats: Vec<SeatKind>,
+    pub seed: Option<u64>,
+    pub rules: RuleSet,
+    /// How bot seats play unless `bot_styles` says otherwise.
+    pub bot_style: BotStyle,
+    /// How each bot seat plays, in seat order.
+    pub bot_styles: Vec<BotStyle>,
+    pub headless: bool,
+    /// Whether the bid advisor is available.
+    pub advisor: bool,
+    /// Whether moves count for good, otherwise a human can take back their last one.
+    pub ranked: bool,
+    /// How long a seat has to act before the lowest bid or card is played for it.
+    pub turn_time: Option<Duration>,
+    /// Programs played by [`SeatKind::Process`] seats, in seat order.
+    pub bot_commands: Vec<String>,
+    /// How long an external bot has to answer.
+    pub bot_timeout: Duration,
+    /// The policy played by [`SeatKind::Model`] seats.
+    pub model: Option<PathBuf>,
+    pub profiles: PathBuf,
+    pub stats: PathBuf,
+    /// Where game reviews are saved.
+    pub reviews: PathBuf,
+    /// Where game logs are saved.
+    pub logs: PathBuf,
+    /// Where the tables are saved as they're played, and resumed from at launch.
+    pub resume: Option<PathBuf>,
+    pub tournament: TournamentConfig,
+    pub analysis: AnalysisConfig,
+    pub dataset: DatasetConfig,
+    /// Player names for the scorekeeper.
+    pub names: Vec<String>,
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            command: Command::Play,
+            num_tables: 1,
+            seats: SeatKind::default_seats(5, BotStyle::default()),
+            seed: None,
+            rules: RuleSet::default(),
+            bot_style: BotStyle::default(),
+            bot_styles: Vec::new(),
+            headless: false,
+            advisor: true,
+            ranked: false,
+            turn_time: None,
+            bot_commands: Vec::new(),
+            bot_timeout: DEFAULT_ANSWER_TIMEOUT,
+            model: None,
+            profiles: "profiles.json".into(),
+            stats: "stats.json".into(),
+            reviews: "reviews".into(),
+            logs: "logs".into(),
+            resume: None,
+            tournament: TournamentConfig::default(),
+            analysis: AnalysisConfig::default(),
+            dataset: DatasetConfig::default(),
+            names: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum CliError {
+    Help,
+    MissingValue(String),
+    InvalidValue { flag: String, value: String },
+    UnknownArgument(String),
+    SeatCountMismatch { players: usize, seats: usize },
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{USAGE}"),
+            CliError::MissingValue(flag) => write!(f, "{flag} needs a value"),
+            CliError::InvalidValue { flag, value } => {
+                write!(f, "invalid value for {flag}: {value}")
+            }
+            CliError::UnknownArgument(arg) => write!(f, "unknown argument: {arg}"),
+            CliError::SeatCountMismatch { players, seats } => {
+                write!(
+                    f,
+                    "--players {players} doesn't match {seats} seats given by --seats"
+                )
+            }
+        }
+    }
+}
+
+impl LaunchConfig {
+    /// Parse the arguments after the program name.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut config = LaunchConfig::default();
+        let mut players = None;
+        let mut seat_kinds = None;
+
+        let mut args = args.into_iter().peekable();
+        match args.peek().map(|arg| arg.as_str()) {
+            Some("stats") => config.command = Command::Stats,
+            Some("tournament") => config.command = Command::Tournament,
+            Some("scorekeeper") => config.command = Command::Scorekeeper,
+            Some("analyse") => config.command = Command::Analyse,
+            Some("tutorial") => config.command = Command::Tutorial,
+            Some("bot") => config.command = Command::Bot,
+            Some("selfplay") => config.command = Command::SelfPlay,
+            _ => {}
+        }
+        if config.command != Command::Play {
+            args.next();
+        }
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| CliError::MissingValue(arg.clone()))
+            };
+            match arg.as_str() {
+                "--tables" => {
+                    let tables = value()?;
+                    config.num_tables = parse(&arg, tables.clone())?;
+                    if config.num_tables == 0 {
+                        return Err(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: tables,
+                        });
+                    }
+                }
+                "--players" => players = Some(parse(&arg, value()?)?),
+                "--seats" => {
+                    let seats = value()?;
+                    let kinds = seats.to_ascii_lowercase().chars().collect_vec();
+                    if kinds.iter().any(|kind| !"hbpm".contains(*kind)) {
+                        return Err(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: seats,
+                        });
+                    }
+                    seat_kinds = Some(kinds);
+                }
+                "--seed" => config.seed = Some(parse(&arg, value()?)?),
+                "--rules" => {
+                    let name = value()?;
+                    config.rules = RuleSet::preset(&name).ok_or(CliError::InvalidValue {
+                        flag: arg.clone(),
+                        value: name,
+                    })?;
+                }
+                "--difficulty" => {
+                    let name = value()?;
+                    config.bot_style.difficulty =
+                        BotDifficulty::from_name(&name).ok_or(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: name,
+                        })?;
+                }
+                "--personality" => {
+                    let name = value()?;
+                    config.bot_style.personality =
+                        Personality::from_name(&name).ok_or(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: name,
+                        })?;
+                }
+                "--bots" => {
+                    let names = value()?;
+                    config.bot_styles = names
+                        .split(',')
+                        .map(BotStyle::from_name)
+                        .collect::<Option<_>>()
+                        .ok_or(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: names,
+                        })?;
+                }
+                "--headless" => config.headless = true,
+                "--no-advisor" => config.advisor = false,
+                "--ranked" => {
+                    config.ranked = true;
+                    config.advisor = false;
+                }
+                "--turn-time" => {
+                    let secs = value()?;
+                    config.turn_time = secs
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs > 0.0)
+                        .map(Duration::from_secs_f32);
+                    if config.turn_time.is_none() {
+                        return Err(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: secs,
+                        });
+                    }
+                }
+                "--bot" => config.bot_commands.push(value()?),
+                "--model" => config.model = Some(value()?.into()),
+                "--bot-timeout" => {
+                    let secs = value()?;
+                    config.bot_timeout = secs
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs > 0.0)
+                        .map(Duration::from_secs_f32)
+                        .ok_or(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: secs,
+                        })?;
+                }
+                "--profiles" => config.profiles = value()?.into(),
+                "--stats" => config.stats = value()?.into(),
+                "--reviews" => config.reviews = value()?.into(),
+                "--logs" => config.logs = value()?.into(),
+                "--resume" => config.resume = Some(value()?.into()),
+                "--strategies" => {
+                    let names = value()?;
+                    let strategies = names
+                        .split(',')
+                        .map(|name| name.trim().to_owned())
+                        .collect_vec();
+                    if !(2..=MAX_PLAYERS).contains(&strategies.len())
+                        || strategies.iter().any(|name| {
+                            !STRATEGY_NAMES.contains(&name.as_str())
+                                && !name.starts_with("exe:")
+                                && !name.starts_with("model:")
+                        })
+                    {
+                        return Err(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: names,
+                        });
+                    }
+                    config.tournament.strategies = strategies;
+                }
+                "--names" => {
+                    let names = value()?;
+                    config.names = names
+                        .split(',')
+                        .map(|name| name.trim().to_owned())
+                        .filter(|name| !name.is_empty())
+                        .collect();
+                    if !(2..=MAX_PLAYERS).contains(&config.names.len()) {
+                        return Err(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: names,
+                        });
+                    }
+                }
+                "--games" => {
+                    let games = parse(&arg, value()?)?;
+                    match config.command {
+                        Command::SelfPlay => config.dataset.games = games,
+                        _ => config.tournament.games = games,
+                    }
+                }
+                "--parallel" => {
+                    let tables = value()?;
+                    config.tournament.tables = parse(&arg, tables.clone())?;
+                    if config.tournament.tables == 0 {
+                        return Err(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: tables,
+                        });
+                    }
+                }
+                "--out" => {
+                    let out = Some(value()?.into());
+                    match config.command {
+                        Command::SelfPlay => config.dataset.out = out,
+                        _ => config.tournament.out = out,
+                    }
+                }
+                "--strategy" => {
+                    let name = value()?;
+                    if !STRATEGY_NAMES.contains(&name.as_str()) {
+                        return Err(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: name,
+                        });
+                    }
+                    config.dataset.strategy = name;
+                }
+                "--format" => {
+                    let format = value()?;
+                    config.tournament.format = match format.as_str() {
+                        "csv" => OutputFormat::Csv,
+                        "json" => OutputFormat::Json,
+                        _ => {
+                            return Err(CliError::InvalidValue {
+                                flag: arg.clone(),
+                                value: format,
+                            })
+                        }
+                    };
+                }
+                "--hands" => {
+                    let hands = value()?;
+                    config.analysis.hands =
+                        Some(parse_hands(&hands).ok_or(CliError::InvalidValue {
+                            flag: arg.clone(),
+                            value: hands,
+                        })?);
+                }
+                "--trump" => {
+                    let trump = value()?;
+                    config.analysis.trump = match trump.to_ascii_lowercase().as_str() {
+                        "spades" | "s" => Some(Suit::Spades),
+                        "hearts" | "h" => Some(Suit::Hearts),
+                        "diamonds" | "d" => Some(Suit::Diamonds),
+                        "clubs" | "c" => Some(Suit::Clubs),
+                        "none" => None,
+                        _ => {
+                            return Err(CliError::InvalidValue {
+                                flag: arg.clone(),
+                                value: trump,
+                            })
+                        }
+                    };
+                }
+                "--leader" => {
+                    let leader = value()?;
+                    config.analysis.leader = match parse::<usize>(&arg, leader.clone())? {
+                        0 => {
+                            return Err(CliError::InvalidValue {
+                                flag: arg,
+                                value: leader,
+                            })
+                        }
+                        seat => seat - 1,
+                    };
+                }
+                "--cards" => config.analysis.cards = parse(&arg, value()?)?,
+                "--help" | "-h" => return Err(CliError::Help),
+                _ => return Err(CliError::UnknownArgument(arg)),
+            }
+        }
+
+        config.seats = match (players, seat_kinds) {
+            (Some(players), Some(kinds)) if players != kinds.len() => {
+                return Err(CliError::SeatCountMismatch {
+                    players,
+                    seats: kinds.len(),
+                });
+            }
+            (_, Some(kinds)) => kinds
+                .into_iter()
+                .map(|kind| match kind {
+                    'h' => SeatKind::Human,
+                    'p' => SeatKind::Process,
+                    'm' => SeatKind::Model,
+                    _ => SeatKind::Bot(config.bot_style),
+                })
+                .collect_vec(),
+            (players, None) => SeatKind::default_seats(players.unwrap_or(5), config.bot_style),
+        };
+        let bot_seats = config.seats.iter_mut().filter_map(|seat| match seat {
+            SeatKind::Bot(style) => Some(style),
+            _ => None,
+        });
+        for (i, style) in bot_seats.enumerate() {
+            if let Some(chosen) = config.bot_styles.get(i).or(config.bot_styles.last()) {
+                *style = *chosen;
+            }
+        }
+        if config.seats.contains(&SeatKind::Process) && config.bot_commands.is_empty() {
+            return Err(CliError::MissingValue("--bot".into()));
+        }
+        if config.seats.contains(&SeatKind::Model) && config.model.is_none() {
+            return Err(CliError::MissingValue("--model".into()));
+        }
+        if config.command == Command::Scorekeeper && config.names.is_empty() {
+            return Err(CliError::MissingValue("--names".into()));
+        }
+        if config.command == Command::Analyse {
+            let num_players = match &config.analysis.hands {
+                Some(hands) => hands.len(),
+                None => config.seats.len(),
+            };
+            if config.analysis.leader >= num_players {
+                return Err(CliError::InvalidValue {
+                    flag: "--leader".into(),
+                    value: (config.analysis.leader + 1).to_string(),
+                });
+            }
+            if config.analysis.hands.is_none()
+                && !(1..=51 / num_players).contains(&config.analysis.cards)
+            {
+                return Err(CliError::InvalidValue {
+                    flag: "--cards".into(),
+                    value: config.analysis.cards.to_string(),
+                });
+            }
+        }
+        if config.command == Command::Tutorial {
+            config.num_tables = 1;
+            config.advisor = false;
+            config.resume = None;
+        }
+        if !(2..=MAX_PLAYERS).contains(&config.seats.len()) {
+            return Err(CliError::InvalidValue {
+                flag: "--players".into(),
+                value: config.seats.len().to_string(),
+            });
+        }
+
+        Ok(config)
+    }
+
+    /// The program for the `index`th external bot seat at a table.
+    pub fn bot_command(&self, index: usize) -> Option<&str> {
+        self.bot_commands
+            .get(index)
+            .or(self.bot_commands.last())
+            .map(|command| command.as_str())
+    }
+
+    /// The spawn event for the table at `table_index`.
+    pub fn session_event(&self, table_index: usize) -> SpawnSessionEvent {
+        if self.command == Command::Tutorial {
+            return tutorial::session_event();
+        }
+        SpawnSessionEvent {
+            seats: self.seats.clone(),
+            rules: self.rules.clone(),
+            seed: self.seed.map(|seed| seed.wrapping_add(table_index as u64)),
+            dealer: None,
+            decks: Vec::new(),
+            moves: Vec::new(),
+        }
+    }
+}
+
+/// Hands separated by `/`, 2 to 7 of them, all the same size with no card twice.
+fn parse_hands(hands: &str) -> Option<Vec<Vec<Card>>> {
+    let hands = hands
+        .split('/')
+        .map(|hand| {
+            hand.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|card| !card.is_empty())
+                .map(|card| card.parse::<Card>().ok())
+                .collect::<Option<Vec<Card>>>()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let all_cards = hands.iter().flatten().collect_vec();
+    let valid = (2..=MAX_PLAYERS).contains(&hands.len())
+        && hands.iter().map(|hand| hand.len()).all_equal()
+        && !hands[0].is_empty()
+        && all_cards.iter().all_unique();
+    valid.then_some(hands)
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: String) -> Result<T, CliError> {
+    value.parse().map_err(|_| CliError::InvalidValue {
+        flag: flag.to_owned(),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<LaunchConfig, CliError> {
+        LaunchConfig::from_args(args.split_whitespace().map(|arg| arg.to_owned()))
+    }
+
+    fn invalid(flag: &str, value: &str) -> Result<LaunchConfig, CliError> {
+        Err(CliError::InvalidValue {
+            flag: flag.into(),
+            value: value.into(),
+        })
+    }
+
+    #[test]
+    fn no_arguments_play_one_table_of_a_human_and_four_bots() {
+        let config = parse_args("").unwrap();
+        assert_eq!(config, LaunchConfig::default());
+        assert_eq!(config.command, Command::Play);
+        assert_eq!(config.num_tables, 1);
+        assert_eq!(config.seats.len(), 5);
+        assert_eq!(config.seats[0], SeatKind::Human);
+    }
+
+    #[test]
+    fn table_options_are_parsed() {
+        let config =
+            parse_args("--tables 3 --players 4 --seed 42 --rules hook --headless --ranked")
+                .unwrap();
+        assert_eq!(config.num_tables, 3);
+        assert_eq!(config.seats.len(), 4);
+        assert_eq!(config.seed, Some(42));
+        assert_eq!(config.rules, RuleSet { hook: true });
+        assert!(config.headless);
+        assert!(config.ranked);
+        assert!(!config.advisor);
+        assert_eq!(config.session_event(2).seed, Some(44));
+
+        let config = parse_args("--resume save.json").unwrap();
+        assert_eq!(config.resume, Some("save.json".into()));
+        let config = parse_args("tutorial --resume save.json").unwrap();
+        assert_eq!(config.resume, None);
+    }
+
+    #[test]
+    fn seats_and_bot_styles_are_given_in_order() {
+        let config =
+            parse_args("--seats hbbhb --difficulty expert --bots beginner,family:aggressive")
+                .unwrap();
+        let expert = BotStyle {
+            difficulty: BotDifficulty::Expert,
+            personality: Personality::Balanced,
+        };
+        assert_eq!(config.bot_style, expert);
+        let aggressive = BotStyle {
+            difficulty: BotDifficulty::Family,
+            personality: Personality::Aggressive,
+        };
+        assert_eq!(
+            config.seats,
+            vec![
+                SeatKind::Human,
+                SeatKind::Bot(BotStyle::from_name("beginner").unwrap()),
+                SeatKind::Bot(aggressive),
+                SeatKind::Human,
+                SeatKind::Bot(aggressive),
+            ]
+        );
+    }
+
+    #[test]
+    fn commands_route_shared_options() {
+        let config = parse_args("tournament --games 10 --parallel 2 --format json").unwrap();
+        assert_eq!(config.command, Command::Tournament);
+        assert_eq!(config.tournament.games, 10);
+        assert_eq!(config.tournament.tables, 2);
+        assert_eq!(config.tournament.format, OutputFormat::Json);
+
+        let config = parse_args("selfplay --games 10 --strategy greedy").unwrap();
+        assert_eq!(config.command, Command::SelfPlay);
+        assert_eq!(config.dataset.games, 10);
+        assert_eq!(config.dataset.strategy, "greedy");
+        assert_eq!(config.tournament.games, TournamentConfig::default().games);
+
+        let config = parse_args("tutorial --tables 4").unwrap();
+        assert_eq!(config.num_tables, 1);
+    }
+
+    #[test]
+    fn analyse_hands_are_parsed() {
+        let config = parse_args("analyse --trump h --leader 2").unwrap();
+        assert_eq!(config.analysis.trump, Some(Suit::Hearts));
+        assert_eq!(config.analysis.leader, 1);
+
+        let hands = parse_hands("AS 10H/KD,2C").unwrap();
+        assert_eq!(hands.len(), 2);
+        assert_eq!(hands[0][0], "AS".parse().unwrap());
+        assert_eq!(parse_hands("AS/AS"), None);
+        assert_eq!(parse_hands("AS/KD QD"), None);
+        assert_eq!(parse_hands("AS"), None);
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert_eq!(parse_args("--help"), Err(CliError::Help));
+        assert_eq!(
+            parse_args("--tables"),
+            Err(CliError::MissingValue("--tables".into()))
+        );
+        assert_eq!(parse_args("--tables two"), invalid("--tables", "two"));
+        assert_eq!(parse_args("--seats hx"), invalid("--seats", "hx"));
+        assert_eq!(parse_args("--rules house"), invalid("--rules", "house"));
+        assert_eq!(
+            parse_args("--bots expert:timid"),
+            invalid("--bots", "expert:timid")
+        );
+        assert_eq!(parse_args("--turn-time 0"), invalid("--turn-time", "0"));
+        assert_eq!(
+            parse_args("--bot-timeout -1"),
+            invalid("--bot-timeout", "-1")
+        );
+        assert_eq!(
+            parse_args("--players 3 --seats hbbb"),
+            Err(CliError::SeatCountMismatch {
+                players: 3,
+                seats: 4
+            })
+        );
+        assert_eq!(
+            parse_args("--seats hbp"),
+            Err(CliError::MissingValue("--bot".into()))
+        );
+        assert_eq!(
+            parse_args("scorekeeper"),
+            Err(CliError::MissingValue("--names".into()))
+        );
+        assert_eq!(
+            parse_args("tournament --strategies heuristic"),
+            invalid("--strategies", "heuristic")
+        );
+        assert_eq!(
+            parse_args("tournament --parallel 0"),
+            invalid("--parallel", "0")
+        );
+        assert_eq!(parse_args("analyse --leader 0"), invalid("--leader", "0"));
+        assert_eq!(parse_args("analyse --leader 6"), invalid("--leader", "6"));
+        assert_eq!(
+            parse_args("analyse --players 6 --cards 9"),
+            invalid("--cards", "9")
+        );
+    }
+
+    #[test]
+    fn tables_have_two_to_seven_players() {
+        assert_eq!(parse_args("--players 1"), invalid("--players", "1"));
+        assert_eq!(parse_args("--players 7").unwrap().seats.len(), 7);
+        assert_eq!(parse_args("--players 8"), invalid("--players", "8"));
+        assert_eq!(parse_args("--seats hbbbbbbb"), invalid("--players", "8"));
+        assert_eq!(
+            parse_args("selfplay --players 8"),
+            invalid("--players", "8")
+        );
+        assert_eq!(parse_args("--tables 0"), invalid("--tables", "0"));
+    }
+}
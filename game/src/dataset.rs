@@ -0,0 +1,311 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::PathBuf;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Serialize;
+
+use crate::bots::choose_action;
+use crate::bots::strategy_by_name;
+use crate::bots::Strategy;
+use crate::cli::LaunchConfig;
+use crate::game::Action;
+use crate::game::Game;
+use crate::game::SeatView;
+use crate::protocol::PlayedCard;
+use crate::rules::RuleSet;
+use crate::rules::MAX_HAND_SIZE;
+use crate::rules::MAX_PLAYERS;
+use crate::Card;
+
+/// Bumped whenever the records or the [`features`] layout change.
+pub const FEATURES_VERSION: u32 = 1;
+/// Bid and trick slots in [`features`], one for each seat of the biggest table.
+pub const MAX_SEATS: usize = MAX_PLAYERS;
+
+const HAND: usize = 0;
+const TRUMP: usize = HAND + 52;
+const PLAYED: usize = TRUMP + 4;
+const TRICK: usize = PLAYED + 52;
+const SEAT_FROM_DEALER: usize = TRICK + 52;
+const HAND_SIZE: usize = SEAT_FROM_DEALER + 1;
+const NUM_PLAYERS: usize = HAND_SIZE + 1;
+const BIDS: usize = NUM_PLAYERS + 1;
+const TAKEN: usize = BIDS + MAX_SEATS;
+pub const NUM_FEATURES: usize = TAKEN + MAX_SEATS;
+
+/// Bids 0 to 7, then a card by [`Card::index`].
+pub const NUM_ACTIONS: usize = MAX_HAND_SIZE + 1 + 52;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DatasetConfig {
+    pub games: usize,
+    /// The strategy every seat plays.
+    pub strategy: String,
+    pub out: Option<PathBuf>,
+}
+impl Default for DatasetConfig {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            strategy: "heuristic".into(),
+            out: None,
+        }
+    }
+}
+
+/// What a seat could see when it decided, in readable form.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Observation {
+    pub hand: Vec<Card>,
+    pub trump: Option<Card>,
+    /// By seat, `null` until the seat has bid.
+    pub bids: Vec<Option<usize>>,
+    /// Tricks taken so far by seat.
+    pub taken: Vec<usize>,
+    /// Cards of the finished tricks, in the order they were played.
+    pub played: Vec<Card>,
+    pub trick: Vec<PlayedCard>,
+    /// 0 for the dealer, 1 for the seat to their left, and so on.
+    pub seat_from_dealer: usize,
+    pub hand_size: usize,
+    pub num_players: usize,
+}
+
+/// One decision, a line of the dataset.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Record {
+    pub version: u32,
+    pub game: usize,
+    /// Replays the game with [`Game::new`].
+    pub seed: u64,
+    pub round: usize,
+    pub seat: usize,
+    pub observation: Observation,
+    /// See [`features`].
+    pub features: Vec<f32>,
+    /// The legal actions by [`action_index`].
+    pub legal: Vec<usize>,
+    pub action: Action,
+    pub action_index: usize,
+    /// The points the seat scored for the round, filled in once it's over.
+    pub round_score: usize,
+}
+
+/// Where an action sits among the [`NUM_ACTIONS`] outputs of a policy.
+pub fn action_index(action: Action) -> usize {
+    match action {
+        Action::Bid(bid) => bid,
+        Action::Play(card) => MAX_HAND_SIZE + 1 + card.index(),
+    }
+}
+
+pub fn observation(view: &SeatView) -> Observation {
+    let trick: Vec<Card> = view.trick.cards.iter().map(|(_, card)| *card).collect();
+    Observation {
+        hand: view.hand.clone(),
+        trump: view.trump_card,
+        bids: view.bids.clone(),
+        taken: view.taken.clone(),
+        played: view
+            .played
+            .iter()
+            .copied()
+            .filter(|card| !trick.contains(card))
+            .collect(),
+        trick: view
+            .trick
+            .cards
+            .iter()
+            .map(|(seat, card)| PlayedCard {
+                seat: *seat,
+                card: *card,
+            })
+            .collect(),
+        seat_from_dealer: (view.seat + view.num_players - view.dealer) % view.num_players,
+        hand_size: view.hand_size,
+        num_players: view.num_players,
+    }
+}
+
+/// A seat's view as [`NUM_FEATURES`] numbers, version [`FEATURES_VERSION`].
+///
+/// | Offset | Length | Feature |
+/// | --- | --- | --- |
+/// | 0 | 52 | 1 for each card in hand, by [`Card::index`] |
+/// | 52 | 4 | 1 for the trump suit, in [`crate::Suit`] order, all 0 without trump |
+/// | 56 | 52 | 1 for each card of the finished tricks |
+/// | 108 | 52 | 1 for each card of the trick being played |
+/// | 160 | 1 | Seats from the dealer, 0 for the dealer |
+/// | 161 | 1 | Hand size |
+/// | 162 | 1 | Number of players |
+/// | 163 | 7 | Bids clockwise from this seat, -1 before bidding |
+/// | 170 | 7 | Tricks taken clockwise from this seat |
+///
+/// Bid and trick slots past the number of players are -1.
+pub fn features(view: &SeatView) -> Vec<f32> {
+    let observation = observation(view);
+    let mut features = vec![0.0; NUM_FEATURES];
+    for card in observation.hand.iter() {
+        features[HAND + card.index()] = 1.0;
+    }
+    if let Some(card) = observation.trump {
+        features[TRUMP + card.suit as usize] = 1.0;
+    }
+    for card in observation.played.iter() {
+        features[PLAYED + card.index()] = 1.0;
+    }
+    for played in observation.trick.iter() {
+        features[TRICK + played.card.index()] = 1.0;
+    }
+    features[SEAT_FROM_DEALER] = observation.seat_from_dealer as f32;
+    features[HAND_SIZE] = observation.hand_size as f32;
+    features[NUM_PLAYERS] = observation.num_players as f32;
+    for i in 0..MAX_SEATS {
+        let (bid, taken) = match i < view.num_players {
+            true => {
+                let seat = (view.seat + i) % view.num_players;
+                let bid = view.bids[seat].map_or(-1.0, |bid| bid as f32);
+                (bid, view.taken[seat] as f32)
+            }
+            false => (-1.0, -1.0),
+        };
+        features[BIDS + i] = bid;
+        features[TAKEN + i] = taken;
+    }
+    features
+}
+
+/// Play game `game_index` with every seat using `strategy`, recording every decision.
+///
+/// The deal is seeded with `seed + game_index`, so the same arguments always give the same records.
+pub fn self_play(
+    strategy: &dyn Strategy,
+    rules: &RuleSet,
+    num_players: usize,
+    seed: u64,
+    game_index: usize,
+) -> Vec<Record> {
+    let deal_seed = seed.wrapping_add(game_index as u64);
+    let mut game = Game::new(rules.clone(), num_players, deal_seed);
+    let mut rng = StdRng::seed_from_u64(deal_seed);
+    let mut records = Vec::new();
+    let mut round_start = 0;
+    while let Some((seat, action, _)) = choose_action(strategy, &game, &mut rng) {
+        let view = game.round.view(seat);
+        let round = game.round_index;
+        records.push(Record {
+            version: FEATURES_VERSION,
+            game: game_index,
+            seed: deal_seed,
+            round,
+            seat,
+            observation: observation(&view),
+            features: features(&view),
+            legal: game.legal_actions().into_iter().map(action_index).collect(),
+            action,
+            action_index: action_index(action),
+            round_score: 0,
+        });
+        game.apply(seat, action).expect("strategies play legally");
+
+        if game.round_index != round {
+            for record in records[round_start..].iter_mut() {
+                record.round_score = game.results[record.seat][round].points;
+            }
+            round_start = records.len();
+        }
+    }
+    records
+}
+
+/// Write self-play records as NDJSON for `poche selfplay`.
+pub fn run(launch_config: &LaunchConfig) {
+    let config = &launch_config.dataset;
+    let strategy =
+        strategy_by_name(&config.strategy).expect("strategy names are checked by the cli");
+    let out: Box<dyn Write> = match &config.out {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Failed to create {path:?}: {e}");
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut out = BufWriter::new(out);
+    let seed = launch_config.seed.unwrap_or_default();
+    let num_players = launch_config.seats.len();
+
+    let mut written = 0;
+    for game_index in 0..config.games {
+        for record in self_play(
+            &*strategy,
+            &launch_config.rules,
+            num_players,
+            seed,
+            game_index,
+        ) {
+            let line = serde_json::to_string(&record).expect("records are serializable");
+            if let Err(e) = writeln!(out, "{line}") {
+                eprintln!("Failed to write records: {e}");
+                std::process::exit(1);
+            }
+            written += 1;
+        }
+    }
+    if let Err(e) = out.flush() {
+        eprintln!("Failed to write records: {e}");
+        std::process::exit(1);
+    }
+    eprintln!(
+        "Wrote {written} decisions from {} games of {}",
+        config.games, config.strategy
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
+
+    use super::*;
+    use crate::bots::HeuristicStrategy;
+    use crate::properties::games;
+    use crate::properties::no_check;
+    use crate::properties::play_game;
+
+    proptest! {
+        #[test]
+        fn self_play_records_every_decision((num_players, seed, _, rules) in games(), game_index in 0usize..100) {
+            let records = self_play(&HeuristicStrategy, &rules, num_players, seed, game_index);
+            prop_assert_eq!(&records, &self_play(&HeuristicStrategy, &rules, num_players, seed, game_index));
+
+            // Replaying the actions from the record's seed, each record describes the game as it
+            // was, and the game ends in the same scores
+            let mut game = Game::new(rules, num_players, records[0].seed);
+            let mut replayed = records.iter();
+            let choose = |game: &Game, seat: usize| {
+                let record = replayed.next().ok_or_else(|| TestCaseError::fail("records ran out"))?;
+                let view = game.round.view(seat);
+                prop_assert_eq!(record.seat, seat);
+                prop_assert_eq!(record.round, game.round_index);
+                prop_assert_eq!(&record.observation, &observation(&view));
+                prop_assert_eq!(&record.features, &features(&view));
+                let legal = game.legal_actions().into_iter().map(action_index).collect_vec();
+                prop_assert_eq!(&record.legal, &legal);
+                prop_assert_eq!(record.action_index, action_index(record.action));
+                Ok(record.action)
+            };
+            play_game(&mut game, choose, no_check)?;
+            prop_assert!(replayed.next().is_none(), "records left after the game");
+            for record in records.iter() {
+                prop_assert_eq!(record.round_score, game.results[record.seat][record.round].points);
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_rts_camera::RtsCamera;
+
+use crate::seat_camera::SeatCamera;
+use crate::Handles;
+use crate::Session;
+
+/// How far the camera zooms in on a focused table, see [`RtsCamera::target_zoom`].
+const FOCUS_ZOOM: f32 = 0.5;
+
+/// Clicking a table focuses its session, the one the debug keys act on.
+pub struct FocusPlugin;
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FocusedSession>();
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            Update,
+            (
+                keep_focus_on_a_session,
+                handle_table_click,
+                frame_focused_table,
+                update_focus_text,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// The session that session-scoped keys like dealing, shuffling back in and killing act on.
+#[derive(Resource, Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct FocusedSession(pub Option<Entity>);
+
+#[derive(Component)]
+struct FocusText;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+        FocusText,
+        Name::new("Focus Text"),
+    ));
+}
+
+/// Drop the focus when its session is killed, and focus new sessions while nothing else is.
+fn keep_focus_on_a_session(
+    mut focused: ResMut<FocusedSession>,
+    session_query: Query<(), With<Session>>,
+    added_query: Query<Entity, Added<Session>>,
+) {
+    if let Some(session_id) = focused.0
+        && session_query.get(session_id).is_err()
+    {
+        info!("Focused session {session_id:?} is gone");
+        focused.0 = None;
+    }
+    if focused.0.is_none()
+        && let Some(session_id) = added_query.iter().min()
+    {
+        focused.0 = Some(session_id);
+    }
+}
+
+/// Focus the table under the cursor on a left click, unless the click was on the UI.
+fn handle_table_click(
+    buttons: Res<ButtonInput<MouseButton>>,
+    handles: Res<Handles>,
+    mut focused: ResMut<FocusedSession>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<SeatCamera>>,
+    interaction_query: Query<&Interaction>,
+    session_query: Query<(Entity, &Session)>,
+    transform_query: Query<&Transform>,
+) {
+    if !buttons.just_pressed(MouseButton::Left)
+        || interaction_query
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (window_query.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+    let Some(ray) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+    else {
+        return;
+    };
+
+    // Tables all stand on the ground and aren't parented, so their tops share a plane
+    let top = Vec3::Y * handles.table_shape.half_height * 2.0;
+    let Some(hit) = ray
+        .intersect_plane(top, Plane3d::new(Vec3::Y))
+        .map(|distance| ray.get_point(distance))
+    else {
+        return;
+    };
+    let clicked = session_query
+        .iter()
+        .filter_map(|(session_id, session)| {
+            let table = transform_query.get(session.table_id).ok()?;
+            let distance = ((table.translation - hit) * Vec3::new(1.0, 0.0, 1.0)).length();
+            (distance <= handles.table_shape.radius).then_some((session_id, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(session_id, _)| session_id);
+    if let Some(session_id) = clicked
+        && focused.0 != Some(session_id)
+    {
+        info!("Focused session {session_id:?}");
+        focused.0 = Some(session_id);
+    }
+}
+
+/// Move the overview camera over a newly focused table.
+fn frame_focused_table(
+    focused: Res<FocusedSession>,
+    session_query: Query<&Session>,
+    transform_query: Query<&Transform>,
+    mut camera_query: Query<&mut RtsCamera>,
+) {
+    if !focused.is_changed() {
+        return;
+    }
+    let Some(table) = focused
+        .0
+        .and_then(|session_id| session_query.get(session_id).ok())
+        .and_then(|session| transform_query.get(session.table_id).ok())
+    else {
+        return;
+    };
+    for mut camera in camera_query.iter_mut() {
+        // Keep the camera's heading, only where it looks and how close it is change
+        camera.target_focus.translation = table.translation * Vec3::new(1.0, 0.0, 1.0);
+        camera.target_zoom = FOCUS_ZOOM;
+    }
+}
+
+fn update_focus_text(
+    focused: Res<FocusedSession>,
+    session_query: Query<&Session>,
+    mut focus_text_query: Query<&mut Text, With<FocusText>>,
+) {
+    if !focused.is_changed() {
+        return;
+    }
+    let label = match focused
+        .0
+        .and_then(|session_id| session_query.get(session_id).ok())
+    {
+        Some(session) => format!("Focused on table {}", session.number),
+        None => "No table focused, click one".to_owned(),
+    };
+    for mut text in focus_text_query.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
@@ -0,0 +1,613 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bots::Rationale;
+use crate::rules::round_hand_sizes;
+use crate::rules::score;
+use crate::rules::winners;
+use crate::rules::Ledger;
+use crate::rules::RuleError;
+use crate::rules::RuleSet;
+use crate::stats::RoundResult;
+use crate::Card;
+use crate::Suit;
+
+/// A decision made by a seat, written like `{"bid":2}` or `{"play":"10H"}`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Bid(usize),
+    Play(Card),
+}
+
+/// Whose turn it is and what they need to do.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Phase {
+    Bidding { seat: usize },
+    Playing { seat: usize },
+    RoundOver,
+    GameOver,
+}
+impl Phase {
+    /// The seat expected to act, if any.
+    pub fn seat(&self) -> Option<usize> {
+        match self {
+            Phase::Bidding { seat } | Phase::Playing { seat } => Some(*seat),
+            Phase::RoundOver | Phase::GameOver => None,
+        }
+    }
+}
+
+/// Whether `challenger` takes the trick from the `best` card played so far.
+pub fn beats(challenger: Card, best: Card, led: Suit, trump: Option<Suit>) -> bool {
+    let strength = |card: Card| {
+        let tier = match card.suit {
+            suit if Some(suit) == trump => 2,
+            suit if suit == led => 1,
+            _ => 0,
+        };
+        (tier, card.rank.value())
+    };
+    strength(challenger) > strength(best)
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct Trick {
+    pub leader: usize,
+    /// Cards in the order they were played, with the seat that played them.
+    pub cards: Vec<(usize, Card)>,
+}
+impl Trick {
+    pub fn led_suit(&self) -> Option<Suit> {
+        self.cards.first().map(|(_, card)| card.suit)
+    }
+
+    /// The seat and card currently taking the trick.
+    pub fn winner(&self, trump: Option<Suit>) -> Option<(usize, Card)> {
+        let led = self.led_suit()?;
+        self.cards.iter().copied().reduce(|best, challenger| {
+            match beats(challenger.1, best.1, led, trump) {
+                true => challenger,
+                false => best,
+            }
+        })
+    }
+}
+
+/// One deal: bidding then playing a trick per card in hand.
+///
+/// Seats are numbered clockwise, so the seat to the left of `s` is `s + 1`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Round {
+    pub num_players: usize,
+    pub hand_size: usize,
+    pub dealer: usize,
+    pub hands: Vec<Vec<Card>>,
+    /// The card revealed after dealing, its suit is trump.
+    pub trump_card: Option<Card>,
+    pub bids: Vec<Option<usize>>,
+    pub taken: Vec<usize>,
+    pub tricks: Vec<Trick>,
+    pub trick: Trick,
+}
+
+impl Round {
+    /// Deal `hand_size` cards to each seat clockwise from the dealer's left.
+    ///
+    /// The top of the deck is the end of the `Vec`. The next card is revealed as trump.
+    pub fn deal(deck: &mut Vec<Card>, num_players: usize, hand_size: usize, dealer: usize) -> Self {
+        let mut hands = vec![Vec::with_capacity(hand_size); num_players];
+        for _ in 0..hand_size {
+            for i in 1..=num_players {
+                let card = deck.pop().expect("deck should cover every hand");
+                hands[(dealer + i) % num_players].push(card);
+            }
+        }
+        let trump_card = deck.pop();
+        Self::from_hands(hands, trump_card, dealer)
+    }
+
+    /// Start a round from known hands, e.g. for analysis or scripted deals.
+    pub fn from_hands(hands: Vec<Vec<Card>>, trump_card: Option<Card>, dealer: usize) -> Self {
+        let num_players = hands.len();
+        let hand_size = hands.first().map_or(0, |hand| hand.len());
+        Self {
+            num_players,
+            hand_size,
+            dealer,
+            hands,
+            trump_card,
+            bids: vec![None; num_players],
+            taken: vec![0; num_players],
+            tricks: Vec::new(),
+            trick: Trick {
+                leader: (dealer + 1) % num_players,
+                cards: Vec::new(),
+            },
+        }
+    }
+
+    pub fn trump(&self) -> Option<Suit> {
+        self.trump_card.map(|card| card.suit)
+    }
+
+    /// Seats in bidding order, starting left of the dealer and ending with the dealer.
+    pub fn bidding_order(&self) -> impl Iterator<Item = usize> + '_ {
+        (1..=self.num_players).map(|i| (self.dealer + i) % self.num_players)
+    }
+
+    /// Bids made so far, in bidding order.
+    pub fn bids_in_order(&self) -> Vec<usize> {
+        self.bidding_order()
+            .map_while(|seat| self.bids[seat])
+            .collect()
+    }
+
+    pub fn phase(&self) -> Phase {
+        if let Some(seat) = self.bidding_order().find(|seat| self.bids[*seat].is_none()) {
+            return Phase::Bidding { seat };
+        }
+        if self.tricks.len() >= self.hand_size {
+            return Phase::RoundOver;
+        }
+        let seat = match self.trick.cards.last() {
+            Some((last, _)) => (last + 1) % self.num_players,
+            None => self.trick.leader,
+        };
+        Phase::Playing { seat }
+    }
+
+    pub fn legal_bids(&self, rules: &RuleSet) -> Vec<usize> {
+        let bids = self.bids_in_order();
+        (0..=self.hand_size)
+            .filter(|bid| {
+                rules
+                    .validate_bid(*bid, self.hand_size, &bids, self.num_players)
+                    .is_ok()
+            })
+            .collect()
+    }
+
+    pub fn legal_plays(&self, seat: usize) -> Vec<Card> {
+        legal_plays(&self.hands[seat], &self.trick)
+    }
+
+    pub fn bid(&mut self, seat: usize, bid: usize, rules: &RuleSet) -> Result<(), RuleError> {
+        match self.phase() {
+            Phase::Bidding { seat: expected } if expected == seat => {}
+            Phase::Bidding { .. } => return Err(RuleError::NotYourTurn { seat }),
+            _ => return Err(RuleError::NotBidding),
+        }
+        rules.validate_bid(bid, self.hand_size, &self.bids_in_order(), self.num_players)?;
+        self.bids[seat] = Some(bid);
+        Ok(())
+    }
+
+    /// Play a card, returning the seat that took the trick if this card completed it.
+    pub fn play(&mut self, seat: usize, card: Card) -> Result<Option<usize>, RuleError> {
+        match self.phase() {
+            Phase::Playing { seat: expected } if expected == seat => {}
+            Phase::Playing { .. } => return Err(RuleError::NotYourTurn { seat }),
+            _ => return Err(RuleError::NotPlaying),
+        }
+        if !self.hands[seat].contains(&card) {
+            return Err(RuleError::CardNotInHand(card));
+        }
+        if !self.legal_plays(seat).contains(&card) {
+            let led = self.trick.led_suit().expect("only followers can revoke");
+            return Err(RuleError::MustFollowSuit(led));
+        }
+
+        self.hands[seat].retain(|c| *c != card);
+        self.trick.cards.push((seat, card));
+        if self.trick.cards.len() < self.num_players {
+            return Ok(None);
+        }
+
+        let (winner, _) = self
+            .trick
+            .winner(self.trump())
+            .expect("a full trick has a winner");
+        self.taken[winner] += 1;
+        let trick = std::mem::replace(
+            &mut self.trick,
+            Trick {
+                leader: winner,
+                cards: Vec::new(),
+            },
+        );
+        self.tricks.push(trick);
+        Ok(Some(winner))
+    }
+
+    pub fn apply(&mut self, seat: usize, action: Action, rules: &RuleSet) -> Result<(), RuleError> {
+        match action {
+            Action::Bid(bid) => self.bid(seat, bid, rules),
+            Action::Play(card) => self.play(seat, card).map(|_| ()),
+        }
+    }
+
+    /// How each seat did, once every trick has been taken.
+    pub fn results(&self) -> Vec<RoundResult> {
+        (0..self.num_players)
+            .map(|seat| {
+                let bid = self.bids[seat].unwrap_or_default();
+                let taken = self.taken[seat];
+                RoundResult {
+                    hand_size: self.hand_size,
+                    bid,
+                    taken,
+                    points: score(bid, taken, self.hand_size),
+                }
+            })
+            .collect()
+    }
+
+    /// Everything `seat` is allowed to know.
+    pub fn view(&self, seat: usize) -> SeatView {
+        SeatView {
+            seat,
+            num_players: self.num_players,
+            dealer: self.dealer,
+            hand_size: self.hand_size,
+            hand: self.hands[seat].clone(),
+            trump_card: self.trump_card,
+            bids: self.bids.clone(),
+            taken: self.taken.clone(),
+            trick: self.trick.clone(),
+            played: self
+                .tricks
+                .iter()
+                .chain(std::iter::once(&self.trick))
+                .flat_map(|trick| trick.cards.iter().map(|(_, card)| *card))
+                .collect(),
+        }
+    }
+}
+
+/// Cards of `hand` that follow the led suit, or the whole hand if none do.
+fn legal_plays(hand: &[Card], trick: &Trick) -> Vec<Card> {
+    let following = trick
+        .led_suit()
+        .map(|led| {
+            hand.iter()
+                .copied()
+                .filter(|card| card.suit == led)
+                .collect_vec()
+        })
+        .unwrap_or_default();
+    match following.is_empty() {
+        true => hand.to_vec(),
+        false => following,
+    }
+}
+
+/// A seat's view of the round, the input to bots and advisors.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SeatView {
+    pub seat: usize,
+    pub num_players: usize,
+    pub dealer: usize,
+    pub hand_size: usize,
+    pub hand: Vec<Card>,
+    pub trump_card: Option<Card>,
+    pub bids: Vec<Option<usize>>,
+    pub taken: Vec<usize>,
+    pub trick: Trick,
+    /// Every card played this round, including the current trick.
+    pub played: Vec<Card>,
+}
+impl SeatView {
+    pub fn trump(&self) -> Option<Suit> {
+        self.trump_card.map(|card| card.suit)
+    }
+
+    /// Whether `card` would take the trick as it stands.
+    pub fn would_win(&self, card: Card) -> bool {
+        match (self.trick.led_suit(), self.trick.winner(self.trump())) {
+            (Some(led), Some((_, best))) => beats(card, best, led, self.trump()),
+            _ => true,
+        }
+    }
+}
+
+/// Deal single cards to the contenders, high card deals, ties draw again.
+pub fn choose_dealer(num_players: usize, rng: &mut StdRng) -> usize {
+    let mut contenders = (0..num_players).collect_vec();
+    let mut deck = Vec::new();
+    while contenders.len() > 1 {
+        if deck.len() < contenders.len() {
+            deck = Card::get_new_deck();
+            deck.shuffle(rng);
+        }
+        let draws = contenders
+            .iter()
+            .map(|seat| (*seat, deck.pop().expect("deck was refilled").rank.value()))
+            .collect_vec();
+        let high = draws
+            .iter()
+            .map(|(_, value)| *value)
+            .max()
+            .unwrap_or_default();
+        contenders = draws
+            .into_iter()
+            .filter(|(_, value)| *value == high)
+            .map(|(seat, _)| seat)
+            .collect();
+    }
+    contenders[0]
+}
+
+/// A deck that deals `hands` by seat and turns up `trump_card`, the other cards under them.
+pub fn stacked_deck(hands: &[Vec<Card>], trump_card: Card, dealer: usize) -> Vec<Card> {
+    let num_players = hands.len();
+    let hand_size = hands.first().map_or(0, |hand| hand.len());
+    let dealt: Vec<Card> = (0..hand_size)
+        .flat_map(|i| (1..=num_players).map(move |offset| (i, (dealer + offset) % num_players)))
+        .map(|(i, seat)| hands[seat][i])
+        .chain(std::iter::once(trump_card))
+        .collect();
+
+    let mut deck = Card::get_new_deck();
+    deck.retain(|card| !dealt.contains(card));
+    // Dealing takes from the end
+    deck.extend(dealt.into_iter().rev());
+    deck
+}
+
+/// A whole game of rounds 1..7..1, with the dealer rotating left and money changing hands.
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub rules: RuleSet,
+    pub num_players: usize,
+    pub hand_sizes: Vec<usize>,
+    pub round_index: usize,
+    pub round: Round,
+    /// Finished rounds by seat.
+    pub results: Vec<Vec<RoundResult>>,
+    pub ledger: Ledger,
+    /// Every action applied so far, in order.
+    pub log: Vec<(usize, Action)>,
+    /// Why bots took some of the actions, by index into [`Game::log`].
+    pub rationales: BTreeMap<usize, Rationale>,
+    first_dealer: usize,
+    /// The shuffler as it was before the first deal, so the game can be replayed.
+    first_rng: StdRng,
+    /// Stacked decks for the first rounds.
+    decks: Vec<Vec<Card>>,
+    rng: StdRng,
+}
+
+impl Game {
+    pub fn new(rules: RuleSet, num_players: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let dealer = choose_dealer(num_players, &mut rng);
+        Self::start(rules, num_players, dealer, rng, Vec::new())
+    }
+
+    /// A game whose first dealer was already chosen, e.g. by drawing cards at the table.
+    ///
+    /// The first rounds are dealt from `decks` in order, e.g. for a tutorial, the rest are shuffled.
+    pub fn with_decks(
+        rules: RuleSet,
+        num_players: usize,
+        dealer: usize,
+        seed: u64,
+        decks: Vec<Vec<Card>>,
+    ) -> Self {
+        Self::start(
+            rules,
+            num_players,
+            dealer,
+            StdRng::seed_from_u64(seed),
+            decks,
+        )
+    }
+
+    /// The same game back at its first deal, ready to replay [`Game::log`] onto.
+    pub fn restart(&self) -> Self {
+        Self::start(
+            self.rules.clone(),
+            self.num_players,
+            self.first_dealer,
+            self.first_rng.clone(),
+            self.decks.clone(),
+        )
+    }
+
+    fn start(
+        rules: RuleSet,
+        num_players: usize,
+        dealer: usize,
+        mut rng: StdRng,
+        decks: Vec<Vec<Card>>,
+    ) -> Self {
+        let first_rng = rng.clone();
+        let hand_sizes = round_hand_sizes(num_players);
+        let mut deck = match decks.first() {
+            Some(deck) => deck.clone(),
+            None => {
+                let mut deck = Card::get_new_deck();
+                deck.shuffle(&mut rng);
+                deck
+            }
+        };
+        let round = Round::deal(&mut deck, num_players, hand_sizes[0], dealer);
+        Self {
+            rules,
+            num_players,
+            hand_sizes,
+            round_index: 0,
+            round,
+            results: vec![Vec::new(); num_players],
+            ledger: Ledger::new(num_players),
+            log: Vec::new(),
+            rationales: BTreeMap::new(),
+            first_dealer: dealer,
+            first_rng,
+            decks,
+            rng,
+        }
+    }
+
+    /// The seat that dealt the first round.
+    pub fn first_dealer(&self) -> usize {
+        self.first_dealer
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self.round_index >= self.hand_sizes.len() {
+            true => Phase::GameOver,
+            false => self.round.phase(),
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.phase() == Phase::GameOver
+    }
+
+    pub fn legal_actions(&self) -> Vec<Action> {
+        match self.phase() {
+            Phase::Bidding { .. } => self
+                .round
+                .legal_bids(&self.rules)
+                .into_iter()
+                .map(Action::Bid)
+                .collect(),
+            Phase::Playing { seat } => self
+                .round
+                .legal_plays(seat)
+                .into_iter()
+                .map(Action::Play)
+                .collect(),
+            Phase::RoundOver | Phase::GameOver => Vec::new(),
+        }
+    }
+
+    /// Apply an action, settling the round and dealing the next once the last trick is taken.
+    pub fn apply(&mut self, seat: usize, action: Action) -> Result<(), RuleError> {
+        if self.is_over() {
+            return Err(RuleError::NotPlaying);
+        }
+        self.round.apply(seat, action, &self.rules)?;
+        self.log.push((seat, action));
+        if self.round.phase() == Phase::RoundOver {
+            self.finish_round();
+        }
+        Ok(())
+    }
+
+    /// Record why the last action was taken.
+    pub fn explain_last(&mut self, rationale: Rationale) {
+        if let Some(index) = self.log.len().checked_sub(1) {
+            self.rationales.insert(index, rationale);
+        }
+    }
+
+    fn finish_round(&mut self) {
+        for (seat, result) in self.round.results().into_iter().enumerate() {
+            if !result.made() {
+                self.ledger.pay_poche(seat);
+            }
+            self.results[seat].push(result);
+        }
+
+        self.round_index += 1;
+        if let Some(hand_size) = self.hand_sizes.get(self.round_index) {
+            let dealer = (self.round.dealer + 1) % self.num_players;
+            let mut deck = match self.decks.get(self.round_index) {
+                Some(deck) => deck.clone(),
+                None => {
+                    let mut deck = Card::get_new_deck();
+                    deck.shuffle(&mut self.rng);
+                    deck
+                }
+            };
+            self.round = Round::deal(&mut deck, self.num_players, *hand_size, dealer);
+            return;
+        }
+
+        self.ledger.pay_winners(&self.winners());
+    }
+
+    pub fn points(&self, seat: usize) -> usize {
+        self.results[seat].iter().map(|result| result.points).sum()
+    }
+
+    /// The seats with the most points, more than one when tied.
+    pub fn winners(&self) -> Vec<usize> {
+        winners(
+            &(0..self.num_players)
+                .map(|seat| self.points(seat))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
+
+    use super::*;
+    use crate::bots::Intent;
+    use crate::properties::games;
+    use crate::properties::play_randomly;
+
+    proptest! {
+        #[test]
+        fn replaying_the_log_reproduces_the_game((num_players, seed, moves_seed, rules) in games()) {
+            let mut game = Game::new(rules, num_players, seed);
+            play_randomly(&mut game, moves_seed, |_, _, _, _| Ok(()))?;
+
+            let mut replay = game.restart();
+            for (seat, action) in game.log.iter().copied() {
+                replay.apply(seat, action)
+                    .map_err(|e| TestCaseError::fail(format!("replaying {action:?} failed: {e}")))?;
+            }
+            prop_assert_eq!(&replay.results, &game.results);
+            prop_assert_eq!(&replay.ledger, &game.ledger);
+        }
+
+        #[test]
+        fn stacked_decks_deal_their_hands((num_players, seed, _, _) in games(), hand_size in 1usize..=7, dealer in 0usize..7) {
+            let dealer = dealer % num_players;
+            let mut deck = Card::get_new_deck();
+            deck.shuffle(&mut StdRng::seed_from_u64(seed));
+            let round = Round::deal(&mut deck, num_players, hand_size, dealer);
+            let trump_card = round.trump_card.expect("cards are left for trump");
+
+            let mut stacked = stacked_deck(&round.hands, trump_card, dealer);
+            prop_assert_eq!(stacked.len(), 52);
+            prop_assert_eq!(Round::deal(&mut stacked, num_players, hand_size, dealer), round);
+        }
+    }
+
+    #[test]
+    fn an_explanation_goes_with_the_move_just_made() {
+        let mut game = Game::new(RuleSet::default(), 3, 0);
+        let rationale = Rationale {
+            counted: Vec::new(),
+            estimate: 0.0,
+            needed: None,
+            intent: Intent::Estimate,
+        };
+        game.explain_last(rationale.clone());
+        assert!(game.rationales.is_empty());
+
+        for _ in 0..2 {
+            let seat = game.phase().seat().expect("a seat to act");
+            game.apply(seat, game.legal_actions()[0])
+                .expect("legal actions are legal");
+        }
+        game.explain_last(rationale.clone());
+        assert_eq!(game.rationales.len(), 1);
+        assert_eq!(game.rationales.get(&1), Some(&rationale));
+    }
+}
@@ -0,0 +1,477 @@
+use std::time::SystemTime;
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_inspector_egui::bevy_egui::EguiClipboard;
+use itertools::Itertools;
+
+use crate::bots::Rationale;
+use crate::cli::LaunchConfig;
+use crate::game::Action;
+use crate::game::Game;
+use crate::rules::ANTE;
+use crate::rules::POCHE_PENALTY;
+
+/// Lines of the log shown at once.
+const VISIBLE_LINES: usize = 14;
+
+/// A scrolling panel of what happened at the tables.
+///
+/// L hides and shows it, PageUp and PageDown scroll it, [ and ] pick the table, F1 to F9 hide
+/// and show each kind of event, C copies what's shown and O saves it.
+pub struct GameLogPlugin;
+impl Plugin for GameLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameLog>();
+        app.init_resource::<GameLogFilter>();
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            Update,
+            (
+                handle_game_log_key_presses,
+                export_game_log,
+                update_game_log_text,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum LogKind {
+    Deal,
+    Trump,
+    Bid,
+    Play,
+    Trick,
+    Score,
+    Poche,
+    Coins,
+    Undo,
+}
+
+/// In the order of their F keys.
+pub const LOG_KINDS: [LogKind; 9] = [
+    LogKind::Deal,
+    LogKind::Trump,
+    LogKind::Bid,
+    LogKind::Play,
+    LogKind::Trick,
+    LogKind::Score,
+    LogKind::Poche,
+    LogKind::Coins,
+    LogKind::Undo,
+];
+
+impl LogKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogKind::Deal => "deals",
+            LogKind::Trump => "trumps",
+            LogKind::Bid => "bids",
+            LogKind::Play => "plays",
+            LogKind::Trick => "tricks",
+            LogKind::Score => "scores",
+            LogKind::Poche => "poches",
+            LogKind::Coins => "coins",
+            LogKind::Undo => "take backs",
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct LogEntry {
+    pub session_id: Entity,
+    pub kind: LogKind,
+    pub text: String,
+}
+
+/// Everything that happened at the tables, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct GameLog {
+    pub entries: Vec<LogEntry>,
+}
+impl GameLog {
+    pub fn push(&mut self, session_id: Entity, kind: LogKind, text: String) {
+        self.entries.push(LogEntry {
+            session_id,
+            kind,
+            text,
+        });
+    }
+
+    pub fn extend(&mut self, session_id: Entity, events: Vec<(LogKind, String)>) {
+        for (kind, text) in events {
+            self.push(session_id, kind, text);
+        }
+    }
+}
+
+/// Which entries the panel shows, and what gets copied and saved.
+#[derive(Resource, Debug, Default)]
+pub struct GameLogFilter {
+    /// Only this table, or every table when `None`.
+    pub session_id: Option<Entity>,
+    pub hidden_kinds: HashSet<LogKind>,
+}
+impl GameLogFilter {
+    pub fn shows(&self, entry: &LogEntry) -> bool {
+        self.session_id.iter().all(|id| *id == entry.session_id)
+            && !self.hidden_kinds.contains(&entry.kind)
+    }
+
+    /// The shown entries as text, a line each.
+    pub fn render(&self, game_log: &GameLog) -> String {
+        game_log
+            .entries
+            .iter()
+            .filter(|entry| self.shows(entry))
+            .map(|entry| format!("[{}] {}", entry.session_id.index(), entry.text))
+            .join("\n")
+    }
+}
+
+/// Like `25¢` or `$1.25`.
+fn money(cents: usize) -> String {
+    match cents < 100 {
+        true => format!("{cents}¢"),
+        false => format!("${:.2}", cents as f32 / 100.0),
+    }
+}
+
+/// The ante, when the game has just started.
+pub fn describe_start(game: &Game) -> Vec<(LogKind, String)> {
+    let mut events = vec![(
+        LogKind::Coins,
+        format!(
+            "Everyone antes {}, the pot is {}",
+            money(ANTE.value()),
+            money(game.ledger.pot_cents)
+        ),
+    )];
+    events.extend(describe_deal(game));
+    events
+}
+
+/// The deal and trump of the round being played.
+pub fn describe_deal(game: &Game) -> Vec<(LogKind, String)> {
+    let round = &game.round;
+    let trump = match round.trump_card {
+        Some(card) => format!("{card} is turned up, {:?} are trump", card.suit),
+        None => "No card left to turn up, there's no trump".to_owned(),
+    };
+    vec![
+        (
+            LogKind::Deal,
+            format!(
+                "Round {}: seat {} deals {} cards each",
+                game.round_index + 1,
+                round.dealer + 1,
+                round.hand_size
+            ),
+        ),
+        (LogKind::Trump, trump),
+    ]
+}
+
+/// What `seat` taking `action` did, going from `before` to `after`.
+pub fn describe_action(
+    before: &Game,
+    seat: usize,
+    action: Action,
+    rationale: Option<&Rationale>,
+    after: &Game,
+) -> Vec<(LogKind, String)> {
+    let because = rationale.map_or(String::new(), |rationale| format!(": {rationale}"));
+    let mut events = match action {
+        Action::Bid(bid) => vec![(
+            LogKind::Bid,
+            format!("Seat {} bids {bid}{because}", seat + 1),
+        )],
+        Action::Play(card) => vec![(
+            LogKind::Play,
+            format!("Seat {} plays {card}{because}", seat + 1),
+        )],
+    };
+
+    let round = &before.round;
+    if let Action::Play(card) = action
+        && round.trick.cards.len() + 1 == round.num_players
+    {
+        let mut trick = round.trick.clone();
+        trick.cards.push((seat, card));
+        if let Some((winner, best)) = trick.winner(round.trump()) {
+            events.push((
+                LogKind::Trick,
+                format!("Seat {} takes the trick with {best}", winner + 1),
+            ));
+        }
+    }
+
+    if after.round_index == before.round_index {
+        return events;
+    }
+    let mut poches = 0;
+    for seat in 0..after.num_players {
+        let result = &after.results[seat][before.round_index];
+        match result.made() {
+            true => events.push((
+                LogKind::Score,
+                format!(
+                    "Seat {} makes {}, scoring {}",
+                    seat + 1,
+                    result.bid,
+                    result.points
+                ),
+            )),
+            false => {
+                poches += 1;
+                events.push((
+                    LogKind::Poche,
+                    format!(
+                        "Seat {} poches, bid {} and took {}",
+                        seat + 1,
+                        result.bid,
+                        result.taken
+                    ),
+                ));
+                events.push((
+                    LogKind::Coins,
+                    format!(
+                        "Seat {} pays {} into the pot",
+                        seat + 1,
+                        money(POCHE_PENALTY.value())
+                    ),
+                ));
+            }
+        }
+    }
+    match after.is_over() {
+        true => {
+            let pot = before.ledger.pot_cents + poches * POCHE_PENALTY.value();
+            let winners = after.winners();
+            let share = (pot - after.ledger.pot_cents) / winners.len().max(1);
+            for winner in winners {
+                events.push((
+                    LogKind::Coins,
+                    format!(
+                        "Seat {} wins with {} points and takes {} from the pot",
+                        winner + 1,
+                        after.points(winner),
+                        money(share)
+                    ),
+                ));
+            }
+        }
+        false => events.extend(describe_deal(after)),
+    }
+    events
+}
+
+#[derive(Component)]
+struct GameLogText;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 16.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            max_width: Val::Percent(45.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+        GameLogText,
+        Name::new("Game Log"),
+    ));
+}
+
+fn handle_game_log_key_presses(
+    input: Res<ButtonInput<KeyCode>>,
+    game_log: Res<GameLog>,
+    mut filter: ResMut<GameLogFilter>,
+) {
+    let step = match (
+        input.just_pressed(KeyCode::BracketLeft),
+        input.just_pressed(KeyCode::BracketRight),
+    ) {
+        (true, false) => Some(-1),
+        (false, true) => Some(1),
+        _ => None,
+    };
+    if let Some(step) = step {
+        // Every table, then each table that has logged something
+        let choices = std::iter::once(None)
+            .chain(
+                game_log
+                    .entries
+                    .iter()
+                    .map(|entry| Some(entry.session_id))
+                    .unique(),
+            )
+            .collect_vec();
+        let current = choices
+            .iter()
+            .position(|choice| *choice == filter.session_id)
+            .unwrap_or_default() as isize;
+        let next = (current + step).rem_euclid(choices.len() as isize);
+        filter.session_id = choices[next as usize];
+    }
+
+    let keys = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+    ];
+    for (key, kind) in keys.into_iter().zip(LOG_KINDS) {
+        if input.just_pressed(key) && !filter.hidden_kinds.remove(&kind) {
+            filter.hidden_kinds.insert(kind);
+        }
+    }
+}
+
+/// Copy what the panel shows to the clipboard, or save it to a file.
+fn export_game_log(
+    input: Res<ButtonInput<KeyCode>>,
+    launch_config: Res<LaunchConfig>,
+    game_log: Res<GameLog>,
+    filter: Res<GameLogFilter>,
+    clipboard: Option<ResMut<EguiClipboard>>,
+) {
+    if input.just_pressed(KeyCode::KeyC) {
+        match clipboard {
+            Some(mut clipboard) => {
+                clipboard.set_contents(&filter.render(&game_log));
+                info!("Copied the game log");
+            }
+            None => warn!("No clipboard to copy the game log to"),
+        }
+    }
+
+    if input.just_pressed(KeyCode::KeyO) {
+        let secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = launch_config.logs.join(format!("log-{secs}.txt"));
+        let saved = std::fs::create_dir_all(&launch_config.logs)
+            .and_then(|()| std::fs::write(&path, filter.render(&game_log)));
+        match saved {
+            Ok(()) => info!("Saved the game log to {path:?}"),
+            Err(e) => warn!("Failed to save the game log to {path:?}: {e}"),
+        }
+    }
+}
+
+/// Show the latest entries that pass the filter, or older ones when scrolled back.
+fn update_game_log_text(
+    input: Res<ButtonInput<KeyCode>>,
+    game_log: Res<GameLog>,
+    filter: Res<GameLogFilter>,
+    mut hidden: Local<bool>,
+    // Entries back from the latest
+    mut scroll: Local<usize>,
+    mut game_log_text_query: Query<(&mut Text, &mut Visibility), With<GameLogText>>,
+) {
+    let mut changed = game_log.is_changed() || filter.is_changed();
+    if input.just_pressed(KeyCode::KeyL) {
+        *hidden = !*hidden;
+        changed = true;
+    }
+    let shown = game_log
+        .entries
+        .iter()
+        .filter(|entry| filter.shows(entry))
+        .collect_vec();
+    let max_scroll = shown.len().saturating_sub(VISIBLE_LINES);
+    if input.just_pressed(KeyCode::PageUp) {
+        *scroll = (*scroll + VISIBLE_LINES / 2).min(max_scroll);
+        changed = true;
+    }
+    if input.just_pressed(KeyCode::PageDown) {
+        *scroll = scroll.saturating_sub(VISIBLE_LINES / 2);
+        changed = true;
+    }
+    if !changed {
+        return;
+    }
+
+    let table = match filter.session_id {
+        Some(session_id) => format!("table {}", session_id.index()),
+        None => "every table".to_owned(),
+    };
+    let hidden_kinds = LOG_KINDS
+        .iter()
+        .filter(|kind| filter.hidden_kinds.contains(*kind))
+        .map(|kind| kind.label())
+        .join(", ");
+    let mut lines = vec![match hidden_kinds.is_empty() {
+        true => format!("Log of {table}"),
+        false => format!("Log of {table}, hiding {hidden_kinds}"),
+    }];
+    let end = shown.len() - (*scroll).min(max_scroll);
+    let start = end.saturating_sub(VISIBLE_LINES);
+    if start > 0 {
+        lines.push(format!("... {start} earlier, PageUp to scroll"));
+    }
+    lines.extend(
+        shown[start..end]
+            .iter()
+            .map(|entry| format!("[{}] {}", entry.session_id.index(), entry.text)),
+    );
+    for (mut text, mut visibility) in game_log_text_query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+        *visibility = match *hidden || game_log.entries.is_empty() {
+            true => Visibility::Hidden,
+            false => Visibility::Visible,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::properties::games;
+    use crate::properties::play_randomly;
+
+    proptest! {
+        #[test]
+        fn the_game_log_tells_the_whole_game((num_players, seed, moves_seed, rules) in games()) {
+            let mut game = Game::new(rules, num_players, seed);
+            let mut events = describe_start(&game);
+            play_randomly(&mut game, moves_seed, |before, seat, action, after| {
+                events.extend(describe_action(before, seat, action, None, after));
+                Ok(())
+            })?;
+            let count = |kind| events.iter().filter(|(other, _)| *other == kind).count();
+            let rounds = game.hand_sizes.len();
+            prop_assert_eq!(count(LogKind::Deal), rounds);
+            prop_assert_eq!(count(LogKind::Trump), rounds);
+            prop_assert_eq!(count(LogKind::Bid) + count(LogKind::Play), game.log.len());
+            prop_assert_eq!(count(LogKind::Trick), game.hand_sizes.iter().sum::<usize>());
+            prop_assert_eq!(count(LogKind::Score) + count(LogKind::Poche), rounds * num_players);
+            // The ante, a payment per poche and a share per winner
+            prop_assert_eq!(
+                count(LogKind::Coins),
+                1 + count(LogKind::Poche) + game.winners().len()
+            );
+        }
+    }
+}
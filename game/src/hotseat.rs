@@ -0,0 +1,264 @@
+use bevy::prelude::*;
+use bevy_rts_camera::RtsCamera;
+use itertools::Itertools;
+
+use crate::play::TableGame;
+use crate::profiles::ProfileId;
+use crate::profiles::Profiles;
+use crate::Card;
+use crate::CardLocation;
+use crate::Handles;
+use crate::SeatKind;
+use crate::Session;
+
+/// How far the camera zooms in on a seat, see [`RtsCamera::target_zoom`].
+const SEAT_ZOOM: f32 = 0.7;
+
+/// Tables with several humans sharing the screen.
+///
+/// Between human turns every hand is hidden behind a "pass the device" screen. Once the next
+/// human clicks ready, the camera turns to their seat and only their cards are face up.
+pub struct HotSeatPlugin;
+impl Plugin for HotSeatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            Update,
+            (
+                mark_hot_seat_tables,
+                hide_hands_between_turns,
+                update_privacy_screen,
+                handle_ready_button,
+            )
+                .chain(),
+        );
+        // After the cards have moved for the frame, so no card shows for a frame in the wrong hand
+        app.add_systems(PostUpdate, show_hands);
+    }
+}
+
+/// On a session with more than one human seat.
+#[derive(Component, Debug, Eq, PartialEq, Clone, Default)]
+pub struct HotSeat {
+    /// The human seat whose cards are face up, `None` while every hand is hidden.
+    pub showing: Option<usize>,
+}
+impl HotSeat {
+    /// Whether `seat` is the one at the screen, always true away from hot-seat tables.
+    pub fn lets_act(hot_seat: Option<&HotSeat>, seat: usize) -> bool {
+        hot_seat
+            .iter()
+            .all(|hot_seat| hot_seat.showing == Some(seat))
+    }
+}
+
+#[derive(Component)]
+struct PrivacyScreen;
+
+#[derive(Component)]
+struct PrivacyScreenText;
+
+/// The session and seat the privacy screen is waiting on.
+#[derive(Component, Default)]
+struct ReadyButton {
+    waiting: Option<(Entity, usize)>,
+}
+
+fn setup(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.1).into(),
+                // Above the other panels
+                z_index: ZIndex::Global(10),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            PrivacyScreen,
+            Name::new("Privacy Screen"),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: default(),
+                        font_size: 36.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                PrivacyScreenText,
+            ));
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(24.0), Val::Px(12.0)),
+                            ..default()
+                        },
+                        background_color: Color::DARK_GREEN.into(),
+                        ..default()
+                    },
+                    ReadyButton::default(),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Ready",
+                        TextStyle {
+                            font: default(),
+                            font_size: 28.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        });
+}
+
+fn mark_hot_seat_tables(
+    mut commands: Commands,
+    game_query: Query<(Entity, &TableGame), Added<TableGame>>,
+    seat_kind_query: Query<&SeatKind>,
+) {
+    for (session_id, table_game) in game_query.iter() {
+        let humans = seat_kind_query
+            .iter_many(&table_game.seats)
+            .filter(|kind| **kind == SeatKind::Human)
+            .count();
+        if humans > 1 {
+            info!("Session {session_id:?} is hot-seat with {humans} humans");
+            commands.entity(session_id).insert(HotSeat::default());
+        }
+    }
+}
+
+/// Hide the hands as soon as the seat at the screen is done.
+fn hide_hands_between_turns(mut game_query: Query<(&TableGame, &mut HotSeat)>) {
+    for (table_game, mut hot_seat) in game_query.iter_mut() {
+        let to_act = table_game.to_act().map(|(seat, _)| seat);
+        if hot_seat.showing.is_some() && hot_seat.showing != to_act {
+            hot_seat.showing = None;
+        }
+    }
+}
+
+/// Cover the screen while a human is next but their hand isn't showing yet.
+fn update_privacy_screen(
+    game_query: Query<(Entity, &TableGame, &HotSeat)>,
+    player_query: Query<(&SeatKind, Option<&ProfileId>)>,
+    profiles: Option<Res<Profiles>>,
+    mut screen_query: Query<&mut Visibility, With<PrivacyScreen>>,
+    mut text_query: Query<&mut Text, With<PrivacyScreenText>>,
+    mut button_query: Query<&mut ReadyButton>,
+) {
+    let waiting = game_query
+        .iter()
+        .sorted_by_key(|(session_id, ..)| *session_id)
+        .find_map(|(session_id, table_game, hot_seat)| {
+            let (seat, player_id) = table_game.to_act()?;
+            let (kind, profile_id) = player_query.get(player_id).ok()?;
+            (*kind == SeatKind::Human && hot_seat.showing.is_none())
+                .then_some((session_id, seat, profile_id))
+        });
+
+    for mut button in button_query.iter_mut() {
+        let waiting = waiting.map(|(session_id, seat, _)| (session_id, seat));
+        if button.waiting != waiting {
+            button.waiting = waiting;
+        }
+    }
+    for mut visibility in screen_query.iter_mut() {
+        let wanted = match waiting {
+            Some(_) => Visibility::Visible,
+            None => Visibility::Hidden,
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+    let Some((_, seat, profile_id)) = waiting else {
+        return;
+    };
+    let name = match (&profiles, profile_id) {
+        (Some(profiles), Some(profile_id)) => profiles.name(*profile_id),
+        _ => format!("Seat {}", seat + 1),
+    };
+    for mut text in text_query.iter_mut() {
+        let message = format!("Pass the device to {name}");
+        if text.sections[0].value != message {
+            text.sections[0].value = message;
+        }
+    }
+}
+
+/// Show the waiting human their hand, from their seat.
+fn handle_ready_button(
+    button_query: Query<(&Interaction, &ReadyButton), Changed<Interaction>>,
+    mut game_query: Query<(&Session, &TableGame, &mut HotSeat)>,
+    transform_query: Query<&GlobalTransform>,
+    mut camera_query: Query<&mut RtsCamera>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some((session_id, seat)) = button.waiting else {
+            continue;
+        };
+        let Ok((session, table_game, mut hot_seat)) = game_query.get_mut(session_id) else {
+            continue;
+        };
+        hot_seat.showing = Some(seat);
+        info!("Seat {seat} is ready in session {session_id:?}");
+
+        let (Ok(table), Ok(player)) = (
+            transform_query.get(session.table_id),
+            transform_query.get(table_game.seats[seat]),
+        ) else {
+            continue;
+        };
+        // Look across the table from behind the seat
+        let centre = table.translation() * Vec3::new(1.0, 0.0, 1.0);
+        let across = (centre - player.translation()) * Vec3::new(1.0, 0.0, 1.0);
+        for mut camera in camera_query.iter_mut() {
+            camera.target_focus = Transform::from_translation(centre).looking_to(across, Vec3::Y);
+            camera.target_zoom = SEAT_ZOOM;
+        }
+    }
+}
+
+/// At hot-seat tables, only the hand of the seat at the screen is face up.
+fn show_hands(
+    handles: Res<Handles>,
+    game_query: Query<(&Session, &TableGame, &HotSeat)>,
+    mut card_query: Query<(&Card, &CardLocation, &mut Handle<StandardMaterial>)>,
+) {
+    for (session, table_game, hot_seat) in game_query.iter() {
+        let showing = hot_seat.showing.map(|seat| table_game.seats[seat]);
+        let mut cards = card_query.iter_many_mut(&session.card_ids);
+        while let Some((card, location, mut material)) = cards.fetch_next() {
+            let face_up = match location {
+                CardLocation::Hand { player, .. } => Some(*player) == showing,
+                _ => true,
+            };
+            let wanted = match face_up {
+                true => handles.card_materials.get(card),
+                false => Some(&handles.card_back_material),
+            };
+            if let Some(wanted) = wanted
+                && *material != *wanted
+            {
+                *material = wanted.clone();
+            }
+        }
+    }
+}
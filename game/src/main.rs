@@ -1,10 +1,45 @@
 #![feature(let_chains)]
+// Bevy systems take their state as arguments and their queries spell out whole types
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+// The section banners below are written as doc comments
+#![allow(clippy::empty_line_after_doc_comments)]
+mod advisor;
+mod audit;
+mod bidding;
+mod bots;
+mod cli;
+mod dataset;
+mod focus;
 mod fps_text_plugin;
+mod game;
+mod game_log;
+mod hotseat;
+mod model;
+mod play;
+mod profiles;
+#[cfg(test)]
+mod properties;
+mod protocol;
+mod review;
+mod rules;
+mod save;
+#[cfg(test)]
+mod scenarios;
+mod scorekeeper;
+mod seat_camera;
+mod solver;
+mod stats;
+mod tournament;
+mod turns;
+mod tutorial;
 
 use std::f32::consts::PI;
 use std::time::Instant;
 
+use advisor::AdvisorPlugin;
+use audit::AuditPlugin;
 use bevy::app::AppExit;
+use bidding::BiddingPlugin;
 /*
 In the game Poche my family plays, the dealer is chosen by dealing a card to each player, high card deals.
 If tied, deal to tied players, high card deals, repeat until no tie.
@@ -42,55 +77,130 @@ use bevy_rts_camera::Ground;
 use bevy_rts_camera::RtsCamera;
 use bevy_rts_camera::RtsCameraControls;
 use bevy_rts_camera::RtsCameraPlugin;
+use cli::CliError;
+use cli::Command;
+use cli::LaunchConfig;
+use focus::FocusPlugin;
+use focus::FocusedSession;
 use fps_text_plugin::FpsTextPlugin;
+use game::Action;
+use game_log::GameLogPlugin;
+use hotseat::HotSeatPlugin;
 use itertools::Itertools;
 use meshtext::IndexedMeshText;
 use meshtext::MeshGenerator;
 use meshtext::QualitySettings;
 use meshtext::TextSection;
+use play::PlayPlugin;
+use play::TableGame;
+use profiles::ProfileId;
+use profiles::Profiles;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use rand::Rng;
+use rand::SeedableRng;
+use review::ReviewPlugin;
+use rules::RuleSet;
+use save::SavePlugin;
+use seat_camera::SeatCamera;
+use seat_camera::SeatCameraPlugin;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use stats::StatsDb;
+use stats::StatsPlugin;
+use turns::TurnPlugin;
+use tutorial::TutorialPlugin;
 
 ////////////////////////////
 /// APP
 ////////////////////////////
 
 fn main() {
-    let mut app = App::new();
-    app.register_type::<Session>();
-    app.register_type::<Card>();
-    app.register_type::<InHand>();
-    app.register_type::<InDeck>();
-    app.register_type::<Trump>();
-    app.register_type::<Played>();
-    app.register_type::<CardPositioningBehaviour>();
-    app.register_type::<Table>();
-    app.register_type::<Player>();
-    app.register_type::<SpawnSessionEvent>();
-    app.register_type::<BelongsToPlayer>();
-    app.register_type::<Handles>();
-    app.register_type::<Sleeping>();
-    app.register_type::<TablePositions>();
-
-    app.init_resource::<Handles>();
-    app.init_resource::<TablePositions>();
-
-    app.add_event::<SpawnSessionEvent>();
-    app.add_event::<KillSessionEvent>();
-    app.add_event::<SpawnDeckEvent>();
-    app.add_event::<DealCardsEvent>();
+    let launch_config = match LaunchConfig::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{e}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if launch_config.command == Command::Stats {
+        let profiles = Profiles::load(&launch_config.profiles);
+        let stats = StatsDb::load(&launch_config.stats);
+        print!("{}", stats.report(&profiles));
+        return;
+    }
+    if launch_config.command == Command::Tournament {
+        tournament::run(launch_config);
+        return;
+    }
+    if launch_config.command == Command::Scorekeeper {
+        scorekeeper::run(launch_config);
+        return;
+    }
+    if launch_config.command == Command::Analyse {
+        solver::run(&launch_config);
+        return;
+    }
+    if launch_config.command == Command::Bot {
+        protocol::run(&launch_config);
+        return;
+    }
+    if launch_config.command == Command::SelfPlay {
+        dataset::run(&launch_config);
+        return;
+    }
 
+    let mut app = App::new();
+    app.insert_resource(launch_config.clone());
+
+    app.add_plugins(SessionPlugin);
+    app.add_plugins(PlayPlugin);
+    app.add_plugins(ReviewPlugin);
+    app.add_plugins(TurnPlugin);
+    app.add_plugins(HotSeatPlugin);
+    app.add_plugins(SeatCameraPlugin);
+    app.add_plugins(FocusPlugin);
+    app.add_plugins(BiddingPlugin);
+    if launch_config.command == Command::Tutorial {
+        app.add_plugins(TutorialPlugin);
+    }
+    if launch_config.advisor {
+        app.add_plugins(AdvisorPlugin);
+    }
+    app.add_plugins(GameLogPlugin);
     app.add_plugins(FpsTextPlugin);
+    app.add_plugins(StatsPlugin);
+    if launch_config.resume.is_some() {
+        app.add_plugins(SavePlugin);
+    }
+    if cfg!(debug_assertions) {
+        app.add_plugins(AuditPlugin);
+    }
     app.add_plugins(
         DefaultPlugins
-            .set(WindowPlugin {
-                primary_window: Some(Window {
-                    // cursor: bevy::window::Cursor {
-                    //     grab_mode: bevy::window::CursorGrabMode::Confined,
-                    //     ..default()
-                    // },
+            .set(match launch_config.headless {
+                true => WindowPlugin {
+                    primary_window: None,
+                    exit_condition: bevy::window::ExitCondition::DontExit,
+                    close_when_requested: false,
+                },
+                false => WindowPlugin {
+                    primary_window: Some(Window {
+                        // cursor: bevy::window::Cursor {
+                        //     grab_mode: bevy::window::CursorGrabMode::Confined,
+                        //     ..default()
+                        // },
+                        ..default()
+                    }),
                     ..default()
-                }),
-                ..default()
+                },
             })
             .set(LogPlugin {
                 level: bevy::log::Level::INFO,
@@ -108,37 +218,90 @@ fn main() {
                 ..default()
             }),
     );
-    app.add_plugins(
-        WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::Backquote)),
-    );
+    if !launch_config.headless {
+        app.add_plugins(
+            WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::Backquote)),
+        );
+    }
     app.add_plugins(RtsCameraPlugin);
 
-    app.add_systems(Startup, setup);
-    app.add_systems(
-        Update,
-        (
-            handle_spawn_session_events,
-            handle_spawn_deck_events,
-            handle_tables_needing_dealer,
-            handle_deal_cards_events,
-            determine_card_positioning_behaviours,
-            position_cards_in_deck,
-            position_cards_in_hand,
-        )
-            .chain(),
-    );
+    app.add_systems(Startup, (load_profiles, setup));
     app.add_systems(Update, handle_quit_key_press);
     app.add_systems(Update, handle_deal_key_press);
     app.add_systems(Update, handle_kill_session_key_press);
     app.add_systems(Update, handle_new_table_key_press);
     app.add_systems(Update, handle_sleeping_key_press);
     app.add_systems(Update, handle_shuffle_back_in_key_press);
-    app.add_systems(Update, update_card_names);
-    app.add_systems(Update, handle_kill_session_events);
+    app.add_systems(Update, handle_pause_key_press);
+    app.add_systems(Update, save_new_profiles);
 
     app.run();
 }
 
+/// Sessions, decks, dealing and card positioning; everything but the window, camera and input.
+pub struct SessionPlugin;
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Session>();
+        app.register_type::<Card>();
+        app.register_type::<CardLocation>();
+        app.register_type::<CardPositioningBehaviour>();
+        app.register_type::<Table>();
+        app.register_type::<Player>();
+        app.register_type::<Seat>();
+        app.register_type::<Eye>();
+        app.register_type::<SpawnSessionEvent>();
+        app.register_type::<BelongsToPlayer>();
+        app.register_type::<Handles>();
+        app.register_type::<Sleeping>();
+        app.register_type::<TablePositions>();
+        app.register_type::<RuleSet>();
+        app.register_type::<SeatKind>();
+        app.register_type::<ProfileId>();
+
+        app.init_resource::<Handles>();
+        app.init_resource::<TablePositions>();
+
+        app.add_event::<SpawnSessionEvent>();
+        app.add_event::<KillSessionEvent>();
+        app.add_event::<SpawnDeckEvent>();
+        app.add_event::<DealCardsEvent>();
+
+        app.add_systems(
+            Update,
+            (
+                handle_spawn_session_events,
+                handle_spawn_deck_events,
+                handle_tables_needing_dealer,
+                handle_deal_cards_events,
+                determine_card_positioning_behaviours,
+                position_cards_in_deck,
+                position_cards_in_hand,
+                position_cards_on_table,
+            )
+                .chain(),
+        );
+        app.add_systems(Update, update_card_names);
+        app.add_systems(Update, handle_kill_session_events);
+    }
+}
+
+/// The [`SessionPlugin`] without a renderer, for tests and tournaments.
+pub struct HeadlessSessionPlugin;
+impl Plugin for HeadlessSessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Assets<Mesh>>();
+        app.init_resource::<Assets<StandardMaterial>>();
+        app.add_plugins(SessionPlugin);
+
+        // Nothing is rendered, but every card still needs a material
+        app.world.resource_mut::<Handles>().card_materials = Card::get_new_deck()
+            .into_iter()
+            .map(|card| (card, Handle::default()))
+            .collect();
+    }
+}
+
 ////////////////////////////
 /// MONEY
 ////////////////////////////
@@ -242,6 +405,11 @@ impl Card {
         format!("cards/{suit}_{rank}_white.png")
     }
 
+    /// Where the card is in a new deck, 0 to 51, by suit then rank.
+    pub fn index(&self) -> usize {
+        self.suit as usize * 13 + self.rank as usize
+    }
+
     pub fn get_new_deck() -> Vec<Self> {
         let mut cards = Vec::new();
         for &suit in &[Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs] {
@@ -267,26 +435,124 @@ impl Card {
     }
 }
 
-#[derive(Component, Debug, Eq, PartialEq, Clone, Reflect, Default)]
-pub struct InHand {
-    index_from_left: usize,
+/// Short form like `QS` or `10H`, rank then suit.
+impl std::fmt::Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rank = match self.rank {
+            Rank::Ace => "A".to_owned(),
+            Rank::Jack => "J".to_owned(),
+            Rank::Queen => "Q".to_owned(),
+            Rank::King => "K".to_owned(),
+            rank => rank.value().to_string(),
+        };
+        let suit = match self.suit {
+            Suit::Spades => 'S',
+            Suit::Hearts => 'H',
+            Suit::Diamonds => 'D',
+            Suit::Clubs => 'C',
+        };
+        write!(f, "{rank}{suit}")
+    }
 }
 
-#[derive(Component, Debug, Eq, PartialEq, Clone, Reflect, Default)]
-pub struct Played;
+impl std::str::FromStr for Card {
+    type Err = String;
 
-#[derive(Component, Debug, Eq, PartialEq, Clone, Reflect, Default)]
-pub struct InDeck {
-    index_from_bottom: usize,
+    /// Parse the short form, `T` also works for ten.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_uppercase();
+        let Some(suit_letter) = s.chars().last() else {
+            return Err("empty card".into());
+        };
+        let suit = match suit_letter {
+            'S' => Suit::Spades,
+            'H' => Suit::Hearts,
+            'D' => Suit::Diamonds,
+            'C' => Suit::Clubs,
+            _ => return Err(format!("unknown suit in {s}")),
+        };
+        let rank = match &s[..s.len() - 1] {
+            "A" => Rank::Ace,
+            "2" => Rank::Two,
+            "3" => Rank::Three,
+            "4" => Rank::Four,
+            "5" => Rank::Five,
+            "6" => Rank::Six,
+            "7" => Rank::Seven,
+            "8" => Rank::Eight,
+            "9" => Rank::Nine,
+            "10" | "T" => Rank::Ten,
+            "J" => Rank::Jack,
+            "Q" => Rank::Queen,
+            "K" => Rank::King,
+            _ => return Err(format!("unknown rank in {s}")),
+        };
+        Ok(Card::new(suit, rank))
+    }
 }
 
-#[derive(Component, Debug, Eq, PartialEq, Clone, Reflect, Default)]
-pub struct Trump;
+/// Cards are written in their short form, like `"10H"`.
+impl Serialize for Card {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+impl<'de> Deserialize<'de> for Card {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Where a card is. Every card has exactly one location, change it using [`move_card`].
+#[derive(Component, Debug, Eq, PartialEq, Clone, Reflect)]
+pub enum CardLocation {
+    Deck {
+        index_from_bottom: usize,
+    },
+    Hand {
+        player: Entity,
+        index_from_left: usize,
+    },
+    Trick {
+        index: usize,
+        played_by: Entity,
+    },
+    Taken {
+        player: Entity,
+        trick: usize,
+        index: usize,
+    },
+    TrumpReveal,
+}
+impl CardLocation {
+    pub fn positioning_behaviour(&self) -> CardPositioningBehaviour {
+        match self {
+            CardLocation::Deck { .. } => CardPositioningBehaviour::InDeck,
+            CardLocation::Hand { .. } => CardPositioningBehaviour::InHand,
+            CardLocation::Trick { .. } => CardPositioningBehaviour::Played,
+            CardLocation::Taken { .. } => CardPositioningBehaviour::InTakenTrick,
+            CardLocation::TrumpReveal => CardPositioningBehaviour::RevealedOnDeck,
+        }
+    }
+}
+
+/// Move a card to a new location and wake it up so it travels there.
+pub fn move_card(commands: &mut Commands, card_id: Entity, location: CardLocation) {
+    commands
+        .entity(card_id)
+        .insert(location)
+        .remove::<TravelTime>()
+        .remove::<Sleeping>();
+}
 
 #[derive(Component, Debug, Eq, PartialEq, Clone, Reflect)]
 pub struct BelongsToPlayer(Entity);
 
 /// To avoid conflicting card transform updates, use a component to enforce exclusive update bahviour.
+///
+/// Derived from the [`CardLocation`], except for `None` which pins a card where it is.
 #[derive(Component, Debug, Eq, PartialEq, Clone, Reflect)]
 pub enum CardPositioningBehaviour {
     None,
@@ -318,17 +584,18 @@ pub struct Handles {
     pub card_shape: Cuboid,
     pub card_mesh: Handle<Mesh>,
     pub card_materials: HashMap<Card, Handle<StandardMaterial>>,
+    pub card_back_material: Handle<StandardMaterial>,
     pub player_body_shape: Capsule3d,
     pub player_body_mesh: Handle<Mesh>,
-    pub player_body_material: Handle<StandardMaterial>,
     pub player_eye_shape: Sphere,
     pub player_eye_mesh: Handle<Mesh>,
     pub player_eye_material: Handle<StandardMaterial>,
     pub coin_shape: Cylinder,
     pub coin_mesh: Handle<Mesh>,
     pub coin_material: Handle<StandardMaterial>,
-    pub ahoy_mesh: Handle<Mesh>,
-    pub ahoy_material: Handle<StandardMaterial>,
+    pub nametag_material: Handle<StandardMaterial>,
+    pub avatar_shape: Rectangle,
+    pub avatar_mesh: Handle<Mesh>,
 }
 
 ////////////////////////////
@@ -385,9 +652,17 @@ pub struct NeedsDealer;
 
 #[derive(Component, Debug, Eq, PartialEq, Clone, Reflect)]
 pub struct Session {
+    /// What people call the table, the lowest number free when it was set.
+    number: usize,
     table_id: Entity,
     player_ids: HashSet<Entity>,
     card_ids: HashSet<Entity>,
+    rules: RuleSet,
+    seed: u64,
+    decks: Vec<Vec<Card>>,
+    /// Moves to replay when the game starts, for a resumed game.
+    #[reflect(ignore)]
+    moves: Vec<(usize, Action)>,
 }
 
 #[derive(Component, Debug, Eq, PartialEq, Clone, Reflect)]
@@ -415,6 +690,126 @@ pub struct Player;
 #[derive(Component, Debug, Eq, PartialEq, Clone, Reflect, Default)]
 pub struct Dealer;
 
+/// One of a player's eyes, which look towards whoever's turn it is.
+#[derive(Component, Debug, PartialEq, Clone, Copy, Reflect)]
+pub struct Eye {
+    /// Where the eye sits when looking straight ahead.
+    pub rest: Vec3,
+}
+
+/// Where a player sits, numbered like the seats of a [`game::Game`].
+#[derive(Component, Debug, Eq, PartialEq, Clone, Copy, Reflect)]
+pub struct Seat(pub usize);
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Reflect, Default, Serialize, Deserialize)]
+pub enum BotDifficulty {
+    Beginner,
+    #[default]
+    Family,
+    Expert,
+}
+impl BotDifficulty {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "beginner" => Some(BotDifficulty::Beginner),
+            "family" => Some(BotDifficulty::Family),
+            "expert" => Some(BotDifficulty::Expert),
+            _ => None,
+        }
+    }
+
+    /// How far a bid can stray from the bot's estimate, in tricks.
+    pub fn bid_noise(&self) -> f32 {
+        match self {
+            BotDifficulty::Beginner => 1.5,
+            BotDifficulty::Family => 0.6,
+            BotDifficulty::Expert => 0.0,
+        }
+    }
+
+    /// The chance of playing a random legal card instead of the planned one.
+    pub fn misplay_chance(&self) -> f64 {
+        match self {
+            BotDifficulty::Beginner => 0.15,
+            BotDifficulty::Family => 0.05,
+            BotDifficulty::Expert => 0.0,
+        }
+    }
+
+    /// How many of the latest cards of finished tricks the bot remembers, `None` for all of them.
+    pub fn memory(&self) -> Option<usize> {
+        match self {
+            BotDifficulty::Beginner => Some(0),
+            BotDifficulty::Family => Some(6),
+            BotDifficulty::Expert => None,
+        }
+    }
+}
+
+/// What a bot is going for, on top of how well it plays.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Reflect, Default, Serialize, Deserialize)]
+pub enum Personality {
+    /// Bids what it expects to take.
+    #[default]
+    Balanced,
+    /// Bids under its estimate.
+    Conservative,
+    /// Bids every trick with a strong hand, chasing the 2 prefix.
+    Aggressive,
+}
+impl Personality {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "balanced" => Some(Personality::Balanced),
+            "conservative" => Some(Personality::Conservative),
+            "aggressive" => Some(Personality::Aggressive),
+            _ => None,
+        }
+    }
+}
+
+/// How a [`SeatKind::Bot`] plays, it is the bot's [`bots::Strategy`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Reflect, Default, Serialize, Deserialize)]
+pub struct BotStyle {
+    pub difficulty: BotDifficulty,
+    pub personality: Personality,
+}
+impl BotStyle {
+    /// `level` or `level:personality`, like `expert:aggressive`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (difficulty, personality) = match name.split_once(':') {
+            Some((difficulty, personality)) => (difficulty, Personality::from_name(personality)?),
+            None => (name, Personality::default()),
+        };
+        Some(Self {
+            difficulty: BotDifficulty::from_name(difficulty)?,
+            personality,
+        })
+    }
+}
+
+/// Who is sitting in a seat.
+#[derive(Component, Debug, Eq, PartialEq, Clone, Copy, Reflect, Serialize, Deserialize)]
+pub enum SeatKind {
+    Human,
+    Bot(BotStyle),
+    /// Another program, see [`protocol::ProcessBot`].
+    Process,
+    /// A trained policy, see [`model::ModelBot`].
+    Model,
+}
+impl SeatKind {
+    /// A human in the first seat and bots in the rest.
+    pub fn default_seats(num_players: usize, style: BotStyle) -> Vec<Self> {
+        (0..num_players)
+            .map(|i| match i {
+                0 => SeatKind::Human,
+                _ => SeatKind::Bot(style),
+            })
+            .collect()
+    }
+}
+
 ////////////////////////////
 /// EVENTS
 ////////////////////////////
@@ -426,9 +821,20 @@ pub struct DealCardsEvent {
     pub player_ids: Vec<Entity>,
 }
 
+/// The number of players is the number of seats.
 #[derive(Event, Debug, Reflect)]
 pub struct SpawnSessionEvent {
-    pub num_players: usize,
+    pub seats: Vec<SeatKind>,
+    pub rules: RuleSet,
+    /// Seeds the deck shuffle, random when not given.
+    pub seed: Option<u64>,
+    /// The seat dealing first, drawn for at the table when not given.
+    pub dealer: Option<usize>,
+    /// Stacked decks for the first rounds, see [`game::stacked_deck`].
+    pub decks: Vec<Vec<Card>>,
+    /// Moves already made in the game, replayed when it starts, see [`save`].
+    #[reflect(ignore)]
+    pub moves: Vec<(usize, Action)>,
 }
 #[derive(Event, Debug, Reflect)]
 pub struct KillSessionEvent {
@@ -449,10 +855,13 @@ fn handle_quit_key_press(mut exit: ResMut<Events<AppExit>>, input: Res<ButtonInp
     }
 }
 
+/// Deal by hand at the focused table if it isn't playing a game, where the number keys bid and
+/// play instead.
 fn handle_deal_key_press(
     mut events: EventWriter<DealCardsEvent>,
     input: Res<ButtonInput<KeyCode>>,
-    session_query: Query<(Entity, &Session)>,
+    focused: Res<FocusedSession>,
+    session_query: Query<&Session, Without<TableGame>>,
 ) {
     let keys = [
         KeyCode::Digit1,
@@ -467,34 +876,25 @@ fn handle_deal_key_press(
     ];
     for (i, key) in keys.iter().enumerate() {
         if input.just_pressed(*key) {
-            for session in session_query.iter() {
-                let (session_id, session) = session;
-                let mut player_ids = Vec::new();
+            let Some(session_id) = focused.0 else {
+                warn!("No table focused to deal at, click one");
+                return;
+            };
+            let Ok(session) = session_query.get(session_id) else {
+                continue;
+            };
+            let mut player_ids = Vec::new();
 
-                for _ in 0..i + 1 {
-                    player_ids.append(&mut session.player_ids.iter().cloned().collect_vec());
-                }
-                info!(
-                    "Dealing cards to all players in session {session_id:?} because of key press"
-                );
-                events.send(DealCardsEvent {
-                    session_id,
-                    player_ids,
-                });
+            for _ in 0..i + 1 {
+                player_ids.append(&mut session.player_ids.iter().cloned().collect_vec());
             }
+            info!("Dealing cards to all players in session {session_id:?} because of key press");
+            events.send(DealCardsEvent {
+                session_id,
+                player_ids,
+            });
         }
     }
-    // if input.just_pressed(KeyCode::Digit1) {
-    //     for session in session_query.iter() {
-    //         let (session_id, session) = session;
-    //         let player_ids = session.player_ids.iter().cloned().collect_vec();
-    //         info!("Dealing cards to all players in session {session_id:?} because of key press");
-    //         events.send(DealCardsEvent {
-    //             session_id,
-    //             player_ids,
-    //         });
-    //     }
-    // }
 }
 
 fn handle_spawn_session_events(
@@ -502,9 +902,24 @@ fn handle_spawn_session_events(
     mut spawn_table_events: EventReader<SpawnSessionEvent>,
     mut spawn_deck_events: EventWriter<SpawnDeckEvent>,
     mut table_positions: ResMut<TablePositions>,
+    mut profiles: ResMut<Profiles>,
+    session_query: Query<&Session>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
     handles: Res<Handles>,
+    // Absent in headless apps, which go without avatars
+    asset_server: Option<Res<AssetServer>>,
 ) {
+    let mut numbers = session_query
+        .iter()
+        .map(|session| session.number)
+        .collect::<HashSet<_>>();
     for event in spawn_table_events.read() {
+        let number = (1..)
+            .find(|number| !numbers.contains(number))
+            .expect("some number is free");
+        numbers.insert(number);
+
         // Reserve the table position
         let table_position = table_positions.acquire_position(handles.table_shape.half_height);
 
@@ -512,11 +927,21 @@ fn handle_spawn_session_events(
         let mut players = HashSet::new();
         let mut needs_session_id_ref = Vec::new();
         let seating_radius = handles.table_shape.radius + 0.7;
-        for i in 0..event.num_players {
+        let num_players = event.seats.len();
+        let known_profiles = profiles.profiles.len();
+        let profile_ids = profiles.bypass_change_detection().assign(&event.seats);
+        // Only new profiles need saving, see save_new_profiles
+        if profiles.profiles.len() > known_profiles {
+            profiles.set_changed();
+        }
+        for (i, (seat, profile_id)) in event.seats.iter().zip(profile_ids).enumerate() {
+            let profile = profiles
+                .get(profile_id)
+                .expect("assigned profiles should exist");
+
             // Get the angle from the center of the table to the player
-            let player_angle = Quat::from_rotation_y(
-                std::f32::consts::PI * 2.0 * i as f32 / event.num_players as f32,
-            );
+            let player_angle =
+                Quat::from_rotation_y(std::f32::consts::PI * 2.0 * i as f32 / num_players as f32);
 
             // Get the position of the player
             let player_position = table_position
@@ -539,43 +964,49 @@ fn handle_spawn_session_events(
                 .spawn((
                     PbrBundle {
                         mesh: handles.player_body_mesh.clone(),
-                        material: handles.player_body_material.clone(),
+                        material: materials.add(StandardMaterial {
+                            base_color: profile.color(),
+                            ..default()
+                        }),
                         transform: player_transform,
                         ..default()
                     },
-                    Player::default(),
-                    Name::new("Player"),
+                    Player,
+                    Seat(i),
+                    *seat,
+                    profile_id,
+                    Name::new(format!("Player - {}", profile.name)),
                 ))
                 .with_children(|parent| {
                     // spawn the eyes
-                    parent.spawn(PbrBundle {
-                        mesh: handles.player_eye_mesh.clone(),
-                        material: handles.player_eye_material.clone(),
-                        transform: Transform::from_xyz(
-                            0.1,
-                            handles.player_body_shape.half_length * 0.8,
-                            -handles.player_body_shape.radius,
-                        ),
-                        ..default()
-                    });
-                    parent.spawn(PbrBundle {
-                        mesh: handles.player_eye_mesh.clone(),
-                        material: handles.player_eye_material.clone(),
-                        transform: Transform::from_xyz(
-                            -0.1,
+                    for x in [0.1, -0.1] {
+                        let rest = Vec3::new(
+                            x,
                             handles.player_body_shape.half_length * 0.8,
                             -handles.player_body_shape.radius,
-                        ),
-                        ..default()
-                    });
-                    let nametag_transform = player_transform;
-                    let _ = nametag_transform.translation
-                        + Vec3::Y * handles.player_body_shape.half_length
-                        + Vec3::Y * 0.2;
+                        );
+                        parent.spawn((
+                            PbrBundle {
+                                mesh: handles.player_eye_mesh.clone(),
+                                material: handles.player_eye_material.clone(),
+                                transform: Transform::from_translation(rest),
+                                ..default()
+                            },
+                            Eye { rest },
+                        ));
+                    }
+                    let nametag_transform = Transform::from_xyz(
+                        0.0,
+                        handles.player_body_shape.half_length
+                            + handles.player_body_shape.radius
+                            + 0.2,
+                        0.0,
+                    )
+                    .with_scale(Vec3::splat(0.2));
                     parent.spawn((
                         PbrBundle {
-                            mesh: handles.ahoy_mesh.clone(),
-                            material: handles.ahoy_material.clone(),
+                            mesh: meshes.add(create_mesh(&profile.name)),
+                            material: handles.nametag_material.clone(),
                             transform: nametag_transform,
                             ..default()
                         },
@@ -583,6 +1014,32 @@ fn handle_spawn_session_events(
                         NotShadowCaster,
                         NotShadowReceiver,
                     ));
+                    if let Some(avatar) = &profile.avatar
+                        && let Some(asset_server) = &asset_server
+                    {
+                        let avatar_transform = nametag_transform
+                            .with_translation(
+                                nametag_transform.translation
+                                    + Vec3::Y * (handles.avatar_shape.half_size.y + 0.2),
+                            )
+                            .with_scale(Vec3::ONE);
+                        parent.spawn((
+                            PbrBundle {
+                                mesh: handles.avatar_mesh.clone(),
+                                material: materials.add(StandardMaterial {
+                                    base_color_texture: Some(asset_server.load(avatar.clone())),
+                                    alpha_mode: AlphaMode::Blend,
+                                    unlit: true,
+                                    ..default()
+                                }),
+                                transform: avatar_transform,
+                                ..default()
+                            },
+                            Name::new("Avatar"),
+                            NotShadowCaster,
+                            NotShadowReceiver,
+                        ));
+                    }
                     // parent.spawn(Text2dBundle {
                     //     text: Text::from_section(
                     //         "hehe",
@@ -598,6 +1055,9 @@ fn handle_spawn_session_events(
                     // });
                 })
                 .id();
+            if event.dealer == Some(i) {
+                commands.entity(player_id).insert(Dealer);
+            }
             players.insert(player_id);
             needs_session_id_ref.push(player_id);
 
@@ -636,18 +1096,25 @@ fn handle_spawn_session_events(
                 },
                 Name::new("Table"),
                 Table,
-                NeedsDealer,
             ))
             .id();
+        if event.dealer.is_none() {
+            commands.entity(table).insert(NeedsDealer);
+        }
         needs_session_id_ref.push(table);
 
         // Create the session
         let session_id = commands
             .spawn((
                 Session {
+                    number,
                     table_id: table,
                     player_ids: players.clone(),
                     card_ids: Default::default(),
+                    rules: event.rules.clone(),
+                    seed: event.seed.unwrap_or_else(rand::random),
+                    decks: event.decks.clone(),
+                    moves: event.moves.clone(),
                 },
                 Name::new("Session"),
             ))
@@ -671,7 +1138,7 @@ fn handle_spawn_session_events(
         // Spawn the deck
         spawn_deck_events.send(SpawnDeckEvent { session_id });
 
-        info!("Table spawned with {} players", event.num_players);
+        info!("Table spawned with {} players", num_players);
     }
 }
 
@@ -703,7 +1170,8 @@ fn handle_spawn_deck_events(
         // Spawn the deck by spawning in each card
         let y_increment = 0.01;
         let mut y = 0.0;
-        let cards = Card::get_new_deck();
+        let mut cards = Card::get_new_deck();
+        cards.shuffle(&mut StdRng::seed_from_u64(session.seed));
         for (i, card) in cards.into_iter().enumerate() {
             let card_position = deck_position + Vec3::Y * y;
             let card_id = commands
@@ -716,7 +1184,7 @@ fn handle_spawn_deck_events(
                     },
                     card,
                     Name::new("Card"),
-                    InDeck {
+                    CardLocation::Deck {
                         index_from_bottom: i,
                     },
                     SessionRef(event.session_id),
@@ -740,7 +1208,7 @@ fn handle_tables_needing_dealer(
     mut commands: Commands,
     table_query: Query<(Entity, &SessionRef), (With<NeedsDealer>, With<Table>)>,
     session_query: Query<&Session>,
-    cards_in_hands_query: Query<(&Card, Option<&BelongsToPlayer>), With<InHand>>,
+    card_query: Query<(&Card, &CardLocation)>,
     mut deal_card_events: EventWriter<DealCardsEvent>,
 ) {
     for table in table_query.iter() {
@@ -763,12 +1231,12 @@ fn handle_tables_needing_dealer(
                 .iter()
                 .fold(HashMap::default(), |mut map, card_id| {
                     // Get card
-                    let Ok((card, player_id)) = cards_in_hands_query.get(*card_id) else {
+                    let Ok((card, location)) = card_query.get(*card_id) else {
                         return map;
                     };
                     // Add to player's hand
-                    if let Some(player_id) = player_id {
-                        map.entry(player_id.0)
+                    if let CardLocation::Hand { player, .. } = location {
+                        map.entry(*player)
                             .or_insert_with(Vec::new)
                             .push((card_id, card));
                     }
@@ -777,14 +1245,17 @@ fn handle_tables_needing_dealer(
                 });
 
         {
-            // Find players with no cards in hand
+            // Find players with no cards in hand, dealing in the order they sat down so the same
+            // seed always picks the same dealer
             let no_cards_in_hand = session
                 .player_ids
                 .iter()
+                .sorted()
                 .filter(|player_id| {
                     cards_by_player
                         .get(*player_id)
-                        .map_or(true, |cards| cards.is_empty())
+                        .iter()
+                        .all(|cards| cards.is_empty())
                 })
                 .cloned()
                 .collect_vec();
@@ -822,16 +1293,13 @@ fn handle_tables_needing_dealer(
                 .copied()
                 .expect("there should be at least one player with a card by now");
 
-            // find tied for first
-            let players_with_max_value = player_values
+            // find tied for first, in the same order
+            let players_with_max_value = session
+                .player_ids
                 .iter()
-                .filter_map(|(player_id, value)| {
-                    if *value == max_value {
-                        Some(*player_id)
-                    } else {
-                        None
-                    }
-                })
+                .sorted()
+                .filter(|player_id| player_values.get(*player_id) == Some(&max_value))
+                .copied()
                 .collect::<Vec<Entity>>();
             assert!(!players_with_max_value.is_empty());
 
@@ -855,8 +1323,7 @@ fn handle_tables_needing_dealer(
 
 fn handle_deal_cards_events(
     mut commands: Commands,
-    cards_in_decks_query: Query<&InDeck, (With<Card>, Without<InHand>)>,
-    cards_in_hands_query: Query<(&BelongsToPlayer, &InHand), (With<Card>, Without<InDeck>)>,
+    card_query: Query<&CardLocation, With<Card>>,
     mut deal_cards_events: EventReader<DealCardsEvent>,
     session_query: Query<&Session>,
 ) {
@@ -865,13 +1332,17 @@ fn handle_deal_cards_events(
     }
 
     // Get the size of hands for each player so we know what index to start at
-    let mut hand_sizes =
-        cards_in_hands_query
-            .iter()
-            .fold(HashMap::default(), |mut map, (player, _)| {
-                *map.entry(player.0).or_insert(0) += 1;
-                map
-            });
+    let mut hand_sizes = card_query
+        .iter()
+        .fold(HashMap::default(), |mut map, location| {
+            if let CardLocation::Hand { player, .. } = location {
+                *map.entry(*player).or_insert(0) += 1;
+            }
+            map
+        });
+
+    // Commands aren't applied until the system ends, so remember what was dealt by earlier events
+    let mut dealt = HashSet::new();
 
     for event in deal_cards_events.read() {
         let players = &event.player_ids;
@@ -887,11 +1358,10 @@ fn handle_deal_cards_events(
         let top_cards = session
             .card_ids
             .iter()
-            .filter_map(|card_id| {
-                let Ok(in_deck) = cards_in_decks_query.get(*card_id) else {
-                    return None;
-                };
-                Some((card_id, in_deck.index_from_bottom))
+            .filter(|card_id| !dealt.contains(*card_id))
+            .filter_map(|card_id| match card_query.get(*card_id) {
+                Ok(CardLocation::Deck { index_from_bottom }) => Some((card_id, *index_from_bottom)),
+                _ => None,
             })
             .sorted_by_key(|(_, index_from_bottom)| *index_from_bottom)
             .rev()
@@ -909,23 +1379,20 @@ fn handle_deal_cards_events(
             .iter()
             .zip(top_cards)
             .for_each(|(player_id, card_id)| {
-                // Take it out of the deck
-                commands.entity(card_id).remove::<InDeck>();
-
-                // Make it belong to the player
-                commands.entity(card_id).insert(BelongsToPlayer(*player_id));
-
-                // Put it in the player's hand
+                // Move it from the deck to the player's hand
                 let hand_size = hand_sizes.entry(*player_id).or_insert(0usize);
-                commands.entity(card_id).insert(InHand {
-                    index_from_left: *hand_size,
-                });
+                move_card(
+                    &mut commands,
+                    card_id,
+                    CardLocation::Hand {
+                        player: *player_id,
+                        index_from_left: *hand_size,
+                    },
+                );
                 *hand_size += 1;
+                dealt.insert(card_id);
                 debug!("Player hand size updated to {:?}", hand_size);
 
-                // Wake it up
-                commands.entity(card_id).remove::<Sleeping>();
-
                 info!("Dealt card {:?} to player {:?}", card_id, player_id);
             });
 
@@ -936,82 +1403,23 @@ fn handle_deal_cards_events(
 fn determine_card_positioning_behaviours(
     mut commands: Commands,
     card_query: Query<
-        (
-            Entity,
-            Option<&CardPositioningBehaviour>,
-            Option<&BelongsToPlayer>,
-            Option<&InDeck>,
-            Option<&InHand>,
-            Option<&Played>,
-            Option<&Trump>,
-        ),
-        With<Card>,
+        (Entity, &CardLocation, Option<&CardPositioningBehaviour>),
+        (With<Card>, Changed<CardLocation>),
     >,
 ) {
     for card in card_query.iter() {
-        let (
-            card_id,
-            card_positioning_behaviour,
-            card_player,
-            card_in_deck,
-            card_in_hand,
-            card_played,
-            card_trump,
-        ) = card;
+        let (card_id, location, card_positioning_behaviour) = card;
         if card_positioning_behaviour == Some(&CardPositioningBehaviour::None) {
             continue;
         }
 
-        struct Decision {
-            has_player: bool,
-            in_deck: bool,
-            in_hand: bool,
-            played: bool,
-            trump: bool,
-        }
-        let decision = Decision {
-            has_player: card_player.is_some(),
-            in_deck: card_in_deck.is_some(),
-            in_hand: card_in_hand.is_some(),
-            played: card_played.is_some(),
-            trump: card_trump.is_some(),
-        };
-        match match decision {
-            Decision {
-                in_hand: true,
-                has_player,
-                ..
-            } => {
-                if !has_player {
-                    warn!("Card in hand without player");
-                }
-                Some(CardPositioningBehaviour::InHand)
-            }
-            Decision { in_deck: true, .. } => Some(CardPositioningBehaviour::InDeck),
-            Decision { played: true, .. } => Some(CardPositioningBehaviour::Played),
-            Decision { trump: true, .. } => Some(CardPositioningBehaviour::RevealedOnDeck),
-            _ => None,
-        } {
-            Some(behaviour) => {
-                if card_positioning_behaviour != Some(&behaviour) {
-                    debug!(
-                        "Card {:?} changed now using positioning behaviour: {:?}",
-                        card_id, behaviour
-                    );
-                }
-                commands.entity(card_id).insert(behaviour);
-            }
-            None => {
-                if card_positioning_behaviour.is_some() {
-                    warn!(
-                        "Card {:?} changed now using positioning behaviour: None",
-                        card_id
-                    );
-                }
-                commands
-                    .entity(card_id)
-                    .remove::<CardPositioningBehaviour>();
-            }
+        let behaviour = location.positioning_behaviour();
+        if card_positioning_behaviour != Some(&behaviour) {
+            debug!(
+                "Card {:?} changed now using positioning behaviour: {:?}",
+                card_id, behaviour
+            );
+            commands.entity(card_id).insert(behaviour);
         }
     }
 }
@@ -1025,7 +1433,7 @@ fn position_cards_in_deck(
         (
             &CardPositioningBehaviour,
             &mut Transform,
-            &InDeck,
+            &CardLocation,
             Option<&TravelTime>,
             Option<&Sleeping>,
         ),
@@ -1042,9 +1450,12 @@ fn position_cards_in_deck(
                 let Ok(card) = cards_in_decks_query.get(*card_id) else {
                     return None;
                 };
-                Some((card_id, card))
+                let CardLocation::Deck { index_from_bottom } = card.2 else {
+                    return None;
+                };
+                Some((card_id, (card, *index_from_bottom)))
             })
-            .sorted_by_key(|(_card_id, (_, _, in_deck, ..))| in_deck.index_from_bottom)
+            .sorted_by_key(|(_card_id, (_, index_from_bottom))| *index_from_bottom)
             .peekable();
         let bottom_card_id = match cards_in_deck.peek() {
             Some((card_id, _)) => *card_id,
@@ -1056,12 +1467,11 @@ fn position_cards_in_deck(
 
         let cards_to_position = cards_in_deck
             .filter(
-                |(_card_id, (behaviour, _transform, _in_deck, _travel_time, sleeping))| {
+                |(_card_id, ((behaviour, _transform, _location, _travel_time, sleeping), _))| {
                     sleeping.is_none() && matches!(behaviour, CardPositioningBehaviour::InDeck)
                 },
             )
-            .map(|(card_id, _)| card_id)
-            .cloned()
+            .map(|(card_id, (_, index_from_bottom))| (*card_id, index_from_bottom))
             .collect_vec();
 
         let Ok((_, bottom_card_transform, ..)) = cards_in_decks_query.get(*bottom_card_id) else {
@@ -1070,13 +1480,12 @@ fn position_cards_in_deck(
         };
         let bottom_card_transform = bottom_card_transform.to_owned();
 
-        for card_id in cards_to_position {
+        for (card_id, i) in cards_to_position {
             let Ok(mut card) = cards_in_decks_query.get_mut(card_id) else {
                 warn!("Card not found in deck");
                 continue;
             };
             // get values
-            let i = card.2.index_from_bottom;
             let card_transform = &mut *card.1;
             let travel_time = card.3;
 
@@ -1146,8 +1555,7 @@ fn position_cards_in_hand(
         (
             &CardPositioningBehaviour,
             &mut Transform,
-            &InHand,
-            &BelongsToPlayer,
+            &CardLocation,
             Option<&TravelTime>,
             Option<&Sleeping>,
         ),
@@ -1175,27 +1583,35 @@ fn position_cards_in_hand(
                     let (
                         _card_positioning_behaviour,
                         _card_transform,
-                        in_hand,
-                        belongs_to_player,
+                        location,
                         _travel_start_time,
                         sleeping,
                     ) = card;
 
-                    // Check if the card belongs to the player
-                    if belongs_to_player.0 != *player_id {
+                    // Check if the card is in the player's hand
+                    let CardLocation::Hand {
+                        player,
+                        index_from_left,
+                    } = location
+                    else {
+                        return None;
+                    };
+                    if player != player_id {
                         return None;
                     }
 
-                    Some((card_id, _card_positioning_behaviour, in_hand, sleeping))
+                    Some((
+                        card_id,
+                        _card_positioning_behaviour,
+                        *index_from_left,
+                        sleeping,
+                    ))
                 })
                 .sorted_by_key(
-                    |(_card_id, _card_positioning_behaviour, in_hand, _sleeping)| {
-                        in_hand.index_from_left
+                    |(_card_id, _card_positioning_behaviour, index_from_left, _sleeping)| {
+                        *index_from_left
                     },
                 )
-                .map(|(card_id, card_positioning_behaviour, _, sleeping, ..)| {
-                    (card_id, card_positioning_behaviour, sleeping)
-                })
                 .peekable();
 
             // The first card in hand is the leftmost card
@@ -1211,12 +1627,11 @@ fn position_cards_in_hand(
 
             // Get the cards set to this behaviour
             let cards_to_position = cards_in_hand
-                .filter(|(_card_id, card_positioning_behaviour, sleeping)| {
+                .filter(|(_card_id, card_positioning_behaviour, _, sleeping)| {
                     sleeping.is_none()
                         && matches!(card_positioning_behaviour, CardPositioningBehaviour::InHand)
                 })
-                .map(|(card_id, ..)| card_id)
-                .cloned()
+                .map(|(card_id, _, index_from_left, _)| (*card_id, index_from_left))
                 .collect_vec();
 
             // debug!(
@@ -1226,15 +1641,14 @@ fn position_cards_in_hand(
             // );
 
             let num_cards_to_position = cards_to_position.len() as f32;
-            for card_id in cards_to_position {
+            for (card_id, i) in cards_to_position {
                 let Ok(mut card) = cards_in_hands_query.get_mut(card_id) else {
                     warn!("Card not found in hand");
                     continue;
                 };
                 // get values
-                let i = card.2.index_from_left;
                 let card_transform = &mut *card.1;
-                let travel_time = card.4;
+                let travel_time = card.3;
 
                 // calculate positions
                 let (desired_pos, desired_rot) = match i {
@@ -1292,6 +1706,120 @@ fn position_cards_in_hand(
     }
 }
 
+/// Played cards go in front of whoever played them, taken tricks next to whoever took them,
+/// and the trump card beside the deck.
+fn position_cards_on_table(
+    mut commands: Commands,
+    session_query: Query<&Session>,
+    mut cards_on_table_query: Query<
+        (
+            Entity,
+            &CardPositioningBehaviour,
+            &mut Transform,
+            &CardLocation,
+            &SessionRef,
+            Option<&TravelTime>,
+        ),
+        (With<Card>, Without<Sleeping>),
+    >,
+    table_query: Query<&Transform, (With<Table>, Without<Card>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Card>)>,
+    handles: Res<Handles>,
+) {
+    for card in cards_on_table_query.iter_mut() {
+        let (card_id, behaviour, mut card_transform, location, session_ref, travel_time) = card;
+        let Ok(session) = session_query.get(**session_ref) else {
+            continue;
+        };
+        let Ok(table_transform) = table_query.get(session.table_id) else {
+            warn!("Table not found for card positioning");
+            continue;
+        };
+        let table_surface = table_transform.translation
+            + Vec3::Y * (handles.table_shape.half_height + handles.card_shape.half_size.y);
+
+        // Flat direction from the middle of the table towards a player
+        let towards = |player_id: &Entity| {
+            player_query.get(*player_id).ok().map(|player_transform| {
+                let offset = player_transform.translation - table_transform.translation;
+                Vec3::new(offset.x, 0.0, offset.z).normalize_or_zero()
+            })
+        };
+
+        // calculate positions
+        let (desired_pos, desired_rot) = match (behaviour, location) {
+            (CardPositioningBehaviour::Played, CardLocation::Trick { index, played_by }) => {
+                let Some(direction) = towards(played_by) else {
+                    warn!("Player not found for played card");
+                    continue;
+                };
+                (
+                    table_surface + direction * 0.7 + Vec3::Y * *index as f32 * 0.01,
+                    Quat::from_rotation_y(direction.x.atan2(direction.z)),
+                )
+            }
+            (
+                CardPositioningBehaviour::InTakenTrick,
+                CardLocation::Taken {
+                    player,
+                    trick,
+                    index,
+                },
+            ) => {
+                let Some(direction) = towards(player) else {
+                    warn!("Player not found for taken trick");
+                    continue;
+                };
+                // each trick is a little pile, the piles in a row to the player's right
+                let right = direction.cross(Vec3::Y);
+                (
+                    table_surface
+                        + direction * 1.5
+                        + right * (0.3 + *trick as f32 * 0.15)
+                        + Vec3::Y * *index as f32 * 0.01,
+                    Quat::from_rotation_y(direction.x.atan2(direction.z) + PI / 2.0),
+                )
+            }
+            (CardPositioningBehaviour::RevealedOnDeck, CardLocation::TrumpReveal) => (
+                table_surface + Vec3::X * 0.4,
+                Quat::from_rotation_y(PI / 2.0),
+            ),
+            _ => continue,
+        };
+
+        let current_pos = card_transform.translation;
+        let current_rot = card_transform.rotation;
+
+        // get or set travel start time
+        let travel_start_time = match travel_time {
+            Some(travel_time) => travel_time.start_time.to_owned(),
+            None => {
+                let now = Instant::now();
+                commands
+                    .entity(card_id)
+                    .insert(TravelTime { start_time: now });
+                now
+            }
+        };
+
+        // calculate progress
+        let progress = travel_start_time.elapsed().as_secs_f32();
+        let progress = progress.min(1.0);
+        let progress = progress.powf(0.5);
+
+        // update card position
+        card_transform.translation = current_pos.lerp(desired_pos, progress);
+        card_transform.rotation = current_rot.slerp(desired_rot, progress);
+
+        if progress >= 0.99 {
+            commands.entity(card_id).remove::<TravelTime>();
+            commands.entity(card_id).insert(Sleeping {
+                start_time: Instant::now(),
+            });
+        }
+    }
+}
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -1299,10 +1827,11 @@ fn setup(
     mut reset_events: EventWriter<SpawnSessionEvent>,
     mut handles: ResMut<Handles>,
     asset_server: Res<AssetServer>,
+    launch_config: Res<LaunchConfig>,
 ) {
     // Prepare table handles
     handles.table_shape = Cylinder::new(2.0, 1.0);
-    handles.table_mesh = meshes.add(handles.table_shape.clone());
+    handles.table_mesh = meshes.add(handles.table_shape);
     handles.table_material = materials.add(StandardMaterial {
         base_color: Color::rgb(0.8, 0.7, 0.6),
         ..default()
@@ -1310,7 +1839,7 @@ fn setup(
 
     // Prepare money jar handles
     handles.coin_shape = Cylinder::new(0.02, 0.005);
-    handles.coin_mesh = meshes.add(handles.coin_shape.clone());
+    handles.coin_mesh = meshes.add(handles.coin_shape);
     handles.coin_material = materials.add(StandardMaterial {
         base_color: Color::rgb(0.8, 0.9, 0.9),
         ..default()
@@ -1323,7 +1852,7 @@ fn setup(
     let card_width = 0.3;
     let card_height = card_width * card_aspect;
     handles.card_shape = Cuboid::new(card_height, 0.005, card_width);
-    handles.card_mesh = meshes.add(handles.card_shape.clone());
+    handles.card_mesh = meshes.add(handles.card_shape);
     handles.card_materials = Card::get_new_deck()
         .into_iter()
         .map(|card| {
@@ -1338,28 +1867,30 @@ fn setup(
             )
         })
         .collect();
+    handles.card_back_material = materials.add(StandardMaterial {
+        base_color_texture: Some(asset_server.load("cards/back.webp")),
+        alpha_mode: AlphaMode::Mask(0.5),
+        ..default()
+    });
 
     // Prepare player handles
     handles.player_body_shape = Capsule3d::new(0.2, 0.5);
-    handles.player_body_mesh = meshes.add(handles.player_body_shape.clone());
-    handles.player_body_material = materials.add(StandardMaterial {
-        base_color: Color::rgb(0.0, 0.0, 1.0),
-        ..default()
-    });
+    handles.player_body_mesh = meshes.add(handles.player_body_shape);
     handles.player_eye_shape = Sphere::new(0.05);
-    handles.player_eye_mesh = meshes.add(handles.player_eye_shape.clone());
+    handles.player_eye_mesh = meshes.add(handles.player_eye_shape);
     handles.player_eye_material = materials.add(StandardMaterial {
         base_color: Color::rgb(1.0, 1.0, 1.0),
         ..default()
     });
 
-    // Prepare ahoy handles
-    handles.ahoy_mesh = meshes.add(create_mesh("ahoy!"));
-    handles.ahoy_material = materials.add(StandardMaterial {
-        base_color: Color::BLUE,
+    // Prepare nametag handles
+    handles.nametag_material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
         unlit: true,
         ..default()
     });
+    handles.avatar_shape = Rectangle::new(0.3, 0.3);
+    handles.avatar_mesh = meshes.add(handles.avatar_shape);
 
     // Spawn camera
     commands.spawn((
@@ -1385,6 +1916,7 @@ fn setup(
             pan_speed: 25.0,
             ..default()
         },
+        SeatCamera::default(),
     ));
 
     // Spawn ground
@@ -1403,8 +1935,43 @@ fn setup(
         Name::new("Ground"),
     ));
 
-    // Spawn table
-    reset_events.send(SpawnSessionEvent { num_players: 5 });
+    // Spawn tables, the saved ones when resuming
+    let resumed = match &launch_config.resume {
+        Some(path) => save::load(path),
+        None => Vec::new(),
+    };
+    match resumed.is_empty() {
+        true => {
+            for i in 0..launch_config.num_tables {
+                reset_events.send(launch_config.session_event(i));
+            }
+        }
+        false => {
+            info!("Resuming {} tables", resumed.len());
+            reset_events.send_batch(resumed);
+        }
+    }
+}
+
+fn load_profiles(mut commands: Commands, launch_config: Res<LaunchConfig>) {
+    let profiles = Profiles::load_or_create(&launch_config.profiles);
+    info!("Loaded {} player profiles", profiles.profiles.len());
+    commands.insert_resource(profiles);
+}
+
+/// Write the profiles file once profiles are made for new seats, so the stats saved under them
+/// can be named.
+fn save_new_profiles(profiles: Res<Profiles>, launch_config: Res<LaunchConfig>) {
+    if !profiles.is_changed() || profiles.is_added() {
+        return;
+    }
+    match profiles.save(&launch_config.profiles) {
+        Ok(()) => info!("Saved {} player profiles", profiles.profiles.len()),
+        Err(e) => warn!(
+            "Failed to save profiles file {:?}: {e}",
+            launch_config.profiles
+        ),
+    }
 }
 
 // from: https://github.com/ForTehLose/bevy_meshtext/blob/master/src/main.rs
@@ -1414,13 +1981,25 @@ fn create_mesh(text: &str) -> Mesh {
         RenderAssetUsages::default(),
     );
 
-    let mesh_data = get_text_vertices(&text);
+    let mesh_data = get_text_vertices(text);
     let chunks = mesh_data
         .vertices
         .chunks(3)
         .map(|c| <[_; 3]>::try_from(c).unwrap())
         .collect::<Vec<_>>();
 
+    // Centre the text horizontally on its origin
+    let (min_x, max_x) = chunks
+        .iter()
+        .map(|[x, ..]| *x)
+        .minmax()
+        .into_option()
+        .unwrap_or_default();
+    let chunks = chunks
+        .into_iter()
+        .map(|[x, y, z]| [x - (min_x + max_x) / 2.0, y, z])
+        .collect::<Vec<_>>();
+
     cube_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, chunks);
     cube_mesh.insert_indices(Indices::U32(mesh_data.indices));
     cube_mesh
@@ -1431,10 +2010,9 @@ fn get_text_vertices(text: &str) -> IndexedMeshText {
     let font_data = include_bytes!("../assets/fonts/Crunchyfont.ttf");
     let mut generator = MeshGenerator::new_without_cache(font_data, QualitySettings::default());
 
-    let thing = generator
-        .generate_section(&text, true, None)
-        .expect("Failed to generate glyph.");
-    return thing;
+    generator
+        .generate_section(text, true, None)
+        .expect("Failed to generate glyph.")
 }
 
 fn handle_sleeping_key_press(
@@ -1452,57 +2030,80 @@ fn handle_sleeping_key_press(
     }
 }
 
+/// Stop the game clock, and with it bots and turn timers.
+fn handle_pause_key_press(input: Res<ButtonInput<KeyCode>>, mut time: ResMut<Time<Virtual>>) {
+    if input.just_pressed(KeyCode::KeyP) {
+        match time.is_paused() {
+            true => time.unpause(),
+            false => time.pause(),
+        }
+        info!("Game clock paused: {}", time.is_paused());
+    }
+}
+
+/// Move every card of the focused session back into its deck.
 fn handle_shuffle_back_in_key_press(
     mut commands: Commands,
     input: Res<ButtonInput<KeyCode>>,
-    session_query: Query<&Session>,
+    focused: Res<FocusedSession>,
+    session_query: Query<(&Session, Has<TableGame>)>,
 ) {
     if input.just_pressed(KeyCode::KeyR) {
-        // Move all cards to the deck
-        for session in session_query.iter() {
-            let mut count = 0;
-            for (i, card_id) in session.card_ids.iter().enumerate() {
-                // Remove from play
-                commands.entity(*card_id).remove::<InHand>();
-                commands.entity(*card_id).remove::<Played>();
-                commands.entity(*card_id).remove::<Trump>();
-                commands.entity(*card_id).remove::<BelongsToPlayer>();
-
-                // Add to deck
-                commands.entity(*card_id).insert(InDeck {
+        let Some((session, has_game)) = focused
+            .0
+            .and_then(|session_id| session_query.get(session_id).ok())
+        else {
+            warn!("No table focused to shuffle back in, click one");
+            return;
+        };
+        // The cards at a table with a game follow the game, and would go back where they were
+        // only once the next move is made.
+        if has_game {
+            warn!(
+                "Table {} is playing a game, the cards stay dealt",
+                session.number
+            );
+            return;
+        }
+        // Sorted first so the same seed always stacks the deck the same way.
+        let mut card_ids = session.card_ids.iter().copied().sorted().collect_vec();
+        card_ids.shuffle(&mut StdRng::seed_from_u64(session.seed));
+        for (i, card_id) in card_ids.iter().enumerate() {
+            move_card(
+                &mut commands,
+                *card_id,
+                CardLocation::Deck {
                     index_from_bottom: i,
-                });
-
-                // Wake up
-                commands.entity(*card_id).remove::<Sleeping>();
-
-                count += 1;
-            }
-            info!("Moved {} cards back into the deck", count);
+                },
+            );
         }
+        info!("Moved {} cards back into the deck", card_ids.len());
     }
 }
 
 fn update_card_names(
-    mut card_query: Query<(&mut Name, Option<&InHand>, Option<&InDeck>), With<Card>>,
+    mut card_query: Query<(&mut Name, &CardLocation), (With<Card>, Changed<CardLocation>)>,
 ) {
     for card in card_query.iter_mut() {
-        let (mut name, in_hand, in_deck) = card;
-        *name = Name::new(format!("Card ({in_hand:?},{in_deck:?})"));
+        let (mut name, location) = card;
+        *name = Name::new(format!("Card ({location:?})"));
     }
 }
 
 fn handle_kill_session_key_press(
     input: Res<ButtonInput<KeyCode>>,
     mut kill_session_events: EventWriter<KillSessionEvent>,
+    focused: Res<FocusedSession>,
     session_query: Query<Entity, With<Session>>,
 ) {
     if input.just_pressed(KeyCode::Minus) {
-        let Some(session) = session_query.iter().next() else {
-            warn!("Failed to find any sessions to kill");
+        let Some(session_id) = focused
+            .0
+            .filter(|session_id| session_query.contains(*session_id))
+        else {
+            warn!("No table focused to kill, click one");
             return;
         };
-        let session_id = session;
 
         info!("Killing session {session_id:?}");
         kill_session_events.send(KillSessionEvent { session_id });
@@ -1512,13 +2113,21 @@ fn handle_kill_session_key_press(
 fn handle_new_table_key_press(
     input: Res<ButtonInput<KeyCode>>,
     mut spawn_session_events: EventWriter<SpawnSessionEvent>,
+    launch_config: Res<LaunchConfig>,
 ) {
     if input.just_pressed(KeyCode::Equal) {
         let min_players = 2;
         let max_players = 6;
         let num_players = rand::thread_rng().gen_range(min_players..=max_players);
         info!("Spawning new table with {} players", num_players);
-        spawn_session_events.send(SpawnSessionEvent { num_players });
+        spawn_session_events.send(SpawnSessionEvent {
+            seats: SeatKind::default_seats(num_players, launch_config.bot_style),
+            rules: launch_config.rules.clone(),
+            seed: None,
+            dealer: None,
+            decks: Vec::new(),
+            moves: Vec::new(),
+        });
     }
 }
 
@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+
+use crate::bots::Rationale;
+use crate::bots::Strategy;
+use crate::dataset::action_index;
+use crate::dataset::features;
+use crate::dataset::FEATURES_VERSION;
+use crate::dataset::NUM_ACTIONS;
+use crate::dataset::NUM_FEATURES;
+use crate::game::Action;
+use crate::game::SeatView;
+use crate::Card;
+
+/// Models bigger than this could take longer than a frame to run on a slow machine.
+pub const MAX_PARAMETERS: usize = 4_000_000;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Activation {
+    Linear,
+    Relu,
+    Tanh,
+}
+
+/// A fully connected layer, `weights` has a row per output.
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct Layer {
+    pub weights: Vec<Vec<f32>>,
+    pub biases: Vec<f32>,
+    pub activation: Activation,
+}
+
+impl Layer {
+    fn inputs(&self) -> usize {
+        self.weights.first().map_or(0, |row| row.len())
+    }
+
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        self.weights
+            .iter()
+            .zip(self.biases.iter())
+            .map(|(row, bias)| {
+                let sum = row.iter().zip(input).map(|(w, x)| w * x).sum::<f32>() + bias;
+                match self.activation {
+                    Activation::Linear => sum,
+                    Activation::Relu => sum.max(0.0),
+                    Activation::Tanh => sum.tanh(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A policy network, loaded from JSON like
+/// `{"features_version":1,"layers":[{"weights":[[..],..],"biases":[..],"activation":"relu"},..]}`.
+///
+/// It takes the [`features`] of a seat's view and scores each of the [`NUM_ACTIONS`] actions
+/// as numbered by [`action_index`], the best scoring legal action is taken.
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct Model {
+    pub features_version: u32,
+    pub layers: Vec<Layer>,
+}
+
+impl Model {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let model: Model = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// Check the layers fit together and match the feature layout.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.features_version != FEATURES_VERSION {
+            return Err(format!(
+                "the model was trained on features version {}, this is version {FEATURES_VERSION}",
+                self.features_version
+            ));
+        }
+        let mut inputs = NUM_FEATURES;
+        for (i, layer) in self.layers.iter().enumerate() {
+            if layer.inputs() != inputs || layer.weights.iter().any(|row| row.len() != inputs) {
+                return Err(format!("layer {i} should take {inputs} inputs"));
+            }
+            if layer.biases.len() != layer.weights.len() {
+                return Err(format!("layer {i} needs a bias per output"));
+            }
+            inputs = layer.weights.len();
+        }
+        if inputs != NUM_ACTIONS {
+            return Err(format!("the last layer should have {NUM_ACTIONS} outputs"));
+        }
+        let parameters: usize = self
+            .layers
+            .iter()
+            .map(|layer| layer.weights.len() * (layer.inputs() + 1))
+            .sum();
+        if parameters > MAX_PARAMETERS {
+            return Err(format!(
+                "{parameters} parameters is too many, the limit is {MAX_PARAMETERS}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// A score for every action.
+    pub fn scores(&self, view: &SeatView) -> Vec<f32> {
+        self.layers
+            .iter()
+            .fold(features(view), |input, layer| layer.forward(&input))
+    }
+
+    /// The best scoring of `legal`, illegal actions never get a look in.
+    fn choose(&self, view: &SeatView, legal: impl IntoIterator<Item = Action>) -> Option<Action> {
+        let scores = self.scores(view);
+        legal
+            .into_iter()
+            .map(|action| (action, scores[action_index(action)]))
+            // The first of any tied actions, so the same view always gets the same answer
+            .reduce(|best, next| match next.1 > best.1 {
+                true => next,
+                false => best,
+            })
+            .map(|(action, _)| action)
+    }
+}
+
+/// Plays with a trained [`Model`], on the CPU.
+#[derive(Component)]
+pub struct ModelBot {
+    pub model: Model,
+}
+
+impl Strategy for ModelBot {
+    fn name(&self) -> &'static str {
+        "model"
+    }
+    fn bid(
+        &self,
+        view: &SeatView,
+        legal: &[usize],
+        _rng: &mut StdRng,
+    ) -> (usize, Option<Rationale>) {
+        match self
+            .model
+            .choose(view, legal.iter().copied().map(Action::Bid))
+        {
+            Some(Action::Bid(bid)) => (bid, None),
+            _ => panic!("there is always a legal bid"),
+        }
+    }
+    fn play(
+        &self,
+        view: &SeatView,
+        legal: &[Card],
+        _rng: &mut StdRng,
+    ) -> (Card, Option<Rationale>) {
+        match self
+            .model
+            .choose(view, legal.iter().copied().map(Action::Play))
+        {
+            Some(Action::Play(card)) => (card, None),
+            _ => panic!("there is always a legal play"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::bots::choose_action;
+    use crate::dataset;
+    use crate::game::Game;
+    use crate::properties::games;
+    use crate::properties::no_check;
+    use crate::properties::play_game;
+
+    /// A model with one hidden layer and random weights.
+    fn random_model(seed: u64) -> Model {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut layer = |inputs: usize, outputs: usize, activation: Activation| Layer {
+            weights: (0..outputs)
+                .map(|_| (0..inputs).map(|_| rng.gen_range(-1.0..1.0)).collect())
+                .collect(),
+            biases: (0..outputs).map(|_| rng.gen_range(-1.0..1.0)).collect(),
+            activation,
+        };
+        Model {
+            features_version: dataset::FEATURES_VERSION,
+            layers: vec![
+                layer(NUM_FEATURES, 16, Activation::Relu),
+                layer(16, NUM_ACTIONS, Activation::Linear),
+            ],
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn model_bots_make_the_legal_move_scored_highest((num_players, seed, moves_seed, rules) in games()) {
+            let model = random_model(moves_seed);
+            prop_assert_eq!(model.validate(), Ok(()));
+            let bot = ModelBot { model: model.clone() };
+            let mut game = Game::new(rules, num_players, seed);
+            let mut rng = StdRng::seed_from_u64(moves_seed);
+            let choose = |game: &Game, seat: usize| {
+                let (_, action, _) = choose_action(&bot, game, &mut rng).expect("a seat to act");
+                let scores = model.scores(&game.round.view(seat));
+                let best = game
+                    .legal_actions()
+                    .into_iter()
+                    .map(|legal| scores[action_index(legal)])
+                    .fold(f32::NEG_INFINITY, f32::max);
+                prop_assert_eq!(scores[action_index(action)], best, "{:?}", action);
+                Ok(action)
+            };
+            play_game(&mut game, choose, no_check)?;
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::bots::choose_action;
+use crate::bots::HeuristicStrategy;
+use crate::bots::Rationale;
+use crate::bots::Strategy;
+use crate::cli::LaunchConfig;
+use crate::focus::FocusedSession;
+use crate::game::Action;
+use crate::game::Game;
+use crate::game::Phase;
+use crate::game_log::describe_action;
+use crate::game_log::describe_start;
+use crate::game_log::GameLog;
+use crate::game_log::LogKind;
+use crate::hotseat::HotSeat;
+use crate::model::Model;
+use crate::model::ModelBot;
+use crate::move_card;
+use crate::profiles::ProfileId;
+use crate::protocol::ProcessBot;
+use crate::rules::RuleError;
+use crate::scorekeeper::digit;
+use crate::stats::GameFinishedEvent;
+use crate::stats::PlayerGameResult;
+use crate::Card;
+use crate::CardLocation;
+use crate::Dealer;
+use crate::NeedsDealer;
+use crate::Player;
+use crate::Seat;
+use crate::SeatKind;
+use crate::Session;
+use crate::Table;
+
+/// How long a bot waits before acting, so people can follow along.
+pub const BOT_DELAY: Duration = Duration::from_millis(700);
+
+/// Plays a game at every table once its dealer is chosen.
+///
+/// Bots take their turns on their own, humans at the focused table bid and play with the number
+/// keys. Outside ranked games, Backspace takes back a lone human's last move there, and whatever the
+/// bots did since.
+pub struct PlayPlugin;
+impl Plugin for PlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ActionEvent>();
+        app.add_event::<RejectedActionEvent>();
+        app.add_event::<IllegalMoveEvent>();
+        app.add_event::<GameFinishedEvent>();
+        app.init_resource::<GameLog>();
+        app.init_resource::<FocusedSession>();
+        app.add_systems(
+            Update,
+            (
+                start_games,
+                handle_undo_key_press,
+                take_bot_turns,
+                handle_action_key_presses,
+                apply_actions,
+                sync_card_locations,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// The game being played at a session's table, on the session entity.
+#[derive(Component, Debug, Clone)]
+pub struct TableGame {
+    pub game: Game,
+    /// Player entities by seat.
+    pub seats: Vec<Entity>,
+    bot_delay: Timer,
+    rng: StdRng,
+}
+
+impl TableGame {
+    /// The seat expected to act and the player sitting there.
+    pub fn to_act(&self) -> Option<(usize, Entity)> {
+        let seat = self.game.phase().seat()?;
+        Some((seat, self.seats[seat]))
+    }
+
+    /// A seat's hand as it's held, by suit then rank.
+    pub fn sorted_hand(&self, seat: usize) -> Vec<Card> {
+        self.game.round.hands[seat]
+            .iter()
+            .copied()
+            .sorted_by_key(|card| (card.suit as u8, card.rank.value()))
+            .collect()
+    }
+
+    /// Where each card of the current round belongs, the rest are in the deck.
+    pub fn card_locations(&self) -> HashMap<Card, CardLocation> {
+        let round = &self.game.round;
+        let mut locations = HashMap::default();
+        for (seat, player_id) in self.seats.iter().enumerate() {
+            for (i, card) in self.sorted_hand(seat).into_iter().enumerate() {
+                let location = CardLocation::Hand {
+                    player: *player_id,
+                    index_from_left: i,
+                };
+                locations.insert(card, location);
+            }
+        }
+        for (i, (seat, card)) in round.trick.cards.iter().enumerate() {
+            let location = CardLocation::Trick {
+                index: i,
+                played_by: self.seats[*seat],
+            };
+            locations.insert(*card, location);
+        }
+        for (trick_index, trick) in round.tricks.iter().enumerate() {
+            let (winner, _) = trick
+                .winner(round.trump())
+                .expect("finished tricks have a winner");
+            for (i, (_, card)) in trick.cards.iter().enumerate() {
+                let location = CardLocation::Taken {
+                    player: self.seats[winner],
+                    trick: trick_index,
+                    index: i,
+                };
+                locations.insert(*card, location);
+            }
+        }
+        if let Some(card) = round.trump_card {
+            locations.insert(card, CardLocation::TrumpReveal);
+        }
+        locations
+    }
+}
+
+/// The table as it was before the lone human's last move, until the game ends.
+#[derive(Component, Debug, Clone)]
+pub struct TakeBack {
+    pub before: TableGame,
+}
+
+/// Holds a table to a script, e.g. a tutorial's.
+#[derive(Component, Debug, Clone, PartialEq, Default)]
+pub struct Scripted {
+    /// Bots wait while this is set.
+    pub paused: bool,
+    /// The only actions humans may take, anything legal when `None`.
+    pub allowed: Option<Vec<Action>>,
+}
+
+/// Plays a seat with any strategy, in place of the one its [`SeatKind`] would, e.g. for a
+/// tournament entry.
+#[derive(Component, Clone)]
+pub struct SeatStrategy(pub Arc<dyn Strategy>);
+
+/// A seat at a table bids or plays.
+#[derive(Event, Debug, Clone)]
+pub struct ActionEvent {
+    pub session_id: Entity,
+    pub seat: usize,
+    pub action: Action,
+    /// Why a bot took the action.
+    pub rationale: Option<Rationale>,
+}
+
+/// An action the rules turned down, so the player can be told why.
+#[derive(Event, Debug, Clone)]
+pub struct RejectedActionEvent {
+    pub session_id: Entity,
+    pub seat: usize,
+    pub action: Action,
+    pub error: RuleError,
+}
+
+/// A bot chose a move the rules don't allow, and the first legal move was made for it instead.
+#[derive(Event, Debug, Clone)]
+pub struct IllegalMoveEvent {
+    pub session_id: Entity,
+    pub seat: usize,
+}
+
+impl fmt::Display for RejectedActionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            Action::Bid(bid) => format!("bid {bid}"),
+            Action::Play(card) => format!("play {card}"),
+        };
+        write!(f, "Seat {} can't {action}: {}", self.seat + 1, self.error)
+    }
+}
+
+pub fn start_games(
+    mut commands: Commands,
+    launch_config: Res<LaunchConfig>,
+    session_query: Query<(Entity, &Session), Without<TableGame>>,
+    table_query: Query<(), (With<Table>, Without<NeedsDealer>)>,
+    player_query: Query<(Entity, &Seat, Has<Dealer>, &SeatKind), With<Player>>,
+    mut game_log: ResMut<GameLog>,
+) {
+    for (session_id, session) in session_query.iter() {
+        if !table_query.contains(session.table_id) || session.card_ids.len() < 52 {
+            continue;
+        }
+        let seats = session
+            .player_ids
+            .iter()
+            .filter_map(|player_id| player_query.get(*player_id).ok())
+            .sorted_by_key(|(_, seat, ..)| seat.0)
+            .collect_vec();
+        let Some(dealer) = seats.iter().position(|(_, _, is_dealer, _)| *is_dealer) else {
+            continue;
+        };
+
+        // Bots that fail to start or load are played by the heuristic bot instead
+        let process_seats = seats
+            .iter()
+            .filter(|(.., kind)| **kind == SeatKind::Process);
+        for (i, (player_id, seat, ..)) in process_seats.enumerate() {
+            let Some(command) = launch_config.bot_command(i) else {
+                warn!("No bot command for seat {}", seat.0);
+                continue;
+            };
+            match ProcessBot::spawn(command, launch_config.bot_timeout) {
+                Ok(bot) => {
+                    commands.entity(*player_id).insert(bot);
+                }
+                Err(e) => warn!("Couldn't start bot {command:?} for seat {}: {e}", seat.0),
+            }
+        }
+        if seats.iter().any(|(.., kind)| **kind == SeatKind::Model)
+            && let Some(path) = &launch_config.model
+        {
+            match Model::load(path) {
+                Ok(model) => {
+                    let model_seats = seats.iter().filter(|(.., kind)| **kind == SeatKind::Model);
+                    for (player_id, ..) in model_seats {
+                        commands.entity(*player_id).insert(ModelBot {
+                            model: model.clone(),
+                        });
+                    }
+                }
+                Err(e) => warn!("Couldn't load the model from {path:?}: {e}"),
+            }
+        }
+
+        let mut game = Game::with_decks(
+            session.rules.clone(),
+            seats.len(),
+            dealer,
+            session.seed,
+            session.decks.clone(),
+        );
+        game_log.extend(session_id, describe_start(&game));
+        for (seat, action) in session.moves.iter().copied() {
+            if let Err(e) = game.apply(seat, action) {
+                warn!(
+                    "Resumed game in session {session_id:?} stops at seat {seat} {action:?}: {e}"
+                );
+                break;
+            }
+        }
+        commands.entity(session_id).insert(TableGame {
+            game,
+            seats: seats.iter().map(|(player_id, ..)| *player_id).collect(),
+            bot_delay: Timer::new(BOT_DELAY, TimerMode::Once),
+            rng: StdRng::seed_from_u64(session.seed),
+        });
+        info!("Game started in session {session_id:?}, seat {dealer} deals first");
+    }
+}
+
+fn take_bot_turns(
+    time: Res<Time>,
+    mut game_query: Query<(Entity, &mut TableGame, Option<&Scripted>)>,
+    seat_kind_query: Query<(
+        &SeatKind,
+        Option<&SeatStrategy>,
+        Option<&ProcessBot>,
+        Option<&ModelBot>,
+    )>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut illegal_move_events: EventWriter<IllegalMoveEvent>,
+) {
+    for (session_id, mut table_game, scripted) in game_query.iter_mut() {
+        if scripted.is_some_and(|scripted| scripted.paused) {
+            continue;
+        }
+        let Some((_, player_id)) = table_game.to_act() else {
+            continue;
+        };
+        let Ok((seat_kind, seat_strategy, process_bot, model_bot)) = seat_kind_query.get(player_id)
+        else {
+            continue;
+        };
+        let strategy: &dyn Strategy = match (seat_kind, seat_strategy, process_bot, model_bot) {
+            (SeatKind::Human, ..) => continue,
+            (_, Some(seat_strategy), ..) => &*seat_strategy.0,
+            (_, _, Some(process_bot), _) => process_bot,
+            (.., Some(model_bot)) => model_bot,
+            (SeatKind::Bot(style), ..) => style,
+            // Failed to start, see start_games
+            (SeatKind::Process | SeatKind::Model, None, None, None) => &HeuristicStrategy,
+        };
+        // Waiting and thinking don't change the game, so don't wake up whatever watches it
+        let table_game = table_game.bypass_change_detection();
+        if !table_game.bot_delay.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let chosen = match process_bot {
+            // Another program answers in its own time, so look for its answer every frame
+            Some(process_bot) => process_bot.poll_action(&table_game.game, &mut table_game.rng),
+            None => choose_action(strategy, &table_game.game, &mut table_game.rng),
+        };
+        if let Some((seat, mut action, mut rationale)) = chosen {
+            let legal = table_game.game.legal_actions();
+            if !legal.contains(&action) {
+                warn!(
+                    "{} made an illegal move: {action:?}, making {:?} for it",
+                    strategy.name(),
+                    legal[0]
+                );
+                illegal_move_events.send(IllegalMoveEvent { session_id, seat });
+                action = legal[0];
+                rationale = None;
+            }
+            action_events.send(ActionEvent {
+                session_id,
+                seat,
+                action,
+                rationale,
+            });
+        }
+    }
+}
+
+/// A number bids that many tricks at the focused table, or plays that card counting from the left
+/// of the hand.
+fn handle_action_key_presses(
+    input: Res<ButtonInput<KeyCode>>,
+    focused: Res<FocusedSession>,
+    game_query: Query<(Entity, &TableGame, Option<&Scripted>, Option<&HotSeat>)>,
+    seat_kind_query: Query<&SeatKind>,
+    mut action_events: EventWriter<ActionEvent>,
+) {
+    let Some(number) = input.get_just_pressed().find_map(|key| digit(*key)) else {
+        return;
+    };
+    let Some((session_id, table_game, scripted, hot_seat)) = focused
+        .0
+        .and_then(|session_id| game_query.get(session_id).ok())
+    else {
+        return;
+    };
+    let Some((seat, player_id)) = table_game.to_act() else {
+        return;
+    };
+    if seat_kind_query.get(player_id) != Ok(&SeatKind::Human) || !HotSeat::lets_act(hot_seat, seat)
+    {
+        return;
+    }
+    let action = match table_game.game.phase() {
+        Phase::Bidding { .. } => Action::Bid(number),
+        Phase::Playing { .. } => {
+            let Some(card) = number
+                .checked_sub(1)
+                .and_then(|i| table_game.sorted_hand(seat).get(i).copied())
+            else {
+                return;
+            };
+            Action::Play(card)
+        }
+        Phase::RoundOver | Phase::GameOver => return,
+    };
+    if let Some(allowed) = scripted.and_then(|scripted| scripted.allowed.as_ref())
+        && !allowed.contains(&action)
+    {
+        info!("Not now, the script is waiting for one of {allowed:?}");
+        return;
+    }
+    action_events.send(ActionEvent {
+        session_id,
+        seat,
+        action,
+        rationale: None,
+    });
+}
+
+/// Undo the last move at each table that can take it back.
+fn handle_undo_key_press(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    focused: Res<FocusedSession>,
+    mut game_query: Query<(Entity, &mut TableGame, &TakeBack)>,
+    mut game_log: ResMut<GameLog>,
+) {
+    if !input.just_pressed(KeyCode::Backspace) {
+        return;
+    }
+    let Some((session_id, mut table_game, take_back)) = focused
+        .0
+        .and_then(|session_id| game_query.get_mut(session_id).ok())
+    else {
+        return;
+    };
+    commands.entity(session_id).remove::<TakeBack>();
+    let moves = &table_game.game.log[take_back.before.game.log.len()..];
+    let Some((seat, action)) = moves.first().copied() else {
+        return;
+    };
+    let since = moves.len() - 1;
+    *table_game = take_back.before.clone();
+    table_game.bot_delay.reset();
+    info!("Seat {seat} took back {action:?} and {since} moves since in session {session_id:?}");
+    let action = match action {
+        Action::Bid(bid) => format!("their bid of {bid}"),
+        Action::Play(card) => card.to_string(),
+    };
+    let since = match since {
+        0 => String::new(),
+        1 => " and the move after it".to_owned(),
+        since => format!(" and the {since} moves after it"),
+    };
+    game_log.push(
+        session_id,
+        LogKind::Undo,
+        format!("Seat {} takes back {action}{since}", seat + 1),
+    );
+}
+
+/// Whether humans at the table may take back moves: a casual game with one human, and no
+/// external bots that would have to forget what they saw.
+fn can_take_back(launch_config: &LaunchConfig, scripted: bool, seat_kinds: &[&SeatKind]) -> bool {
+    let humans = seat_kinds
+        .iter()
+        .filter(|kind| ***kind == SeatKind::Human)
+        .count();
+    !launch_config.ranked && !scripted && humans == 1 && !seat_kinds.contains(&&SeatKind::Process)
+}
+
+fn apply_actions(
+    mut commands: Commands,
+    launch_config: Res<LaunchConfig>,
+    mut action_events: EventReader<ActionEvent>,
+    mut rejected_action_events: EventWriter<RejectedActionEvent>,
+    mut game_query: Query<(&mut TableGame, Has<Scripted>)>,
+    seat_kind_query: Query<&SeatKind>,
+    profile_query: Query<&ProfileId>,
+    process_bot_query: Query<&ProcessBot>,
+    seat_strategy_query: Query<&SeatStrategy>,
+    mut game_log: ResMut<GameLog>,
+    mut game_finished_events: EventWriter<GameFinishedEvent>,
+) {
+    for event in action_events.read() {
+        let Ok((mut table_game, scripted)) = game_query.get_mut(event.session_id) else {
+            warn!("No game in session {:?} for {:?}", event.session_id, event);
+            continue;
+        };
+        let before = table_game.clone();
+        if let Err(error) = table_game.game.apply(event.seat, event.action) {
+            info!("Seat {} can't {:?}: {error}", event.seat, event.action);
+            rejected_action_events.send(RejectedActionEvent {
+                session_id: event.session_id,
+                seat: event.seat,
+                action: event.action,
+                error,
+            });
+            continue;
+        }
+        info!("Seat {} made {:?}", event.seat, event.action);
+        if let Some(rationale) = &event.rationale {
+            table_game.game.explain_last(rationale.clone());
+        }
+        game_log.extend(
+            event.session_id,
+            describe_action(
+                &before.game,
+                event.seat,
+                event.action,
+                event.rationale.as_ref(),
+                &table_game.game,
+            ),
+        );
+        for process_bot in process_bot_query.iter_many(&table_game.seats) {
+            process_bot.observe(&before.game.round, event.seat, event.action);
+        }
+        for seat_strategy in seat_strategy_query.iter_many(&table_game.seats) {
+            seat_strategy
+                .0
+                .observe(&before.game.round, event.seat, event.action);
+        }
+        table_game.bot_delay.reset();
+
+        let seat_kinds = seat_kind_query.iter_many(&table_game.seats).collect_vec();
+        let human = seat_kinds.get(event.seat) == Some(&&SeatKind::Human);
+        // The bots' moves are taken back with the human's before them, so they don't end it
+        if table_game.game.is_over()
+            || human && !can_take_back(&launch_config, scripted, &seat_kinds)
+        {
+            commands.entity(event.session_id).remove::<TakeBack>();
+        } else if human {
+            commands
+                .entity(event.session_id)
+                .insert(TakeBack { before });
+        }
+
+        if table_game.game.is_over() {
+            let game = &table_game.game;
+            let players = table_game
+                .seats
+                .iter()
+                .enumerate()
+                .filter_map(|(seat, player_id)| {
+                    Some(PlayerGameResult {
+                        profile_id: *profile_query.get(*player_id).ok()?,
+                        rounds: game.results[seat].clone(),
+                        money_cents: game.ledger.money_cents[seat],
+                    })
+                })
+                .collect();
+            game_finished_events.send(GameFinishedEvent { players });
+            info!("Game over in session {:?}", event.session_id);
+        }
+    }
+}
+
+/// Move the cards to match the game whenever it changes.
+fn sync_card_locations(
+    mut commands: Commands,
+    game_query: Query<(&Session, &TableGame), Changed<TableGame>>,
+    card_query: Query<(Entity, &Card, &CardLocation)>,
+) {
+    for (session, table_game) in game_query.iter() {
+        let mut locations = table_game.card_locations();
+        let cards = session
+            .card_ids
+            .iter()
+            .filter_map(|card_id| card_query.get(*card_id).ok())
+            .collect_vec();
+
+        // Everything not in play goes back to the deck, keeping the order it's in
+        let deck = cards
+            .iter()
+            .filter(|(_, card, _)| !locations.contains_key(*card))
+            .sorted_by_key(|(.., location)| match location {
+                CardLocation::Deck { index_from_bottom } => *index_from_bottom,
+                _ => usize::MAX,
+            })
+            .map(|(_, card, _)| **card)
+            .collect_vec();
+        for (i, card) in deck.into_iter().enumerate() {
+            locations.insert(
+                card,
+                CardLocation::Deck {
+                    index_from_bottom: i,
+                },
+            );
+        }
+
+        for (card_id, card, location) in cards {
+            if let Some(wanted) = locations.remove(card)
+                && wanted != *location
+            {
+                move_card(&mut commands, card_id, wanted);
+            }
+        }
+    }
+}
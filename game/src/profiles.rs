@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::SeatKind;
+
+/// Stable identity of a player across games, unlike the `Entity` of their seat.
+#[derive(Component, Debug, Eq, PartialEq, Clone, Copy, Hash, Reflect, Serialize, Deserialize)]
+pub struct ProfileId(pub u64);
+
+#[derive(Debug, PartialEq, Clone, Reflect, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub id: ProfileId,
+    pub name: String,
+    /// sRGB body colour.
+    pub colour: [f32; 3],
+    pub bot: bool,
+    /// Image path relative to the assets folder, shown above the nametag.
+    #[serde(default)]
+    pub avatar: Option<String>,
+}
+impl PlayerProfile {
+    pub fn color(&self) -> Color {
+        let [r, g, b] = self.colour;
+        Color::rgb(r, g, b)
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profiles {
+    pub profiles: Vec<PlayerProfile>,
+    /// Rotates seat assignment so tables don't all get the same bots.
+    #[serde(skip)]
+    next_assignment: usize,
+}
+
+impl Profiles {
+    /// A starter set of profiles for a fresh install.
+    pub fn starter() -> Self {
+        let humans = [("You", [0.1, 0.3, 0.9])];
+        let bots = [
+            ("Nana", [0.8, 0.2, 0.5]),
+            ("Grampa", [0.3, 0.6, 0.2]),
+            ("Auntie", [0.9, 0.6, 0.1]),
+            ("Uncle", [0.5, 0.3, 0.7]),
+            ("Cousin", [0.2, 0.7, 0.7]),
+            ("Kiddo", [0.9, 0.9, 0.2]),
+        ];
+        let profiles = humans
+            .iter()
+            .map(|profile| (profile, false))
+            .chain(bots.iter().map(|profile| (profile, true)))
+            .enumerate()
+            .map(|(i, ((name, colour), bot))| PlayerProfile {
+                id: ProfileId(i as u64),
+                name: name.to_string(),
+                colour: *colour,
+                bot,
+                avatar: None,
+            })
+            .collect();
+        Self {
+            profiles,
+            next_assignment: 0,
+        }
+    }
+
+    /// Read the profiles file, creating it with starter profiles if it doesn't exist.
+    pub fn load_or_create(path: &Path) -> Self {
+        if path.exists() {
+            return Self::load(path);
+        }
+        let profiles = Self::starter();
+        match profiles.save(path) {
+            Ok(()) => info!("Created profiles file {path:?}"),
+            Err(e) => warn!("Failed to create profiles file {path:?}: {e}"),
+        }
+        profiles
+    }
+
+    /// Read the profiles file, using starter profiles if it's missing or broken.
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(profiles) => profiles,
+                Err(e) => {
+                    warn!("Failed to parse profiles file {path:?}, using starter profiles: {e}");
+                    Self::starter()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::starter(),
+            Err(e) => {
+                warn!("Failed to read profiles file {path:?}, using starter profiles: {e}");
+                Self::starter()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+
+    pub fn get(&self, id: ProfileId) -> Option<&PlayerProfile> {
+        self.profiles.iter().find(|profile| profile.id == id)
+    }
+
+    /// The display name for a profile, even if it has since been deleted.
+    pub fn name(&self, id: ProfileId) -> String {
+        self.get(id)
+            .map(|profile| profile.name.clone())
+            .unwrap_or_else(|| format!("#{}", id.0))
+    }
+
+    /// Pick a profile for each seat, humans from human profiles and bots from bot profiles.
+    ///
+    /// Every human at a table gets their own profile, so their stats don't mix, with new
+    /// "Player N" profiles made when there aren't enough. Bot profiles are reused when there are
+    /// more bot seats than bot profiles.
+    pub fn assign(&mut self, seats: &[SeatKind]) -> Vec<ProfileId> {
+        let mut humans = self
+            .profiles
+            .iter()
+            .filter(|profile| !profile.bot)
+            .map(|profile| profile.id)
+            .collect::<Vec<_>>()
+            .into_iter();
+        let bots = self
+            .profiles
+            .iter()
+            .filter(|profile| profile.bot)
+            .map(|profile| profile.id)
+            .collect::<Vec<_>>();
+        let mut ids = Vec::with_capacity(seats.len());
+        for seat in seats {
+            let id = match seat {
+                SeatKind::Human => match humans.next() {
+                    Some(id) => id,
+                    None => self.create_player(),
+                },
+                SeatKind::Bot(_) | SeatKind::Process | SeatKind::Model => match bots.len() {
+                    0 => self.create(format!("Bot {}", self.profiles.len()), true),
+                    n => bots[self.next_assignment % n],
+                },
+            };
+            self.next_assignment += 1;
+            ids.push(id);
+        }
+        ids
+    }
+
+    /// A new human profile named "Player N", with the first N no profile has.
+    fn create_player(&mut self) -> ProfileId {
+        let name = (1..)
+            .map(|n| format!("Player {n}"))
+            .find(|name| !self.profiles.iter().any(|profile| &profile.name == name))
+            .expect("some number is free");
+        self.create(name, false)
+    }
+
+    /// The human profile with this name, created if there isn't one.
+    pub fn find_or_create_human(&mut self, name: &str) -> ProfileId {
+        match self
+            .profiles
+            .iter()
+            .find(|profile| !profile.bot && profile.name.eq_ignore_ascii_case(name))
+        {
+            Some(profile) => profile.id,
+            None => self.create(name.to_owned(), false),
+        }
+    }
+
+    fn create(&mut self, name: String, bot: bool) -> ProfileId {
+        let id = ProfileId(
+            self.profiles
+                .iter()
+                .map(|profile| profile.id.0 + 1)
+                .max()
+                .unwrap_or(0),
+        );
+        self.profiles.push(PlayerProfile {
+            id,
+            name,
+            colour: [0.5, 0.5, 0.5],
+            bot,
+            avatar: None,
+        });
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::BotStyle;
+
+    #[test]
+    fn every_human_at_a_table_gets_their_own_profile() {
+        let mut profiles = Profiles::starter();
+        let seats = [
+            SeatKind::Human,
+            SeatKind::Bot(BotStyle::default()),
+            SeatKind::Human,
+            SeatKind::Human,
+        ];
+        let ids = profiles.assign(&seats);
+
+        let humans = [ids[0], ids[2], ids[3]];
+        assert!(humans.iter().all(|id| !profiles.get(*id).unwrap().bot));
+        assert!(profiles.get(ids[1]).unwrap().bot);
+        assert_eq!(humans.iter().collect::<HashSet<_>>().len(), 3);
+        assert_eq!(profiles.name(ids[0]), "You");
+        assert_eq!(profiles.name(ids[2]), "Player 1");
+        assert_eq!(profiles.name(ids[3]), "Player 2");
+
+        // The next table reuses them rather than making more
+        let again = profiles.assign(&seats);
+        assert_eq!([again[0], again[2], again[3]], humans);
+    }
+}
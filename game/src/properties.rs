@@ -0,0 +1,183 @@
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::game::Action;
+use crate::game::Game;
+use crate::rules::RuleError;
+use crate::rules::RuleSet;
+use crate::BotDifficulty;
+use crate::BotStyle;
+use crate::Personality;
+
+/// Player count, deal seed and move seed for a game, under either rule set.
+pub fn games() -> impl Strategy<Value = (usize, u64, u64, RuleSet)> {
+    (2usize..=7, any::<u64>(), any::<u64>(), any::<bool>()).prop_map(
+        |(num_players, seed, moves_seed, hook)| (num_players, seed, moves_seed, RuleSet { hook }),
+    )
+}
+
+/// A bot style for each of 7 seats.
+pub fn bot_styles() -> impl Strategy<Value = Vec<BotStyle>> {
+    let difficulties = [
+        BotDifficulty::Beginner,
+        BotDifficulty::Family,
+        BotDifficulty::Expert,
+    ];
+    let personalities = [
+        Personality::Balanced,
+        Personality::Conservative,
+        Personality::Aggressive,
+    ];
+    prop::collection::vec(
+        (
+            prop::sample::select(difficulties.to_vec()),
+            prop::sample::select(personalities.to_vec()),
+        )
+            .prop_map(|(difficulty, personality)| BotStyle {
+                difficulty,
+                personality,
+            }),
+        7,
+    )
+}
+
+/// Play a whole game, `choose` picking the move of each seat in turn.
+///
+/// `choose` sees the game before the move and `check` before and after it, so either can test
+/// whatever made the move. A move the game turns down fails the test.
+pub fn play_game(
+    game: &mut Game,
+    mut choose: impl FnMut(&Game, usize) -> Result<Action, TestCaseError>,
+    mut check: impl FnMut(&Game, usize, Action, &Game) -> Result<(), TestCaseError>,
+) -> Result<(), TestCaseError> {
+    while let Some(seat) = game.phase().seat() {
+        let action = choose(game, seat)?;
+        let before = game.clone();
+        game.apply(seat, action)
+            .map_err(|e| TestCaseError::fail(format!("seat {seat} can't {action:?}: {e}")))?;
+        check(&before, seat, action, game)?;
+    }
+    prop_assert!(game.is_over());
+    Ok(())
+}
+
+/// Play a whole game with random legal moves, see [`play_game`].
+pub fn play_randomly(
+    game: &mut Game,
+    moves_seed: u64,
+    check: impl FnMut(&Game, usize, Action, &Game) -> Result<(), TestCaseError>,
+) -> Result<(), TestCaseError> {
+    let mut rng = StdRng::seed_from_u64(moves_seed);
+    let choose = |game: &Game, _| {
+        game.legal_actions()
+            .choose(&mut rng)
+            .copied()
+            .ok_or_else(|| TestCaseError::fail("no legal actions"))
+    };
+    play_game(game, choose, check)
+}
+
+/// For [`play_game`] when `choose` does the checking.
+pub fn no_check(_: &Game, _: usize, _: Action, _: &Game) -> Result<(), TestCaseError> {
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn tricks_taken_equal_the_hand_size((num_players, seed, moves_seed, rules) in games()) {
+        let mut game = Game::new(rules, num_players, seed);
+        play_randomly(&mut game, moves_seed, |_, _, _, _| Ok(()))?;
+
+        for (round, hand_size) in game.hand_sizes.iter().enumerate() {
+            let taken: usize = game.results.iter().map(|results| results[round].taken).sum();
+            prop_assert_eq!(taken, *hand_size, "round {}", round);
+        }
+    }
+
+    #[test]
+    fn scores_follow_the_formula((num_players, seed, moves_seed, rules) in games()) {
+        let mut game = Game::new(rules, num_players, seed);
+        play_randomly(&mut game, moves_seed, |_, _, _, _| Ok(()))?;
+
+        for seat in 0..num_players {
+            for result in game.results[seat].iter() {
+                // Making the bid is worth 10 and the bid, taking every trick 20 and the bid
+                let expected = if result.taken != result.bid {
+                    0
+                } else if result.taken == result.hand_size {
+                    20 + result.bid
+                } else {
+                    10 + result.bid
+                };
+                prop_assert_eq!(result.points, expected, "{:?}", result);
+            }
+            let total: usize = game.results[seat].iter().map(|result| result.points).sum();
+            prop_assert_eq!(game.points(seat), total);
+        }
+    }
+
+    #[test]
+    fn money_is_conserved((num_players, seed, moves_seed, rules) in games()) {
+        let mut game = Game::new(rules, num_players, seed);
+        play_randomly(&mut game, moves_seed, |_, _, _, after| {
+            let players: i64 = after.ledger.money_cents.iter().sum();
+            prop_assert_eq!(players + after.ledger.pot_cents as i64, 0);
+            Ok(())
+        })?;
+
+        // Only the cents that can't be split between the winners are left over
+        prop_assert!(game.ledger.pot_cents < game.winners().len());
+    }
+
+    #[test]
+    fn follow_suit_is_enforced((num_players, seed, moves_seed, rules) in games()) {
+        let mut game = Game::new(rules, num_players, seed);
+        let mut rng = StdRng::seed_from_u64(moves_seed);
+        while let Some(seat) = game.phase().seat() {
+            if game.round.bids[seat].is_none() {
+                let action = *game.legal_actions().choose(&mut rng).expect("a legal bid");
+                game.apply(seat, action).expect("legal bids are accepted");
+                continue;
+            }
+
+            // Try any card in hand, not just the legal ones
+            let card = *game.round.hands[seat].choose(&mut rng).expect("cards left in hand");
+            let led = game.round.trick.led_suit();
+            let can_follow = game.round.hands[seat]
+                .iter()
+                .any(|held| Some(held.suit) == led);
+            let result = game.apply(seat, Action::Play(card));
+            match (led, can_follow) {
+                (Some(led), true) if card.suit != led => {
+                    prop_assert_eq!(result, Err(RuleError::MustFollowSuit(led)));
+                }
+                _ => prop_assert_eq!(result, Ok(())),
+            }
+        }
+    }
+
+    #[test]
+    fn the_dealer_rotates_once_per_round((num_players, seed, moves_seed, rules) in games()) {
+        let mut game = Game::new(rules, num_players, seed);
+        let mut rounds = 1;
+        play_randomly(&mut game, moves_seed, |before, _, _, after| {
+            if after.is_over() {
+                return Ok(());
+            }
+            match after.round_index - before.round_index {
+                0 => prop_assert_eq!(after.round.dealer, before.round.dealer),
+                1 => {
+                    prop_assert_eq!(after.round.dealer, (before.round.dealer + 1) % num_players);
+                    rounds += 1;
+                }
+                skipped => prop_assert!(false, "{} rounds passed in one move", skipped),
+            }
+            Ok(())
+        })?;
+
+        prop_assert_eq!(rounds, game.hand_sizes.len());
+    }
+}
@@ -0,0 +1,625 @@
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::TryRecvError;
+use std::sync::Mutex;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bots::choose_action;
+use crate::bots::HeuristicStrategy;
+use crate::bots::Rationale;
+use crate::bots::Strategy;
+use crate::cli::LaunchConfig;
+use crate::game::Action;
+use crate::game::Game;
+use crate::game::Phase;
+use crate::game::Round;
+use crate::game::SeatView;
+use crate::game::Trick;
+use crate::Card;
+
+/// How long an external bot has to answer when the launch options don't say.
+pub const DEFAULT_ANSWER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A card on the table and the seat that played it.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct PlayedCard {
+    pub seat: usize,
+    pub card: Card,
+}
+
+fn played_cards(trick: &Trick) -> Vec<PlayedCard> {
+    trick
+        .cards
+        .iter()
+        .map(|(seat, card)| PlayedCard {
+            seat: *seat,
+            card: *card,
+        })
+        .collect()
+}
+
+/// A line sent to an external bot, one JSON object per line.
+///
+/// Seats are numbered clockwise from 0 and are the same for every seat; a bot learns its own
+/// from `new_round`. Cards are written like `"10H"`, `"QS"` or `"AC"`.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToBot {
+    /// `{"type":"new_round","seat":2,"num_players":4,"dealer":1,"hand_size":3}`
+    NewRound {
+        seat: usize,
+        num_players: usize,
+        dealer: usize,
+        hand_size: usize,
+    },
+    /// `{"type":"hand","cards":["AS","10H","2C"]}`, the bot's cards for the round.
+    Hand { cards: Vec<Card> },
+    /// `{"type":"trump","card":"QD"}`, `null` when the deck ran out before a card was turned up.
+    Trump { card: Option<Card> },
+    /// `{"type":"bid_request","bids":[null,2,null,1],"legal":[0,1,3]}`, answer with a bid.
+    BidRequest {
+        bids: Vec<Option<usize>>,
+        legal: Vec<usize>,
+    },
+    /// `{"type":"play_request","bids":[..],"taken":[..],"trick":[{"seat":1,"card":"KS"}],"legal":["AS"]}`,
+    /// answer with a card.
+    PlayRequest {
+        bids: Vec<Option<usize>>,
+        taken: Vec<usize>,
+        trick: Vec<PlayedCard>,
+        legal: Vec<Card>,
+    },
+    /// `{"type":"trick_result","cards":[..],"winner":2}`, sent after every trick.
+    TrickResult {
+        cards: Vec<PlayedCard>,
+        winner: usize,
+    },
+}
+
+impl ToBot {
+    /// What a seat is told when a round starts: who it is, its hand and trump.
+    pub fn round_start(view: &SeatView) -> [ToBot; 3] {
+        [
+            ToBot::NewRound {
+                seat: view.seat,
+                num_players: view.num_players,
+                dealer: view.dealer,
+                hand_size: view.hand_size,
+            },
+            ToBot::Hand {
+                cards: view.hand.clone(),
+            },
+            ToBot::Trump {
+                card: view.trump_card,
+            },
+        ]
+    }
+
+    pub fn bid_request(view: &SeatView, legal: &[usize]) -> Self {
+        ToBot::BidRequest {
+            bids: view.bids.clone(),
+            legal: legal.to_vec(),
+        }
+    }
+
+    pub fn play_request(view: &SeatView, legal: &[Card]) -> Self {
+        ToBot::PlayRequest {
+            bids: view.bids.clone(),
+            taken: view.taken.clone(),
+            trick: played_cards(&view.trick),
+            legal: legal.to_vec(),
+        }
+    }
+
+    /// The result of the trick `card` completes, if it does, given `round` as it was before.
+    pub fn trick_result(round: &Round, seat: usize, card: Card) -> Option<Self> {
+        let mut trick = round.trick.clone();
+        trick.cards.push((seat, card));
+        if trick.cards.len() < round.num_players {
+            return None;
+        }
+        let (winner, _) = trick
+            .winner(round.trump())
+            .expect("a full trick has a winner");
+        Some(ToBot::TrickResult {
+            cards: played_cards(&trick),
+            winner,
+        })
+    }
+}
+
+/// A line an external bot answers a request with.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FromBot {
+    /// `{"type":"bid","bid":2}`
+    Bid { bid: usize },
+    /// `{"type":"play","card":"10H"}`
+    Play { card: Card },
+}
+
+/// A seat played by another program, talking [`ToBot`] and [`FromBot`] over its stdin and stdout.
+///
+/// Slow, garbled or illegal answers are logged and replaced with the heuristic bot's move.
+/// As a [`Strategy`] it waits for each answer, at the 3D tables [`ProcessBot::poll_action`]
+/// doesn't.
+#[derive(Component)]
+pub struct ProcessBot {
+    timeout: Duration,
+    connection: Mutex<Connection>,
+}
+
+struct Connection {
+    command: String,
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines from the process's stdout, read on their own thread so answers can time out.
+    lines: Receiver<String>,
+    /// Set once the process stops listening, after which it isn't asked anything.
+    closed: bool,
+    /// The dealer and hand size of the round the process was last told about.
+    round: Option<(usize, usize)>,
+    /// The request waiting for an answer and when the answer is due.
+    pending: Option<(ToBot, Instant)>,
+}
+
+impl ProcessBot {
+    /// Start `command`, the program and its arguments separated by spaces.
+    pub fn spawn(command: &str, timeout: Duration) -> std::io::Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty bot command")
+        })?;
+        let mut child = std::process::Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        info!("Started bot {command:?}");
+
+        Ok(Self {
+            timeout,
+            connection: Mutex::new(Connection {
+                command: command.to_owned(),
+                child,
+                stdin,
+                lines,
+                closed: false,
+                round: None,
+                pending: None,
+            }),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .expect("nothing panics while talking to a bot")
+    }
+
+    /// The next action in `game` once the process has answered, without waiting for it.
+    ///
+    /// The first call for a decision sends the request and later ones look for the answer.
+    /// A garbled or illegal answer, or none by the deadline, gets the heuristic bot's move.
+    pub fn poll_action(
+        &self,
+        game: &Game,
+        rng: &mut StdRng,
+    ) -> Option<(usize, Action, Option<Rationale>)> {
+        let seat = game.phase().seat()?;
+        let view = game.round.view(seat);
+        let request = match game.phase() {
+            Phase::Bidding { .. } => ToBot::bid_request(&view, &game.round.legal_bids(&game.rules)),
+            _ => ToBot::play_request(&view, &game.round.legal_plays(seat)),
+        };
+
+        let mut connection = self.connection();
+        if connection.pending.as_ref().map(|(pending, _)| pending) != Some(&request) {
+            connection.start_round(&view);
+            connection.send_request(&request, self.timeout);
+        }
+        let Poll::Ready(answer) = connection.poll_answer() else {
+            return None;
+        };
+        let action = answer.as_ref().map(|answer| match *answer {
+            FromBot::Bid { bid } => Action::Bid(bid),
+            FromBot::Play { card } => Action::Play(card),
+        });
+        if let Some(action) = action
+            && game.legal_actions().contains(&action)
+        {
+            return Some((seat, action, None));
+        }
+        let chosen = choose_action(&HeuristicStrategy, game, rng);
+        warn!(
+            "Bot {:?} gave no legal move ({answer:?}), making {chosen:?} for it",
+            connection.command
+        );
+        chosen
+    }
+}
+
+impl Connection {
+    fn send(&mut self, message: &ToBot) {
+        if self.closed {
+            return;
+        }
+        let line = serde_json::to_string(message).expect("messages are serializable");
+        if let Err(e) = writeln!(self.stdin, "{line}").and_then(|()| self.stdin.flush()) {
+            warn!("Bot {:?} stopped listening: {e}", self.command);
+            self.closed = true;
+        }
+    }
+
+    /// Tell the process about the round `view` is in, unless it already knows.
+    ///
+    /// Every seat bids once a round, so a bid always starts a new one.
+    fn start_round(&mut self, view: &SeatView) {
+        let round = Some((view.dealer, view.hand_size));
+        if self.round == round && view.bids[view.seat].is_some() {
+            return;
+        }
+        for message in ToBot::round_start(view) {
+            self.send(&message);
+        }
+        self.round = round;
+    }
+
+    fn send_request(&mut self, request: &ToBot, timeout: Duration) {
+        // Anything waiting is the answer to an earlier request that timed out
+        while let Ok(late) = self.lines.try_recv() {
+            warn!("Bot {:?} answered too late: {late}", self.command);
+        }
+        self.send(request);
+        self.pending = (!self.closed).then(|| (request.clone(), Instant::now() + timeout));
+    }
+
+    /// The answer to the pending request once it's in or overdue, `None` when there's no answer.
+    fn poll_answer(&mut self) -> Poll<Option<FromBot>> {
+        let Some((_, due)) = &self.pending else {
+            return Poll::Ready(None);
+        };
+        let received = match self.lines.try_recv() {
+            Ok(line) => Ok(line),
+            Err(TryRecvError::Empty) if Instant::now() < *due => return Poll::Pending,
+            Err(TryRecvError::Empty) => Err(RecvTimeoutError::Timeout),
+            Err(TryRecvError::Disconnected) => Err(RecvTimeoutError::Disconnected),
+        };
+        Poll::Ready(self.answer(received))
+    }
+
+    fn ask(&mut self, request: &ToBot, timeout: Duration) -> Option<FromBot> {
+        self.send_request(request, timeout);
+        self.pending.as_ref()?;
+        let received = self.lines.recv_timeout(timeout);
+        self.answer(received)
+    }
+
+    /// Make sense of what came back for the pending request.
+    fn answer(&mut self, received: Result<String, RecvTimeoutError>) -> Option<FromBot> {
+        self.pending = None;
+        match received {
+            Ok(line) => match serde_json::from_str(&line) {
+                Ok(answer) => Some(answer),
+                Err(e) => {
+                    warn!(
+                        "Bot {:?} sent {line:?}, which isn't an answer: {e}",
+                        self.command
+                    );
+                    None
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                warn!("Bot {:?} didn't answer in time", self.command);
+                None
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                warn!("Bot {:?} exited", self.command);
+                self.closed = true;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // The reader thread finishes once the process's stdout closes
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Strategy for ProcessBot {
+    fn name(&self) -> &'static str {
+        "process"
+    }
+
+    fn bid(
+        &self,
+        view: &SeatView,
+        legal: &[usize],
+        rng: &mut StdRng,
+    ) -> (usize, Option<Rationale>) {
+        let mut connection = self.connection();
+        connection.start_round(view);
+        match connection.ask(&ToBot::bid_request(view, legal), self.timeout) {
+            Some(FromBot::Bid { bid }) if legal.contains(&bid) => (bid, None),
+            answer => {
+                let (bid, rationale) = HeuristicStrategy.bid(view, legal, rng);
+                warn!(
+                    "Bot {:?} gave no legal bid ({answer:?}), bidding {bid} for it",
+                    connection.command
+                );
+                (bid, rationale)
+            }
+        }
+    }
+
+    fn play(&self, view: &SeatView, legal: &[Card], rng: &mut StdRng) -> (Card, Option<Rationale>) {
+        let mut connection = self.connection();
+        connection.start_round(view);
+        match connection.ask(&ToBot::play_request(view, legal), self.timeout) {
+            Some(FromBot::Play { card }) if legal.contains(&card) => (card, None),
+            answer => {
+                let (card, rationale) = HeuristicStrategy.play(view, legal, rng);
+                warn!(
+                    "Bot {:?} gave no legal card ({answer:?}), playing {card} for it",
+                    connection.command
+                );
+                (card, rationale)
+            }
+        }
+    }
+
+    fn observe(&self, round: &Round, seat: usize, action: Action) {
+        let Action::Play(card) = action else {
+            return;
+        };
+        let mut connection = self.connection();
+        if connection.round != Some((round.dealer, round.hand_size)) {
+            return;
+        }
+        if let Some(result) = ToBot::trick_result(round, seat, card) {
+            connection.send(&result);
+        }
+    }
+}
+
+/// The other end of the protocol: rebuilds a seat's view from the messages and asks a strategy.
+pub struct ReferenceBot {
+    strategy: Box<dyn Strategy>,
+    rng: StdRng,
+    view: Option<SeatView>,
+    /// Cards from the finished tricks of the round.
+    finished: Vec<Card>,
+}
+
+impl ReferenceBot {
+    pub fn new(strategy: Box<dyn Strategy>, seed: u64) -> Self {
+        Self {
+            strategy,
+            rng: StdRng::seed_from_u64(seed),
+            view: None,
+            finished: Vec::new(),
+        }
+    }
+
+    /// Take in a message, answering it if it's a request.
+    pub fn receive(&mut self, message: ToBot) -> Result<Option<FromBot>, String> {
+        if let ToBot::NewRound {
+            seat,
+            num_players,
+            dealer,
+            hand_size,
+        } = message
+        {
+            self.finished.clear();
+            self.view = Some(SeatView {
+                seat,
+                num_players,
+                dealer,
+                hand_size,
+                hand: Vec::new(),
+                trump_card: None,
+                bids: vec![None; num_players],
+                taken: vec![0; num_players],
+                trick: Trick {
+                    leader: (dealer + 1) % num_players,
+                    cards: Vec::new(),
+                },
+                played: Vec::new(),
+            });
+            return Ok(None);
+        }
+
+        let Some(view) = &mut self.view else {
+            return Err(format!("{message:?} came before new_round"));
+        };
+        match message {
+            ToBot::NewRound { .. } => unreachable!("handled above"),
+            ToBot::Hand { cards } => view.hand = cards,
+            ToBot::Trump { card } => view.trump_card = card,
+            ToBot::BidRequest { bids, legal } => {
+                view.bids = bids;
+                let (bid, _) = self.strategy.bid(view, &legal, &mut self.rng);
+                return Ok(Some(FromBot::Bid { bid }));
+            }
+            ToBot::PlayRequest {
+                bids,
+                taken,
+                trick,
+                legal,
+            } => {
+                view.bids = bids;
+                view.taken = taken;
+                if let Some(first) = trick.first() {
+                    view.trick.leader = first.seat;
+                }
+                view.trick.cards = trick
+                    .iter()
+                    .map(|played| (played.seat, played.card))
+                    .collect();
+                view.played = self.finished.clone();
+                view.played.extend(trick.iter().map(|played| played.card));
+                let (card, _) = self.strategy.play(view, &legal, &mut self.rng);
+                return Ok(Some(FromBot::Play { card }));
+            }
+            ToBot::TrickResult { cards, winner } => {
+                let cards: Vec<Card> = cards.iter().map(|played| played.card).collect();
+                view.hand.retain(|card| !cards.contains(card));
+                view.taken[winner] += 1;
+                view.trick = Trick {
+                    leader: winner,
+                    cards: Vec::new(),
+                };
+                self.finished.extend(cards);
+                view.played = self.finished.clone();
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Speak the protocol on stdin and stdout as the heuristic bot, for `poche bot`.
+///
+/// Run it as an external bot with `--bot "poche bot"`, or use it as a starting point for your own.
+pub fn run(launch_config: &LaunchConfig) {
+    let mut bot = ReferenceBot::new(
+        Box::new(HeuristicStrategy),
+        launch_config.seed.unwrap_or_default(),
+    );
+    for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = serde_json::from_str::<ToBot>(&line)
+            .map_err(|e| format!("can't read {line:?}: {e}"))
+            .and_then(|message| bot.receive(message));
+        match answer {
+            Ok(Some(answer)) => {
+                println!(
+                    "{}",
+                    serde_json::to_string(&answer).expect("answers are serializable")
+                );
+            }
+            Ok(None) => {}
+            // Only answers go to stdout
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
+
+    use super::*;
+    // proptest has a `Strategy` too, these tests mean the bots'
+    use super::Strategy;
+    use crate::properties::games;
+    use crate::properties::no_check;
+    use crate::properties::play_game;
+    use crate::rules::RuleSet;
+
+    /// A message as a bot reads it, after a trip through JSON.
+    fn over_the_wire(message: ToBot) -> ToBot {
+        let line = serde_json::to_string(&message).expect("messages are serializable");
+        serde_json::from_str(&line).expect("messages read back")
+    }
+
+    proptest! {
+        #[test]
+        fn the_reference_bot_plays_like_the_heuristic((num_players, seed, _, rules) in games()) {
+            let mut game = Game::new(rules, num_players, seed);
+            let mut bots: Vec<ReferenceBot> = (0..num_players)
+                .map(|_| ReferenceBot::new(Box::new(HeuristicStrategy), seed))
+                .collect();
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut trick_result: Option<ToBot> = None;
+            let choose = |game: &Game, seat: usize| {
+                // Everyone hears how the last trick went before the next request
+                if let Some(result) = trick_result.take() {
+                    for bot in bots.iter_mut() {
+                        bot.receive(over_the_wire(result.clone())).map_err(TestCaseError::fail)?;
+                    }
+                }
+                let view = game.round.view(seat);
+                let (expected, request) = match game.phase() {
+                    Phase::Bidding { .. } => {
+                        for message in ToBot::round_start(&view) {
+                            bots[seat].receive(over_the_wire(message)).map_err(TestCaseError::fail)?;
+                        }
+                        let legal = game.round.legal_bids(&game.rules);
+                        let (bid, _) = HeuristicStrategy.bid(&view, &legal, &mut rng);
+                        (Action::Bid(bid), ToBot::bid_request(&view, &legal))
+                    }
+                    _ => {
+                        let legal = game.round.legal_plays(seat);
+                        let (card, _) = HeuristicStrategy.play(&view, &legal, &mut rng);
+                        (Action::Play(card), ToBot::play_request(&view, &legal))
+                    }
+                };
+                let action = match bots[seat].receive(over_the_wire(request)).map_err(TestCaseError::fail)? {
+                    Some(FromBot::Bid { bid }) => Action::Bid(bid),
+                    Some(FromBot::Play { card }) => Action::Play(card),
+                    None => return Err(TestCaseError::fail("no answer to a request")),
+                };
+                prop_assert_eq!(action, expected);
+
+                if let Action::Play(card) = action {
+                    trick_result = ToBot::trick_result(&game.round, seat, card);
+                }
+                Ok(action)
+            };
+            play_game(&mut game, choose, no_check)?;
+        }
+    }
+    #[cfg(unix)]
+    #[test]
+    fn a_silent_bot_doesnt_hold_up_the_table() {
+        let timeout = Duration::from_millis(200);
+        let bot = ProcessBot::spawn("sleep 10", timeout).expect("sleep starts");
+        let game = Game::new(RuleSet::default(), 3, 0);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let asked = Instant::now();
+        assert_eq!(bot.poll_action(&game, &mut rng), None);
+        assert!(asked.elapsed() < timeout, "polling waited for the answer");
+
+        std::thread::sleep(timeout);
+        let (seat, action, _) = bot
+            .poll_action(&game, &mut rng)
+            .expect("a late bot gets the heuristic's move");
+        assert_eq!(Some(seat), game.phase().seat());
+        assert!(game.legal_actions().contains(&action));
+    }
+}
@@ -0,0 +1,554 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use bevy::prelude::*;
+use bevy::tasks::block_on;
+use bevy::tasks::futures_lite::future;
+use bevy::tasks::AsyncComputeTaskPool;
+use bevy::tasks::Task;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::advisor::advise_bid;
+use crate::bots::play_out;
+use crate::bots::HeuristicStrategy;
+use crate::cli::LaunchConfig;
+use crate::game::Action;
+use crate::game::Game;
+use crate::game::Round;
+use crate::play::TableGame;
+use crate::profiles::ProfileId;
+use crate::profiles::Profiles;
+use crate::rules::score;
+use crate::rules::RuleSet;
+use crate::stats::RoundResult;
+use crate::Card;
+use crate::SeatKind;
+use crate::Session;
+
+/// Layouts of the unseen cards tried for each decision.
+const SAMPLES: usize = 200;
+
+/// How often something has to happen in the simulations to be expected.
+const EXPECTED: f32 = 0.5;
+
+/// Grades the humans' bids and plays once a game ends.
+///
+/// V hides and shows the review, X saves it as a text report.
+pub struct ReviewPlugin;
+impl Plugin for ReviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            Update,
+            (
+                review_finished_games,
+                show_finished_reviews,
+                handle_review_key_presses,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// How a choice ends the round for the seat making it, on average over the simulations.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Outcome {
+    pub points: f32,
+    pub tricks: f32,
+    /// How often the bid is made.
+    pub made: f32,
+}
+
+/// A choice that the best one shows up as a clear mistake.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Blunder {
+    /// Gave up a trick the seat still needed and would have taken with the best card.
+    LostTrick,
+    /// The best choice makes the bid more often than not, this one poches more often than not.
+    CausedPoche,
+}
+
+impl fmt::Display for Blunder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Blunder::LostTrick => write!(f, "lost an expected trick"),
+            Blunder::CausedPoche => write!(f, "caused a poche"),
+        }
+    }
+}
+
+/// One bid or card play, next to the choice the simulations liked best.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub round_index: usize,
+    pub seat: usize,
+    pub action: Action,
+    pub best: Action,
+    pub outcome: Outcome,
+    pub best_outcome: Outcome,
+    /// Whether the seat had taken fewer tricks than it bid when it chose.
+    pub short_of_bid: bool,
+}
+impl Decision {
+    pub fn points_lost(&self) -> f32 {
+        self.best_outcome.points - self.outcome.points
+    }
+
+    pub fn blunder(&self) -> Option<Blunder> {
+        if self.best_outcome.made >= EXPECTED && self.outcome.made < EXPECTED {
+            return Some(Blunder::CausedPoche);
+        }
+        let tricks_lost = self.best_outcome.tricks - self.outcome.tricks;
+        (self.short_of_bid && tricks_lost >= EXPECTED).then_some(Blunder::LostTrick)
+    }
+}
+
+/// A finished game, decision by decision, on the session it was played in.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Review {
+    /// Player names by seat.
+    pub names: Vec<String>,
+    pub hand_sizes: Vec<usize>,
+    /// Finished rounds by seat.
+    pub results: Vec<Vec<RoundResult>>,
+    pub decisions: Vec<Decision>,
+}
+
+impl Review {
+    /// How each seat bid, then the blunders.
+    pub fn summary(&self) -> String {
+        let mut text = "Game review\n".to_owned();
+        text += &self.bid_accuracy();
+        let blunders = self
+            .decisions
+            .iter()
+            .filter(|decision| decision.blunder().is_some())
+            .collect_vec();
+        if !blunders.is_empty() {
+            text += "\nBlunders\n";
+            for decision in blunders {
+                text += &format!(
+                    "  Round {}: {}\n",
+                    decision.round_index + 1,
+                    self.describe(decision)
+                );
+            }
+        }
+        text += "\nX saves the full report";
+        text
+    }
+
+    /// Every round with the choices that weren't the best, to talk the game over.
+    pub fn report(&self) -> String {
+        let mut text = "Game review\n".to_owned();
+        text += &self.bid_accuracy();
+        for (round_index, hand_size) in self.hand_sizes.iter().enumerate() {
+            let cards = match hand_size {
+                1 => "1 card".to_owned(),
+                n => format!("{n} cards"),
+            };
+            text += &format!("\nRound {}, {cards}\n", round_index + 1);
+            for (name, results) in self.names.iter().zip(&self.results) {
+                let Some(result) = results.get(round_index) else {
+                    continue;
+                };
+                let outcome = match result.made() {
+                    true => format!("{} points", result.points),
+                    false => "poche".to_owned(),
+                };
+                text += &format!(
+                    "  {name} bid {}, took {}, {outcome}\n",
+                    result.bid, result.taken
+                );
+            }
+            for decision in self.decisions.iter().filter(|decision| {
+                decision.round_index == round_index && decision.action != decision.best
+            }) {
+                text += &format!("  - {}\n", self.describe(decision));
+            }
+        }
+        text
+    }
+
+    fn bid_accuracy(&self) -> String {
+        let mut text = String::new();
+        for (seat, (name, results)) in self.names.iter().zip(&self.results).enumerate() {
+            let made = results.iter().filter(|result| result.made()).count();
+            let (blunders, points_lost) = self
+                .decisions
+                .iter()
+                .filter(|decision| decision.seat == seat)
+                .fold((0, 0.0), |(blunders, lost), decision| {
+                    (
+                        blunders + decision.blunder().is_some() as usize,
+                        lost + decision.points_lost(),
+                    )
+                });
+            text += &format!("{name}: made {made} of {} bids", results.len());
+            if self.decisions.iter().any(|decision| decision.seat == seat) {
+                text += &format!(", {blunders} blunders, {points_lost:.1} points given away");
+            }
+            text += "\n";
+        }
+        text
+    }
+
+    fn describe(&self, decision: &Decision) -> String {
+        let verb = |action: Action| match action {
+            Action::Bid(bid) => format!("bid {bid}"),
+            Action::Play(card) => format!("play {card}"),
+        };
+        let mut text = format!(
+            "{} chose to {} ({:.1} points), {} was worth {:.1}",
+            self.names[decision.seat],
+            verb(decision.action),
+            decision.outcome.points,
+            verb(decision.best),
+            decision.best_outcome.points,
+        );
+        if let Some(blunder) = decision.blunder() {
+            text += &format!(", {blunder} ??");
+        }
+        text
+    }
+}
+
+/// Replay `game` from its first deal and grade each choice the `seats` had.
+pub fn review_game(
+    game: &Game,
+    names: Vec<String>,
+    seats: &[usize],
+    samples: usize,
+    rng: &mut StdRng,
+) -> Review {
+    let mut replay = game.restart();
+    let mut decisions = Vec::new();
+    for (seat, action) in game.log.iter().copied() {
+        if seats.contains(&seat) && replay.legal_actions().len() > 1 {
+            let round = &replay.round;
+            let choices = match action {
+                Action::Bid(_) => bid_outcomes(round, seat, &replay.rules, samples, rng),
+                Action::Play(_) => play_outcomes(round, seat, &replay.rules, samples, rng),
+            };
+            let short_of_bid = round.bids[seat].is_some_and(|bid| round.taken[seat] < bid);
+            decisions.push(grade(
+                replay.round_index,
+                seat,
+                action,
+                short_of_bid,
+                &choices,
+            ));
+        }
+        replay.apply(seat, action).expect("the log replays");
+    }
+
+    Review {
+        names,
+        hand_sizes: game.hand_sizes.clone(),
+        results: game.results.clone(),
+        decisions,
+    }
+}
+
+/// Compare `action` to the best of `choices`, keeping `action` as the best when it's as good.
+fn grade(
+    round_index: usize,
+    seat: usize,
+    action: Action,
+    short_of_bid: bool,
+    choices: &[(Action, Outcome)],
+) -> Decision {
+    let outcome = choices
+        .iter()
+        .find(|(choice, _)| *choice == action)
+        .map(|(_, outcome)| *outcome)
+        .unwrap_or_default();
+    let (best, best_outcome) = choices
+        .iter()
+        .copied()
+        .max_by(|(_, a), (_, b)| a.points.total_cmp(&b.points))
+        .filter(|(_, best_outcome)| best_outcome.points > outcome.points)
+        .unwrap_or((action, outcome));
+    Decision {
+        round_index,
+        seat,
+        action,
+        best,
+        outcome,
+        best_outcome,
+        short_of_bid,
+    }
+}
+
+/// How each bid `seat` may make turns out, from the bid advisor's simulations.
+fn bid_outcomes(
+    round: &Round,
+    seat: usize,
+    rules: &RuleSet,
+    samples: usize,
+    rng: &mut StdRng,
+) -> Vec<(Action, Outcome)> {
+    let advice = advise_bid(&round.view(seat), rules, samples, rng);
+    let tricks = advice
+        .chances
+        .iter()
+        .enumerate()
+        .map(|(tricks, chance)| tricks as f32 * chance)
+        .sum();
+    advice
+        .expected_points
+        .iter()
+        .enumerate()
+        .filter_map(|(bid, points)| {
+            let outcome = Outcome {
+                points: (*points)?,
+                tricks,
+                made: advice.chances[bid],
+            };
+            Some((Action::Bid(bid), outcome))
+        })
+        .collect()
+}
+
+/// How each card `seat` may play turns out, over layouts of the cards it can't see.
+fn play_outcomes(
+    round: &Round,
+    seat: usize,
+    rules: &RuleSet,
+    samples: usize,
+    rng: &mut StdRng,
+) -> Vec<(Action, Outcome)> {
+    let legal = round.legal_plays(seat);
+    let bid = round.bids[seat].unwrap_or_default();
+    let mut totals = vec![Outcome::default(); legal.len()];
+    for _ in 0..samples {
+        let layout = deal_unseen(round, seat, rng);
+        for (card, total) in legal.iter().zip(totals.iter_mut()) {
+            let mut round = layout.clone();
+            round.play(seat, *card).expect("legal plays are legal");
+            play_out(&HeuristicStrategy, &mut round, rules, rng);
+            let taken = round.taken[seat];
+            total.points += score(bid, taken, round.hand_size) as f32;
+            total.tricks += taken as f32;
+            total.made += (taken == bid) as usize as f32;
+        }
+    }
+    let samples = samples.max(1) as f32;
+    legal
+        .into_iter()
+        .zip(totals)
+        .map(|(card, total)| {
+            let outcome = Outcome {
+                points: total.points / samples,
+                tricks: total.tricks / samples,
+                made: total.made / samples,
+            };
+            (Action::Play(card), outcome)
+        })
+        .collect()
+}
+
+/// `round` with the other hands dealt again from the cards `seat` hasn't seen.
+///
+/// Who has shown out of which suit isn't taken into account.
+fn deal_unseen(round: &Round, seat: usize, rng: &mut StdRng) -> Round {
+    let view = round.view(seat);
+    let mut unseen = Card::get_new_deck()
+        .into_iter()
+        .filter(|card| {
+            !view.hand.contains(card)
+                && !view.played.contains(card)
+                && Some(*card) != view.trump_card
+        })
+        .collect_vec();
+    unseen.shuffle(rng);
+
+    let mut layout = round.clone();
+    for (other, hand) in layout.hands.iter_mut().enumerate() {
+        if other != seat {
+            *hand = unseen.split_off(unseen.len() - hand.len());
+        }
+    }
+    layout
+}
+
+#[derive(Component)]
+struct ReviewText;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Percent(30.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+        ReviewText,
+        Name::new("Review Screen"),
+    ));
+}
+
+/// A review being worked out in the background, on the session of the game.
+#[derive(Component)]
+struct Reviewing(Task<Review>);
+
+/// Start reviewing each game with a human in it as soon as it's over.
+///
+/// Grading every decision plays out hundreds of layouts each, so it runs on the async compute
+/// pool and [`show_finished_reviews`] picks the review up.
+fn review_finished_games(
+    mut commands: Commands,
+    game_query: Query<(Entity, &Session, &TableGame), (Without<Review>, Without<Reviewing>)>,
+    player_query: Query<(&SeatKind, Option<&ProfileId>)>,
+    profiles: Option<Res<Profiles>>,
+) {
+    for (session_id, session, table_game) in game_query.iter() {
+        if !table_game.game.is_over() {
+            continue;
+        }
+        let mut names = Vec::new();
+        let mut humans = Vec::new();
+        for (seat, player_id) in table_game.seats.iter().enumerate() {
+            let player = player_query.get(*player_id).ok();
+            let name = match (&profiles, player.and_then(|(_, profile_id)| profile_id)) {
+                (Some(profiles), Some(profile_id)) => profiles.name(*profile_id),
+                _ => format!("Seat {}", seat + 1),
+            };
+            names.push(name);
+            if player.is_some_and(|(seat_kind, _)| *seat_kind == SeatKind::Human) {
+                humans.push(seat);
+            }
+        }
+        // Nobody to tell how they played
+        if humans.is_empty() {
+            continue;
+        }
+
+        let game = table_game.game.clone();
+        let mut rng = StdRng::seed_from_u64(session.seed);
+        let task = AsyncComputeTaskPool::get()
+            .spawn(async move { review_game(&game, names, &humans, SAMPLES, &mut rng) });
+        commands.entity(session_id).insert(Reviewing(task));
+    }
+}
+
+/// Show each review once it's worked out.
+fn show_finished_reviews(
+    mut commands: Commands,
+    mut reviewing_query: Query<(Entity, &mut Reviewing)>,
+    mut review_text_query: Query<(&mut Text, &mut Visibility), With<ReviewText>>,
+) {
+    for (session_id, mut reviewing) in reviewing_query.iter_mut() {
+        let Some(review) = block_on(future::poll_once(&mut reviewing.0)) else {
+            continue;
+        };
+        info!(
+            "Reviewed {} decisions in session {session_id:?}",
+            review.decisions.len()
+        );
+        for (mut text, mut visibility) in review_text_query.iter_mut() {
+            text.sections[0].value = review.summary();
+            *visibility = Visibility::Visible;
+        }
+        commands
+            .entity(session_id)
+            .remove::<Reviewing>()
+            .insert(review);
+    }
+}
+
+fn handle_review_key_presses(
+    input: Res<ButtonInput<KeyCode>>,
+    launch_config: Res<LaunchConfig>,
+    review_query: Query<(Entity, &Review)>,
+    mut review_text_query: Query<&mut Visibility, With<ReviewText>>,
+) {
+    if input.just_pressed(KeyCode::KeyV) {
+        for mut visibility in review_text_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+
+    if input.just_pressed(KeyCode::KeyX) {
+        let secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let reviews = review_query
+            .iter()
+            .sorted_by_key(|(session_id, _)| *session_id);
+        for (i, (_, review)) in reviews.enumerate() {
+            let path = launch_config
+                .reviews
+                .join(format!("review-{secs}-{}.txt", i + 1));
+            let saved = std::fs::create_dir_all(&launch_config.reviews)
+                .and_then(|()| std::fs::write(&path, review.report()));
+            match saved {
+                Ok(()) => info!("Saved review to {path:?}"),
+                Err(e) => warn!("Failed to save review to {path:?}: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(outcome: Outcome, best_outcome: Outcome, short_of_bid: bool) -> Decision {
+        Decision {
+            round_index: 0,
+            seat: 0,
+            action: Action::Bid(1),
+            best: Action::Bid(0),
+            outcome,
+            best_outcome,
+            short_of_bid,
+        }
+    }
+
+    #[test]
+    fn blunders_lose_a_needed_trick_or_cause_a_poche() {
+        let outcome = |points, tricks, made| Outcome {
+            points,
+            tricks,
+            made,
+        };
+        let made = outcome(11.0, 1.0, 0.8);
+        assert_eq!(
+            decision(outcome(2.0, 1.0, 0.2), made, false).blunder(),
+            Some(Blunder::CausedPoche)
+        );
+        assert_eq!(
+            decision(outcome(9.0, 0.3, 0.6), made, true).blunder(),
+            Some(Blunder::LostTrick)
+        );
+        // Ducking a trick once the bid is made isn't losing one
+        assert_eq!(
+            decision(outcome(9.0, 0.3, 0.6), made, false).blunder(),
+            None
+        );
+        // Giving away points alone isn't a blunder
+        assert_eq!(
+            decision(outcome(7.0, 0.9, 0.55), made, true).blunder(),
+            None
+        );
+    }
+}
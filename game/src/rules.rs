@@ -0,0 +1,261 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Card;
+use crate::Coin;
+use crate::Suit;
+
+/// Every player pays this into the pot at the start of the game.
+pub const ANTE: Coin = Coin::Quarter;
+/// Paid into the pot by every player who poches; "ten cents a lesson".
+pub const POCHE_PENALTY: Coin = Coin::Dime;
+/// The largest hand dealt, in the middle of the game.
+pub const MAX_HAND_SIZE: usize = 7;
+/// The most players a deck deals [`MAX_HAND_SIZE`] cards each to, with one left for trump.
+pub const MAX_PLAYERS: usize = 7;
+
+/// The hand size of each round, 1..7..1, shrunk so the deck covers every hand plus the trump card.
+pub fn round_hand_sizes(num_players: usize) -> Vec<usize> {
+    let max = MAX_HAND_SIZE.min(51 / num_players.max(1));
+    (1..=max).chain((1..max).rev()).collect()
+}
+
+/// The seats with the most points, more than one when tied.
+pub fn winners(points: &[usize]) -> Vec<usize> {
+    let best = points.iter().max().copied().unwrap_or_default();
+    (0..points.len())
+        .filter(|seat| points[*seat] == best)
+        .collect()
+}
+
+/// Coins paid into and out of the pot over a game.
+#[derive(Debug, Eq, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    pub pot_cents: usize,
+    /// Net winnings by seat.
+    pub money_cents: Vec<i64>,
+}
+impl Ledger {
+    /// Everyone antes into the pot.
+    pub fn new(num_players: usize) -> Self {
+        Self {
+            pot_cents: ANTE.value() * num_players,
+            money_cents: vec![-(ANTE.value() as i64); num_players],
+        }
+    }
+
+    pub fn pay_poche(&mut self, seat: usize) {
+        self.money_cents[seat] -= POCHE_PENALTY.value() as i64;
+        self.pot_cents += POCHE_PENALTY.value();
+    }
+
+    /// The winners split the pot, any odd cents stay in it.
+    pub fn pay_winners(&mut self, winners: &[usize]) {
+        if winners.is_empty() {
+            return;
+        }
+        let share = self.pot_cents / winners.len();
+        for seat in winners.iter() {
+            self.money_cents[*seat] += share as i64;
+        }
+        self.pot_cents -= share * winners.len();
+    }
+}
+
+/// Points for a round.
+///
+/// Making the bid prepends a 1, or a 2 when taking every trick. Poching scores nothing.
+pub fn score(bid: usize, taken: usize, hand_size: usize) -> usize {
+    match (bid == taken, taken == hand_size) {
+        (false, _) => 0,
+        (true, false) => 10 + bid,
+        (true, true) => 20 + bid,
+    }
+}
+
+/// Table rules that vary between families.
+#[derive(Debug, Eq, PartialEq, Clone, Reflect, Default, Serialize, Deserialize)]
+pub struct RuleSet {
+    /// The dealer bids last and may not bid the number that makes the total bids equal the trick count.
+    pub hook: bool,
+}
+
+impl RuleSet {
+    /// Look up a named rule preset.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "family" => Some(RuleSet { hook: false }),
+            "hook" => Some(RuleSet { hook: true }),
+            _ => None,
+        }
+    }
+
+    /// The bid the next bidder is not allowed to make, if any.
+    ///
+    /// `bids` are the bids already made this round, in bidding order.
+    /// Only the last bidder (the dealer) can be hooked.
+    pub fn forbidden_bid(
+        &self,
+        hand_size: usize,
+        bids: &[usize],
+        num_players: usize,
+    ) -> Option<usize> {
+        if !self.hook || bids.len() + 1 != num_players {
+            return None;
+        }
+        hand_size.checked_sub(bids.iter().sum())
+    }
+
+    /// Check a bid against the rules before it is recorded.
+    pub fn validate_bid(
+        &self,
+        bid: usize,
+        hand_size: usize,
+        bids: &[usize],
+        num_players: usize,
+    ) -> Result<(), RuleError> {
+        if bid > hand_size {
+            return Err(RuleError::BidTooHigh { bid, hand_size });
+        }
+        if self.forbidden_bid(hand_size, bids, num_players) == Some(bid) {
+            return Err(RuleError::HookedBid { bid, hand_size });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum RuleError {
+    BidTooHigh {
+        bid: usize,
+        hand_size: usize,
+    },
+    HookedBid {
+        bid: usize,
+        hand_size: usize,
+    },
+    NotYourTurn {
+        seat: usize,
+    },
+    NotBidding,
+    NotPlaying,
+    CardNotInHand(Card),
+    MustFollowSuit(Suit),
+    TooManyTricks {
+        taken: usize,
+        remaining: usize,
+    },
+    TricksDontAddUp {
+        taken: usize,
+        hand_size: usize,
+    },
+    /// The finished scoresheet is already in the stats.
+    AlreadyRecorded,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::BidTooHigh { bid, hand_size } => {
+                write!(f, "can't bid {bid} with only {hand_size} cards in hand")
+            }
+            RuleError::HookedBid { bid, hand_size } => write!(
+                f,
+                "the dealer can't bid {bid}, the total bids would equal the {hand_size} tricks"
+            ),
+            RuleError::NotYourTurn { seat } => write!(f, "it isn't seat {seat}'s turn"),
+            RuleError::NotBidding => write!(f, "bidding is over"),
+            RuleError::NotPlaying => write!(f, "cards can't be played right now"),
+            RuleError::CardNotInHand(card) => write!(f, "{card:?} isn't in hand"),
+            RuleError::MustFollowSuit(suit) => write!(f, "must follow suit with {suit:?}"),
+            RuleError::TooManyTricks { taken, remaining } => {
+                write!(f, "can't take {taken} tricks, only {remaining} left")
+            }
+            RuleError::TricksDontAddUp { taken, hand_size } => write!(
+                f,
+                "{taken} tricks taken in total, but {hand_size} were played"
+            ),
+            RuleError::AlreadyRecorded => {
+                write!(f, "the game is already recorded in the stats")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Whether the table bid for more or fewer tricks than are available this round.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Reflect)]
+pub enum BidBalance {
+    Over(usize),
+    Under(usize),
+    Even,
+}
+
+impl BidBalance {
+    pub fn of(bids: &[usize], hand_size: usize) -> Self {
+        let total: usize = bids.iter().sum();
+        match total.cmp(&hand_size) {
+            std::cmp::Ordering::Greater => BidBalance::Over(total - hand_size),
+            std::cmp::Ordering::Less => BidBalance::Under(hand_size - total),
+            std::cmp::Ordering::Equal => BidBalance::Even,
+        }
+    }
+}
+
+impl fmt::Display for BidBalance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BidBalance::Over(n) => write!(f, "over by {n}"),
+            BidBalance::Under(n) => write!(f, "under by {n}"),
+            BidBalance::Even => write!(f, "even"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOOK: RuleSet = RuleSet { hook: true };
+
+    #[test]
+    fn only_the_dealer_is_hooked() {
+        // Four players and three cards each, the dealer bids last
+        assert_eq!(HOOK.forbidden_bid(3, &[], 4), None);
+        assert_eq!(HOOK.forbidden_bid(3, &[1, 0], 4), None);
+        assert_eq!(HOOK.forbidden_bid(3, &[1, 0, 1], 4), Some(1));
+        assert_eq!(HOOK.forbidden_bid(3, &[0, 0, 0], 4), Some(3));
+        assert_eq!(RuleSet::default().forbidden_bid(3, &[1, 0, 1], 4), None);
+    }
+
+    #[test]
+    fn the_dealer_is_free_once_the_others_bid_over() {
+        assert_eq!(HOOK.forbidden_bid(3, &[2, 2, 0], 4), None);
+        assert_eq!(HOOK.validate_bid(0, 3, &[2, 2, 0], 4), Ok(()));
+    }
+
+    #[test]
+    fn bids_are_checked_against_the_hand_and_the_hook() {
+        assert_eq!(
+            HOOK.validate_bid(1, 3, &[1, 0, 1], 4),
+            Err(RuleError::HookedBid {
+                bid: 1,
+                hand_size: 3
+            })
+        );
+        assert_eq!(HOOK.validate_bid(2, 3, &[1, 0, 1], 4), Ok(()));
+        assert_eq!(HOOK.validate_bid(0, 3, &[1, 0, 1], 4), Ok(()));
+        assert_eq!(RuleSet::default().validate_bid(1, 3, &[1, 0, 1], 4), Ok(()));
+        assert_eq!(
+            HOOK.validate_bid(4, 3, &[], 4),
+            Err(RuleError::BidTooHigh {
+                bid: 4,
+                hand_size: 3
+            })
+        );
+    }
+}
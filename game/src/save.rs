@@ -0,0 +1,237 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use itertools::Itertools;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cli::LaunchConfig;
+use crate::game::Action;
+use crate::game::Game;
+use crate::play::Scripted;
+use crate::play::TableGame;
+use crate::rules::Ledger;
+use crate::rules::RuleSet;
+use crate::rules::MAX_PLAYERS;
+use crate::Card;
+use crate::SeatKind;
+use crate::Session;
+use crate::SpawnSessionEvent;
+
+/// Keeps the unfinished game at every table saved in the `--resume` file as it's played.
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Last, save_tables);
+    }
+}
+
+/// A table's game as it's saved: how it was set up and every move made since.
+///
+/// Replaying the moves rebuilds the game, dealing the same cards again from the seed.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SavedTable {
+    pub seats: Vec<SeatKind>,
+    pub rules: RuleSet,
+    pub seed: u64,
+    /// The seat that dealt the first round.
+    pub dealer: usize,
+    pub decks: Vec<Vec<Card>>,
+    pub moves: Vec<(usize, Action)>,
+    /// The coins when the game was saved, checked against the replayed moves.
+    pub ledger: Ledger,
+}
+
+impl SavedTable {
+    /// The game the saved moves lead to, or why they don't add up.
+    pub fn replay(&self) -> Result<Game, String> {
+        let num_players = self.seats.len();
+        if !(2..=MAX_PLAYERS).contains(&num_players) || self.dealer >= num_players {
+            return Err(format!(
+                "{num_players} seats with seat {} dealing",
+                self.dealer
+            ));
+        }
+        let full_deck = Card::get_new_deck();
+        if self
+            .decks
+            .iter()
+            .any(|deck| deck.len() != full_deck.len() || !deck.iter().all_unique())
+        {
+            return Err("a stacked deck isn't a whole deck".to_owned());
+        }
+
+        let mut game = Game::with_decks(
+            self.rules.clone(),
+            num_players,
+            self.dealer,
+            self.seed,
+            self.decks.clone(),
+        );
+        for (seat, action) in self.moves.iter().copied() {
+            game.apply(seat, action)
+                .map_err(|e| format!("seat {seat} can't {action:?}: {e}"))?;
+        }
+        if game.ledger != self.ledger {
+            return Err("the coins don't match the moves".to_owned());
+        }
+        Ok(game)
+    }
+
+    pub fn session_event(self) -> SpawnSessionEvent {
+        SpawnSessionEvent {
+            seats: self.seats,
+            rules: self.rules,
+            seed: Some(self.seed),
+            dealer: Some(self.dealer),
+            decks: self.decks,
+            moves: self.moves,
+        }
+    }
+}
+
+/// The saved tables to spawn, none if the file is missing or broken.
+///
+/// A table whose moves don't replay is left out with a warning.
+pub fn load(path: &Path) -> Vec<SpawnSessionEvent> {
+    let tables: Vec<SavedTable> = match std::fs::read_to_string(path) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(tables) => tables,
+            Err(e) => {
+                warn!("Failed to parse save file {path:?}, starting new games: {e}");
+                return Vec::new();
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            warn!("Failed to read save file {path:?}, starting new games: {e}");
+            return Vec::new();
+        }
+    };
+    tables
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, table)| match table.replay() {
+            Ok(_) => Some(table.session_event()),
+            Err(e) => {
+                warn!("Can't resume table {} in {path:?}: {e}", i + 1);
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn save(path: &Path, tables: &[SavedTable]) -> std::io::Result<()> {
+    let contents = serde_json::to_string_pretty(tables).map_err(std::io::Error::other)?;
+    std::fs::write(path, contents)
+}
+
+/// Save every unfinished game whenever a move is made or a table goes away.
+fn save_tables(
+    launch_config: Res<LaunchConfig>,
+    game_query: Query<(&Session, Ref<TableGame>), Without<Scripted>>,
+    seat_kind_query: Query<&SeatKind>,
+    mut removed: RemovedComponents<TableGame>,
+) {
+    let Some(path) = &launch_config.resume else {
+        return;
+    };
+    let killed = removed.read().count() > 0;
+    if !killed
+        && !game_query
+            .iter()
+            .any(|(_, table_game)| table_game.is_changed())
+    {
+        return;
+    }
+
+    let tables = game_query
+        .iter()
+        .filter(|(_, table_game)| !table_game.game.is_over())
+        .sorted_by_key(|(session, _)| session.number)
+        .map(|(session, table_game)| SavedTable {
+            seats: seat_kind_query
+                .iter_many(&table_game.seats)
+                .copied()
+                .collect(),
+            rules: table_game.game.rules.clone(),
+            seed: session.seed,
+            dealer: table_game.game.first_dealer(),
+            decks: session.decks.clone(),
+            moves: table_game.game.log.clone(),
+            ledger: table_game.game.ledger.clone(),
+        })
+        .collect_vec();
+    if let Err(e) = save(path, &tables) {
+        warn!("Failed to save the games to {path:?}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::bots::choose_action;
+    use crate::bots::HeuristicStrategy;
+    use crate::BotStyle;
+
+    /// A hook game of four stopped partway through the second round.
+    fn saved_table() -> (SavedTable, Game) {
+        let rules = RuleSet { hook: true };
+        let mut game = Game::with_decks(rules.clone(), 4, 2, 7, Vec::new());
+        let mut rng = StdRng::seed_from_u64(7);
+        while game.round_index < 1 || game.log.len() < 10 {
+            let (seat, action, _) =
+                choose_action(&HeuristicStrategy, &game, &mut rng).expect("the game goes on");
+            game.apply(seat, action)
+                .expect("the heuristic bot plays legally");
+        }
+        let table = SavedTable {
+            seats: SeatKind::default_seats(4, BotStyle::default()),
+            rules,
+            seed: 7,
+            dealer: 2,
+            decks: Vec::new(),
+            moves: game.log.clone(),
+            ledger: game.ledger.clone(),
+        };
+        (table, game)
+    }
+
+    #[test]
+    fn a_saved_game_resumes_where_it_left_off() {
+        let (table, game) = saved_table();
+        let path = std::env::temp_dir().join(format!("poche-save-{}.json", std::process::id()));
+        save(&path, std::slice::from_ref(&table)).expect("the save file is written");
+        let events = load(&path);
+        std::fs::remove_file(&path).expect("the save file is removed");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].seats, table.seats);
+        assert_eq!(events[0].dealer, Some(2));
+        assert_eq!(events[0].moves, game.log);
+        let resumed = table.replay().expect("the moves replay");
+        assert_eq!(resumed.round, game.round);
+        assert_eq!(resumed.results, game.results);
+        assert_eq!(resumed.ledger, game.ledger);
+    }
+
+    #[test]
+    fn a_save_that_doesnt_add_up_isnt_resumed() {
+        let (table, _) = saved_table();
+
+        let mut coins = table.clone();
+        coins.ledger.pot_cents += 10;
+        assert!(coins.replay().is_err());
+
+        let mut moves = table.clone();
+        moves.moves.swap(0, 1);
+        assert!(moves.replay().is_err());
+
+        let mut seats = table;
+        seats.dealer = 4;
+        assert!(seats.replay().is_err());
+    }
+}
@@ -0,0 +1,616 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::ecs::event::ManualEventReader;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+
+use crate::audit::AuditPlugin;
+use crate::audit::AuditViolationEvent;
+use crate::bots::Rationale;
+use crate::bots::Strategy;
+use crate::cli::LaunchConfig;
+use crate::focus::FocusedSession;
+use crate::game::Action;
+use crate::game::Phase;
+use crate::game::SeatView;
+use crate::play::IllegalMoveEvent;
+use crate::play::PlayPlugin;
+use crate::play::RejectedActionEvent;
+use crate::play::SeatStrategy;
+use crate::play::TableGame;
+use crate::play::TakeBack;
+use crate::profiles::ProfileId;
+use crate::profiles::Profiles;
+use crate::rules::RuleSet;
+use crate::save_new_profiles;
+use crate::BotStyle;
+use crate::Card;
+use crate::CardLocation;
+use crate::DealCardsEvent;
+use crate::Dealer;
+use crate::HeadlessSessionPlugin;
+use crate::KillSessionEvent;
+use crate::NeedsDealer;
+use crate::Player;
+use crate::SeatKind;
+use crate::Session;
+use crate::SpawnSessionEvent;
+use crate::Table;
+
+/// How long a frame takes in a [`TestApp::playing`].
+pub const FRAME: Duration = Duration::from_millis(100);
+
+/// How many frames [`TestApp::act`] waits for the human's turn, plenty for every bot to have a go.
+const TURN_FRAMES: usize = 200;
+
+const DIGITS: [KeyCode; 10] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// A headless app running the session systems, advanced one frame at a time.
+pub struct TestApp {
+    pub app: App,
+    violation_reader: ManualEventReader<AuditViolationEvent>,
+    pub violations: Vec<AuditViolationEvent>,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(Profiles::starter());
+        app.add_plugins(HeadlessSessionPlugin);
+        app.add_plugins(AuditPlugin);
+
+        Self {
+            app,
+            violation_reader: default(),
+            violations: Vec::new(),
+        }
+    }
+
+    /// Play games at the tables too, the clock going a fixed [`FRAME`] each frame so bots act on
+    /// cue.
+    pub fn playing() -> Self {
+        let mut test_app = Self::new();
+        let app = &mut test_app.app;
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+        app.insert_resource(LaunchConfig::default());
+        app.init_resource::<ButtonInput<KeyCode>>();
+        app.add_plugins(PlayPlugin);
+        test_app
+    }
+
+    pub fn spawn_session(&mut self, num_players: usize, seed: u64) {
+        self.spawn_seats(
+            SeatKind::default_seats(num_players, BotStyle::default()),
+            seed,
+        );
+    }
+
+    pub fn spawn_seats(&mut self, seats: Vec<SeatKind>, seed: u64) {
+        self.app.world.send_event(SpawnSessionEvent {
+            seats,
+            rules: RuleSet::default(),
+            seed: Some(seed),
+            dealer: None,
+            decks: Vec::new(),
+            moves: Vec::new(),
+        });
+    }
+
+    /// Deal `cards` more cards to every player at every table.
+    pub fn deal(&mut self, cards: usize) {
+        let events = self
+            .sessions()
+            .into_iter()
+            .map(|(session_id, session)| DealCardsEvent {
+                session_id,
+                player_ids: (0..cards)
+                    .flat_map(|_| session.player_ids.iter().copied().sorted())
+                    .collect(),
+            })
+            .collect_vec();
+        self.app.world.send_event_batch(events);
+    }
+
+    /// Kill the oldest session.
+    pub fn kill_session(&mut self) {
+        if let Some((session_id, _)) = self.sessions().first() {
+            self.app.world.send_event(KillSessionEvent {
+                session_id: *session_id,
+            });
+        }
+    }
+
+    pub fn advance(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.app.update();
+            let events = self.app.world.resource::<Events<AuditViolationEvent>>();
+            self.violations
+                .extend(self.violation_reader.read(events).cloned());
+        }
+    }
+
+    /// Press `key` for a frame.
+    pub fn press(&mut self, key: KeyCode) {
+        self.app
+            .world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(key);
+        self.advance(1);
+        let mut input = self.app.world.resource_mut::<ButtonInput<KeyCode>>();
+        input.release(key);
+        input.clear();
+    }
+
+    /// Wait for the human in the first seat of the oldest table to be up, then take `action` with
+    /// the key they'd press.
+    pub fn act(&mut self, action: Action) -> Result<(), String> {
+        for _ in 0..TURN_FRAMES {
+            if let Some(&(session_id, _)) = self.sessions().first()
+                && self
+                    .table_game(session_id)
+                    .and_then(TableGame::to_act)
+                    .is_some_and(|(seat, _)| seat == 0)
+            {
+                break;
+            }
+            self.advance(1);
+        }
+        let Some(&(session_id, _)) = self.sessions().first() else {
+            return Err("there's no table".to_string());
+        };
+        self.app.insert_resource(FocusedSession(Some(session_id)));
+        let Some(table_game) = self.table_game(session_id) else {
+            return Err("the game never started".to_string());
+        };
+        let Some((0, player_id)) = table_game.to_act() else {
+            return Err(format!(
+                "it's not the first seat's turn, {:?}",
+                table_game.to_act()
+            ));
+        };
+        if self.app.world.get::<SeatKind>(player_id) != Some(&SeatKind::Human) {
+            return Err("the first seat isn't a human".to_string());
+        }
+        let number = match action {
+            Action::Bid(bid) => bid,
+            Action::Play(card) => match table_game.sorted_hand(0).iter().position(|c| *c == card) {
+                Some(i) => i + 1,
+                None => {
+                    let hand = table_game.sorted_hand(0).iter().join(" ");
+                    return Err(format!("{card} isn't in hand, {hand}"));
+                }
+            },
+        };
+        let Some(key) = DIGITS.get(number).copied() else {
+            return Err(format!("there's no key for {action:?}"));
+        };
+
+        let moves = table_game.game.log.len();
+        let mut rejected = self
+            .app
+            .world
+            .resource::<Events<RejectedActionEvent>>()
+            .get_reader_current();
+        self.press(key);
+        self.advance(1);
+        if self
+            .table_game(session_id)
+            .is_some_and(|table_game| table_game.game.log.len() > moves)
+        {
+            return Ok(());
+        }
+        let events = self.app.world.resource::<Events<RejectedActionEvent>>();
+        Err(match rejected.read(events).next() {
+            Some(event) => event.to_string(),
+            None => format!("{action:?} wasn't taken"),
+        })
+    }
+
+    /// The game at a table, once it's started.
+    pub fn table_game(&self, session_id: Entity) -> Option<&TableGame> {
+        self.app.world.get::<TableGame>(session_id)
+    }
+
+    pub fn sessions(&mut self) -> Vec<(Entity, Session)> {
+        self.app
+            .world
+            .query::<(Entity, &Session)>()
+            .iter(&self.app.world)
+            .map(|(session_id, session)| (session_id, session.clone()))
+            .sorted_by_key(|(session_id, _)| *session_id)
+            .collect()
+    }
+
+    pub fn count<T: Component>(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<T>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    /// Bids and plays made so far, at every table.
+    pub fn count_moves(&mut self) -> usize {
+        self.app
+            .world
+            .query::<&TableGame>()
+            .iter(&self.app.world)
+            .map(|table_game| table_game.game.log.len())
+            .sum()
+    }
+
+    pub fn count_cards(&mut self, predicate: impl Fn(&CardLocation) -> bool) -> usize {
+        self.app
+            .world
+            .query::<&CardLocation>()
+            .iter(&self.app.world)
+            .filter(|location| predicate(location))
+            .count()
+    }
+}
+
+/// A scripted game, loaded from a JSON file in the `scenarios` folder.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub description: String,
+    pub players: usize,
+    pub seed: u64,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Advance this many frames.
+    Wait(usize),
+    /// Deal this many cards to every player at every table.
+    Deal(usize),
+    SpawnTable {
+        players: usize,
+        seed: u64,
+    },
+    KillTable,
+    /// Wait for the human in the first seat of the oldest table, then bid this.
+    Bid(usize),
+    /// Wait for the human in the first seat of the oldest table, then play this card, like `"QH"`.
+    Play(Card),
+    Expect(Expectation),
+}
+
+/// Counts across every table; anything left out isn't checked.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    pub tables: Option<usize>,
+    pub players: Option<usize>,
+    pub dealers: Option<usize>,
+    pub tables_needing_dealer: Option<usize>,
+    pub cards_in_deck: Option<usize>,
+    pub cards_in_hands: Option<usize>,
+    pub moves: Option<usize>,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    /// Play the scenario, failing at the first step that doesn't go as expected.
+    ///
+    /// The audit runs the whole time, any violation is a failure. Bidding or playing starts the
+    /// games, with bots taking their turns, and the games deal for themselves.
+    pub fn run(&self) -> Result<(), String> {
+        let playing = self
+            .steps
+            .iter()
+            .any(|step| matches!(step, Step::Bid(_) | Step::Play(_)));
+        let mut app = match playing {
+            true => TestApp::playing(),
+            false => TestApp::new(),
+        };
+        app.spawn_session(self.players, self.seed);
+        for (i, step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Wait(frames) => app.advance(*frames),
+                Step::Deal(cards) => app.deal(*cards),
+                Step::SpawnTable { players, seed } => app.spawn_session(*players, *seed),
+                Step::KillTable => app.kill_session(),
+                Step::Bid(bid) => app
+                    .act(Action::Bid(*bid))
+                    .map_err(|e| format!("step {}: {e}", i + 1))?,
+                Step::Play(card) => app
+                    .act(Action::Play(*card))
+                    .map_err(|e| format!("step {}: {e}", i + 1))?,
+                Step::Expect(expectation) => expectation
+                    .check(&mut app)
+                    .map_err(|e| format!("step {}: {e}", i + 1))?,
+            }
+            if let Some(event) = app.violations.first() {
+                return Err(format!("step {}: audit failed: {}", i + 1, event.violation));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Expectation {
+    fn check(&self, app: &mut TestApp) -> Result<(), String> {
+        let actual = [
+            ("tables", self.tables, app.count::<Table>()),
+            ("players", self.players, app.count::<Player>()),
+            ("dealers", self.dealers, app.count::<Dealer>()),
+            (
+                "tables_needing_dealer",
+                self.tables_needing_dealer,
+                app.count::<NeedsDealer>(),
+            ),
+            (
+                "cards_in_deck",
+                self.cards_in_deck,
+                app.count_cards(|location| matches!(location, CardLocation::Deck { .. })),
+            ),
+            (
+                "cards_in_hands",
+                self.cards_in_hands,
+                app.count_cards(|location| matches!(location, CardLocation::Hand { .. })),
+            ),
+            ("moves", self.moves, app.count_moves()),
+        ];
+        let mismatches = actual
+            .iter()
+            .filter_map(|(name, expected, actual)| match expected {
+                Some(expected) if expected != actual => {
+                    Some(format!("expected {expected} {name}, found {actual}"))
+                }
+                _ => None,
+            })
+            .collect_vec();
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(mismatches.join(", ")),
+        }
+    }
+}
+
+fn scenario_paths() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+    std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("failed to read {dir:?}: {e}"))
+        .map(|entry| entry.expect("readable directory entry").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .sorted()
+        .collect()
+}
+
+#[test]
+fn dealer_is_chosen_when_a_table_spawns() {
+    let mut app = TestApp::new();
+    app.spawn_session(4, 1);
+    app.advance(10);
+
+    assert_eq!(app.count::<Dealer>(), 1);
+    assert_eq!(app.count::<NeedsDealer>(), 0);
+    assert!(app.violations.is_empty(), "{:?}", app.violations);
+}
+
+#[test]
+fn every_table_gets_its_own_deck() {
+    let mut app = TestApp::new();
+    app.spawn_session(3, 1);
+    app.spawn_session(5, 2);
+    app.advance(10);
+
+    let sessions = app.sessions();
+    assert_eq!(sessions.len(), 2);
+    for (_, session) in sessions {
+        assert_eq!(session.card_ids.len(), 52);
+    }
+    assert_eq!(app.count::<Dealer>(), 2);
+    assert!(app.violations.is_empty(), "{:?}", app.violations);
+}
+
+#[test]
+fn profiles_made_for_a_table_are_saved() {
+    let path = std::env::temp_dir().join(format!("poche-profiles-{}.json", std::process::id()));
+    let mut app = TestApp::new();
+    app.app.insert_resource(LaunchConfig {
+        profiles: path.clone(),
+        ..default()
+    });
+    app.app.add_systems(Update, save_new_profiles);
+    // The starter profiles have one human, so two more are made
+    app.app.world.send_event(SpawnSessionEvent {
+        seats: vec![
+            SeatKind::Human,
+            SeatKind::Human,
+            SeatKind::Bot(BotStyle::default()),
+            SeatKind::Human,
+        ],
+        rules: RuleSet::default(),
+        seed: Some(1),
+        dealer: None,
+        decks: Vec::new(),
+        moves: Vec::new(),
+    });
+    app.advance(2);
+
+    let saved = Profiles::load(&path);
+    std::fs::remove_file(&path).expect("the profiles file was saved");
+    let profile_ids = app
+        .app
+        .world
+        .query_filtered::<&ProfileId, With<Player>>()
+        .iter(&app.app.world)
+        .copied()
+        .collect_vec();
+    assert_eq!(profile_ids.len(), 4);
+    for profile_id in profile_ids {
+        assert!(
+            saved.get(profile_id).is_some(),
+            "{profile_id:?} wasn't saved"
+        );
+    }
+}
+
+#[test]
+fn backspace_takes_back_a_move_after_the_bots_have_moved_on() {
+    let mut app = TestApp::playing();
+    let bot = SeatKind::Bot(BotStyle::default());
+    app.spawn_seats(vec![SeatKind::Human, bot, bot], 3);
+    app.advance(2);
+    let (session_id, _) = app.sessions()[0];
+    app.app.insert_resource(FocusedSession(Some(session_id)));
+
+    // Bid, then wait to play the one card of the first round
+    for _ in 0..100 {
+        if let Some(table_game) = app.table_game(session_id)
+            && table_game.to_act().is_some_and(|(seat, _)| seat == 0)
+        {
+            let Phase::Bidding { .. } = table_game.game.phase() else {
+                break;
+            };
+            let Some(Action::Bid(bid)) = table_game.game.legal_actions().first().copied() else {
+                panic!("the human can't bid");
+            };
+            app.press([KeyCode::Digit0, KeyCode::Digit1][bid]);
+        }
+        app.advance(1);
+    }
+    let before = app.table_game(session_id).expect("a game").clone();
+    assert!(matches!(before.game.phase(), Phase::Playing { .. }));
+    let human = before.seats[0];
+    let card = before.game.round.hands[0][0];
+
+    app.press(KeyCode::Digit1);
+    // The bots finish the round and bid in the next until it's the human's turn again
+    app.advance(100);
+    let moved_on = app.table_game(session_id).expect("a game");
+    assert_eq!(moved_on.to_act().map(|(seat, _)| seat), Some(0));
+    assert!(moved_on.game.log.len() > before.game.log.len() + 1);
+    assert_ne!(moved_on.game.results, before.game.results);
+    assert_ne!(moved_on.game.ledger, before.game.ledger);
+
+    app.press(KeyCode::Backspace);
+    app.advance(1);
+    let taken_back = app.table_game(session_id).expect("a game");
+    assert_eq!(taken_back.game.log, before.game.log);
+    assert_eq!(taken_back.game.round, before.game.round);
+    assert_eq!(taken_back.game.results, before.game.results);
+    assert_eq!(taken_back.game.ledger, before.game.ledger);
+    assert!(app.app.world.get::<TakeBack>(session_id).is_none());
+    let session = &app.sessions()[0].1;
+    let location = session
+        .card_ids
+        .iter()
+        .find(|card_id| app.app.world.get::<Card>(**card_id) == Some(&card))
+        .and_then(|card_id| app.app.world.get::<CardLocation>(*card_id));
+    assert_eq!(
+        location,
+        Some(&CardLocation::Hand {
+            player: human,
+            index_from_left: 0,
+        })
+    );
+    assert!(app.violations.is_empty(), "{:?}", app.violations);
+}
+
+/// Always bids one more than it's allowed to.
+struct Overbidding;
+impl Strategy for Overbidding {
+    fn name(&self) -> &'static str {
+        "overbidding"
+    }
+
+    fn bid(&self, _: &SeatView, legal: &[usize], _: &mut StdRng) -> (usize, Option<Rationale>) {
+        (legal[legal.len() - 1] + 1, None)
+    }
+
+    fn play(&self, _: &SeatView, legal: &[Card], _: &mut StdRng) -> (Card, Option<Rationale>) {
+        (legal[0], None)
+    }
+}
+
+#[test]
+fn illegal_bot_moves_are_made_legal() {
+    let mut app = TestApp::playing();
+    let bot = SeatKind::Bot(BotStyle::default());
+    app.spawn_seats(vec![bot, bot, bot], 1);
+    app.advance(1);
+    let (session_id, session) = app.sessions().remove(0);
+    for player_id in session.player_ids {
+        app.app
+            .world
+            .entity_mut(player_id)
+            .insert(SeatStrategy(Arc::new(Overbidding)));
+    }
+
+    let mut reader = ManualEventReader::<IllegalMoveEvent>::default();
+    let mut illegal_moves = 0;
+    for _ in 0..30 {
+        app.advance(1);
+        let events = app.app.world.resource::<Events<IllegalMoveEvent>>();
+        illegal_moves += reader.read(events).count();
+    }
+    let game = &app.table_game(session_id).expect("a game").game;
+    let bids = game
+        .log
+        .iter()
+        .filter(|(_, action)| matches!(action, Action::Bid(_)))
+        .count();
+    assert!(game.log.len() > bids, "{:?}", game.log);
+    assert_eq!(illegal_moves, bids);
+    assert!(app.violations.is_empty(), "{:?}", app.violations);
+}
+
+#[test]
+fn a_move_the_rules_turn_down_fails_the_scenario() {
+    let scenario = Scenario {
+        description: "Bidding more than the one card in hand".to_string(),
+        players: 3,
+        seed: 1,
+        steps: vec![Step::Bid(2)],
+    };
+
+    let error = scenario.run().expect_err("the bid is turned down");
+    assert!(error.starts_with("step 1: Seat 1 can't bid 2"), "{error}");
+}
+
+#[test]
+fn scenario_files_pass() {
+    let paths = scenario_paths();
+    assert!(!paths.is_empty(), "no scenarios found");
+    let failures = paths
+        .iter()
+        .filter_map(|path| match Scenario::load(path) {
+            Ok(scenario) => scenario
+                .run()
+                .err()
+                .map(|e| format!("{path:?} ({}): {e}", scenario.description)),
+            Err(e) => Some(format!("{path:?}: {e}")),
+        })
+        .collect_vec();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
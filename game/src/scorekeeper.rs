@@ -0,0 +1,648 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::cli::LaunchConfig;
+use crate::profiles::ProfileId;
+use crate::profiles::Profiles;
+use crate::rules::round_hand_sizes;
+use crate::rules::score;
+use crate::rules::winners;
+use crate::rules::BidBalance;
+use crate::rules::Ledger;
+use crate::rules::RuleError;
+use crate::rules::RuleSet;
+use crate::stats::GameFinishedEvent;
+use crate::stats::PlayerGameResult;
+use crate::stats::RoundResult;
+use crate::stats::StatsPlugin;
+
+/// Run the scorekeeper on its own, for games played with real cards.
+pub fn run(launch_config: LaunchConfig) {
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            title: "Poche Scorekeeper".into(),
+            ..default()
+        }),
+        ..default()
+    }));
+    app.insert_resource(launch_config);
+    app.add_plugins(StatsPlugin);
+    app.add_plugins(ScorekeeperPlugin);
+    app.run();
+}
+
+pub struct ScorekeeperPlugin;
+impl Plugin for ScorekeeperPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            Update,
+            (
+                handle_scoresheet_key_presses,
+                record_finished_scoresheet,
+                update_scoresheet_text,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// One row of the paper scoresheet.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SheetRound {
+    pub hand_size: usize,
+    pub dealer: usize,
+    pub bids: Vec<Option<usize>>,
+    pub taken: Vec<Option<usize>>,
+}
+impl SheetRound {
+    fn new(num_players: usize, hand_size: usize, dealer: usize) -> Self {
+        Self {
+            hand_size,
+            dealer,
+            bids: vec![None; num_players],
+            taken: vec![None; num_players],
+        }
+    }
+
+    /// Seats starting left of the dealer and ending with the dealer.
+    fn order(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        let n = self.bids.len();
+        (1..=n).map(move |i| (self.dealer + i) % n)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.taken.iter().all(|taken| taken.is_some())
+    }
+
+    /// How each seat did, once the tricks are entered.
+    pub fn results(&self) -> Option<Vec<RoundResult>> {
+        self.bids
+            .iter()
+            .zip(self.taken.iter())
+            .map(|(bid, taken)| {
+                let (bid, taken) = (bid.as_ref()?, taken.as_ref()?);
+                Some(RoundResult {
+                    hand_size: self.hand_size,
+                    bid: *bid,
+                    taken: *taken,
+                    points: score(*bid, *taken, self.hand_size),
+                })
+            })
+            .collect()
+    }
+}
+
+/// What the scorekeeper needs to write down next.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Entry {
+    Bid { seat: usize },
+    Tricks { seat: usize },
+    GameOver,
+}
+
+/// The scorekeeper's table, without any cards.
+#[derive(Resource, Debug, Clone)]
+pub struct Scoresheet {
+    pub names: Vec<String>,
+    pub profile_ids: Vec<ProfileId>,
+    pub rules: RuleSet,
+    pub hand_sizes: Vec<usize>,
+    pub rounds: Vec<SheetRound>,
+    /// The last error, shown until the next valid entry.
+    pub error: Option<RuleError>,
+    recorded: bool,
+}
+
+impl Scoresheet {
+    /// `names` are clockwise, starting with the first dealer.
+    pub fn new(names: Vec<String>, profile_ids: Vec<ProfileId>, rules: RuleSet) -> Self {
+        let hand_sizes = round_hand_sizes(names.len());
+        let first = SheetRound::new(names.len(), hand_sizes[0], 0);
+        Self {
+            names,
+            profile_ids,
+            rules,
+            hand_sizes,
+            rounds: vec![first],
+            error: None,
+            recorded: false,
+        }
+    }
+
+    fn current(&self) -> &SheetRound {
+        self.rounds.last().expect("there is always a round")
+    }
+
+    pub fn next_entry(&self) -> Entry {
+        let round = self.current();
+        if let Some(seat) = round.order().find(|seat| round.bids[*seat].is_none()) {
+            return Entry::Bid { seat };
+        }
+        if let Some(seat) = round.order().find(|seat| round.taken[*seat].is_none()) {
+            return Entry::Tricks { seat };
+        }
+        Entry::GameOver
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.next_entry() == Entry::GameOver
+    }
+
+    fn bids_in_order(&self) -> Vec<usize> {
+        let round = self.current();
+        round.order().map_while(|seat| round.bids[seat]).collect()
+    }
+
+    /// The bid the next bidder can't make under the hook rule.
+    pub fn forbidden_bid(&self) -> Option<usize> {
+        self.rules.forbidden_bid(
+            self.current().hand_size,
+            &self.bids_in_order(),
+            self.names.len(),
+        )
+    }
+
+    /// Write down the next bid or trick count.
+    pub fn enter(&mut self, value: usize) -> Result<(), RuleError> {
+        let result = self.try_enter(value);
+        self.error = result.clone().err();
+        result
+    }
+
+    fn try_enter(&mut self, value: usize) -> Result<(), RuleError> {
+        let num_players = self.names.len();
+        match self.next_entry() {
+            Entry::Bid { seat } => {
+                let hand_size = self.current().hand_size;
+                self.rules
+                    .validate_bid(value, hand_size, &self.bids_in_order(), num_players)?;
+                self.rounds.last_mut().expect("current round").bids[seat] = Some(value);
+            }
+            Entry::Tricks { seat } => {
+                let round = self.rounds.last_mut().expect("current round");
+                let entered: usize = round.taken.iter().flatten().sum();
+                let remaining = round.hand_size - entered;
+                let last = round.taken.iter().filter(|taken| taken.is_none()).count() == 1;
+                if value > remaining {
+                    return Err(RuleError::TooManyTricks {
+                        taken: value,
+                        remaining,
+                    });
+                }
+                if last && value != remaining {
+                    return Err(RuleError::TricksDontAddUp {
+                        taken: entered + value,
+                        hand_size: round.hand_size,
+                    });
+                }
+                round.taken[seat] = Some(value);
+
+                // Deal the next round, the dealer rotates left
+                let (complete, dealer) = (round.is_complete(), round.dealer);
+                if complete && let Some(hand_size) = self.hand_sizes.get(self.rounds.len()) {
+                    let dealer = (dealer + 1) % num_players;
+                    self.rounds
+                        .push(SheetRound::new(num_players, *hand_size, dealer));
+                }
+            }
+            Entry::GameOver => return Err(RuleError::NotBidding),
+        }
+        Ok(())
+    }
+
+    /// Rub out the last entry, going back into the previous round if needed.
+    ///
+    /// Once the finished sheet is recorded the stats would no longer match it, so it's final.
+    pub fn undo(&mut self) -> Result<(), RuleError> {
+        if self.recorded {
+            self.error = Some(RuleError::AlreadyRecorded);
+            return Err(RuleError::AlreadyRecorded);
+        }
+        self.error = None;
+        if self.rounds.len() > 1 && self.current().bids.iter().all(|bid| bid.is_none()) {
+            self.rounds.pop();
+        }
+        let round = self.rounds.last_mut().expect("current round");
+        let order = round.order().collect_vec();
+        if let Some(seat) = order
+            .iter()
+            .rev()
+            .find(|seat| round.taken[**seat].is_some())
+        {
+            round.taken[*seat] = None;
+        } else if let Some(seat) = order.iter().rev().find(|seat| round.bids[**seat].is_some()) {
+            round.bids[*seat] = None;
+        }
+        Ok(())
+    }
+
+    /// Results of every finished round by seat.
+    pub fn results(&self) -> Vec<Vec<RoundResult>> {
+        let mut by_seat = vec![Vec::new(); self.names.len()];
+        for results in self.rounds.iter().filter_map(|round| round.results()) {
+            for (seat, result) in results.into_iter().enumerate() {
+                by_seat[seat].push(result);
+            }
+        }
+        by_seat
+    }
+
+    pub fn points(&self) -> Vec<usize> {
+        self.results()
+            .iter()
+            .map(|results| results.iter().map(|result| result.points).sum())
+            .collect()
+    }
+
+    /// The ante, poche payments and, once the game is over, the pot paid to the winners.
+    pub fn ledger(&self) -> Ledger {
+        let mut ledger = Ledger::new(self.names.len());
+        for (seat, results) in self.results().iter().enumerate() {
+            for _ in results.iter().filter(|result| !result.made()) {
+                ledger.pay_poche(seat);
+            }
+        }
+        if self.is_over() {
+            ledger.pay_winners(&winners(&self.points()));
+        }
+        ledger
+    }
+
+    /// A cell as the scorekeeper would write it: the bid, then a 1 or 2 prefix, or a dot for a poche.
+    fn cell(round: &SheetRound, seat: usize) -> String {
+        match (round.bids[seat], round.taken[seat]) {
+            (None, _) => "".into(),
+            (Some(bid), None) => bid.to_string(),
+            (Some(bid), Some(taken)) => match score(bid, taken, round.hand_size) {
+                0 => "*".into(),
+                points => points.to_string(),
+            },
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let header = std::iter::once(format!("{:<6}{:<6}", "Round", "Cards"))
+            .chain(self.names.iter().map(|name| format!("{name:>8.8}")))
+            .chain(std::iter::once("  Bids".to_owned()))
+            .join("");
+        let rows = self.rounds.iter().enumerate().map(|(i, round)| {
+            let bids = round.bids.iter().flatten().copied().collect_vec();
+            let balance = match bids.len() == self.names.len() {
+                true => BidBalance::of(&bids, round.hand_size).to_string(),
+                false => String::new(),
+            };
+            std::iter::once(format!("{:<6}{:<6}", i + 1, round.hand_size))
+                .chain((0..self.names.len()).map(|seat| {
+                    let dealer = if seat == round.dealer { "d" } else { " " };
+                    format!("{:>7}{dealer}", Self::cell(round, seat))
+                }))
+                .chain(std::iter::once(format!("  {balance}")))
+                .join("")
+        });
+        let ledger = self.ledger();
+        let totals = std::iter::once(format!("{:<12}", "Points"))
+            .chain(self.points().iter().map(|points| format!("{points:>7} ")))
+            .join("");
+        let money = std::iter::once(format!("{:<12}", "Money"))
+            .chain(
+                ledger
+                    .money_cents
+                    .iter()
+                    .map(|cents| format!("{:>7} ", format!("{:.2}", *cents as f32 / 100.0))),
+            )
+            .join("");
+
+        let prompt = match self.next_entry() {
+            Entry::Bid { seat } => {
+                let forbidden = self
+                    .forbidden_bid()
+                    .map(|bid| format!(" (can't bid {bid})"))
+                    .unwrap_or_default();
+                format!("{} bids?{forbidden}", self.names[seat])
+            }
+            Entry::Tricks { seat } => format!("How many tricks did {} take?", self.names[seat]),
+            Entry::GameOver => {
+                let names = winners(&self.points())
+                    .into_iter()
+                    .map(|seat| self.names[seat].as_str())
+                    .join(" and ");
+                format!("Game over, {names} won! Escape to quit")
+            }
+        };
+        let error = self
+            .error
+            .as_ref()
+            .map(|e| format!("\n{e}"))
+            .unwrap_or_default();
+
+        format!(
+            "{header}\n{}\n\n{totals}\n{money}\nPot {:.2}\n\n{prompt}{error}\n\nType a number, Backspace to undo. d = dealer, * = poche",
+            rows.collect_vec().join("\n"),
+            ledger.pot_cents as f32 / 100.0,
+        )
+    }
+}
+
+#[derive(Component)]
+struct ScoresheetText;
+
+fn setup(mut commands: Commands, launch_config: Res<LaunchConfig>) {
+    let mut profiles = Profiles::load_or_create(&launch_config.profiles);
+    let profile_ids = launch_config
+        .names
+        .iter()
+        .map(|name| profiles.find_or_create_human(name))
+        .collect_vec();
+    if let Err(e) = profiles.save(&launch_config.profiles) {
+        warn!(
+            "Failed to save profiles to {:?}: {e}",
+            launch_config.profiles
+        );
+    }
+    commands.insert_resource(Scoresheet::new(
+        launch_config.names.clone(),
+        profile_ids,
+        launch_config.rules.clone(),
+    ));
+    commands.insert_resource(profiles);
+
+    commands.spawn(Camera2dBundle::default());
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        }),
+        ScoresheetText,
+        Name::new("Scoresheet"),
+    ));
+}
+
+fn handle_scoresheet_key_presses(
+    input: Res<ButtonInput<KeyCode>>,
+    mut scoresheet: ResMut<Scoresheet>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        exit.send(AppExit);
+    }
+    if input.just_pressed(KeyCode::Backspace)
+        && let Err(e) = scoresheet.undo()
+    {
+        info!("Rejected scoresheet undo: {e}");
+    }
+    for key in input.get_just_pressed() {
+        if let Some(value) = digit(*key)
+            && let Err(e) = scoresheet.enter(value)
+        {
+            info!("Rejected scoresheet entry: {e}");
+        }
+    }
+}
+
+/// The number on a number key, from the top row or the numpad.
+pub fn digit(key: KeyCode) -> Option<usize> {
+    let digits = [
+        (KeyCode::Digit0, KeyCode::Numpad0),
+        (KeyCode::Digit1, KeyCode::Numpad1),
+        (KeyCode::Digit2, KeyCode::Numpad2),
+        (KeyCode::Digit3, KeyCode::Numpad3),
+        (KeyCode::Digit4, KeyCode::Numpad4),
+        (KeyCode::Digit5, KeyCode::Numpad5),
+        (KeyCode::Digit6, KeyCode::Numpad6),
+        (KeyCode::Digit7, KeyCode::Numpad7),
+        (KeyCode::Digit8, KeyCode::Numpad8),
+        (KeyCode::Digit9, KeyCode::Numpad9),
+    ];
+    digits
+        .iter()
+        .position(|(digit, numpad)| key == *digit || key == *numpad)
+}
+
+fn record_finished_scoresheet(
+    mut scoresheet: ResMut<Scoresheet>,
+    mut game_finished_events: EventWriter<GameFinishedEvent>,
+) {
+    if scoresheet.recorded || !scoresheet.is_over() {
+        return;
+    }
+    let ledger = scoresheet.ledger();
+    let players = scoresheet
+        .results()
+        .into_iter()
+        .enumerate()
+        .map(|(seat, rounds)| PlayerGameResult {
+            profile_id: scoresheet.profile_ids[seat],
+            rounds,
+            money_cents: ledger.money_cents[seat],
+        })
+        .collect();
+    game_finished_events.send(GameFinishedEvent { players });
+    scoresheet.recorded = true;
+}
+
+fn update_scoresheet_text(
+    scoresheet: Res<Scoresheet>,
+    mut text_query: Query<&mut Text, With<ScoresheetText>>,
+) {
+    if !scoresheet.is_changed() {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = scoresheet.render();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::ANTE;
+    use crate::rules::POCHE_PENALTY;
+
+    fn sheet(names: &[&str], rules: RuleSet) -> Scoresheet {
+        let names = names.iter().map(|name| name.to_string()).collect_vec();
+        let profile_ids = (0..names.len() as u64).map(ProfileId).collect();
+        Scoresheet::new(names, profile_ids, rules)
+    }
+
+    /// The first round of three players, one card each: Bob poches, Cy makes 0 and Ann, the
+    /// dealer, takes the one trick.
+    fn first_round() -> Scoresheet {
+        let mut sheet = sheet(&["Ann", "Bob", "Cy"], RuleSet { hook: true });
+        for value in [1, 0, 1, 0, 0, 1] {
+            sheet.enter(value).expect("a valid entry");
+        }
+        sheet
+    }
+
+    fn finished_sheet() -> Scoresheet {
+        let names = vec!["Ann".to_owned(), "Bob".to_owned()];
+        let mut sheet =
+            Scoresheet::new(names, vec![ProfileId(0), ProfileId(1)], RuleSet::default());
+        while !sheet.is_over() {
+            // Whoever can't bid 0 bids 1, and the last seat takes whatever tricks are left
+            if sheet.enter(0).is_err() {
+                let entered = sheet.enter(1).or_else(|_| {
+                    let round = sheet.current();
+                    let taken: usize = round.taken.iter().flatten().sum();
+                    sheet.enter(round.hand_size - taken)
+                });
+                entered.expect("some entry is valid");
+            }
+        }
+        sheet
+    }
+
+    #[test]
+    fn a_recorded_sheet_cant_be_undone() {
+        let mut sheet = finished_sheet();
+        let rounds = sheet.rounds.clone();
+        assert_eq!(sheet.undo(), Ok(()));
+        assert!(!sheet.is_over());
+
+        let mut sheet = finished_sheet();
+        sheet.recorded = true;
+        assert_eq!(sheet.undo(), Err(RuleError::AlreadyRecorded));
+        assert_eq!(sheet.rounds, rounds);
+        assert!(sheet.is_over());
+    }
+
+    #[test]
+    fn bids_and_tricks_are_checked_as_they_are_entered() {
+        let mut sheet = sheet(&["Ann", "Bob", "Cy"], RuleSet { hook: true });
+        assert_eq!(sheet.next_entry(), Entry::Bid { seat: 1 });
+        let too_high = RuleError::BidTooHigh {
+            bid: 2,
+            hand_size: 1,
+        };
+        assert_eq!(sheet.enter(2), Err(too_high.clone()));
+        assert_eq!(sheet.error, Some(too_high));
+        assert_eq!(sheet.enter(1), Ok(()));
+        assert_eq!(sheet.error, None);
+        assert_eq!(sheet.enter(0), Ok(()));
+
+        // Ann deals, so bids last and can't make the bids add up to the one trick
+        assert_eq!(sheet.next_entry(), Entry::Bid { seat: 0 });
+        assert_eq!(sheet.forbidden_bid(), Some(0));
+        assert_eq!(
+            sheet.enter(0),
+            Err(RuleError::HookedBid {
+                bid: 0,
+                hand_size: 1
+            })
+        );
+        assert_eq!(sheet.enter(1), Ok(()));
+
+        assert_eq!(sheet.next_entry(), Entry::Tricks { seat: 1 });
+        assert_eq!(
+            sheet.enter(2),
+            Err(RuleError::TooManyTricks {
+                taken: 2,
+                remaining: 1
+            })
+        );
+        assert_eq!(sheet.enter(0), Ok(()));
+        assert_eq!(sheet.enter(0), Ok(()));
+        assert_eq!(
+            sheet.enter(0),
+            Err(RuleError::TricksDontAddUp {
+                taken: 0,
+                hand_size: 1
+            })
+        );
+        assert_eq!(sheet.enter(1), Ok(()));
+
+        // The next round is dealt by Bob with two cards each
+        assert_eq!(sheet.rounds.len(), 2);
+        assert_eq!((sheet.rounds[1].hand_size, sheet.rounds[1].dealer), (2, 1));
+        assert_eq!(sheet.next_entry(), Entry::Bid { seat: 2 });
+    }
+
+    #[test]
+    fn without_the_hook_the_dealer_bids_anything_in_hand() {
+        let mut sheet = sheet(&["Ann", "Bob"], RuleSet { hook: false });
+        assert_eq!(sheet.enter(1), Ok(()));
+        assert_eq!(sheet.forbidden_bid(), None);
+        assert_eq!(sheet.enter(0), Ok(()));
+    }
+
+    #[test]
+    fn points_add_up_by_seat() {
+        let mut sheet = first_round();
+        assert_eq!(sheet.points(), vec![21, 0, 10]);
+
+        // Bob deals two cards each: Cy and Bob make 1 and Ann poches
+        for value in [1, 1, 1, 1, 0, 1] {
+            sheet.enter(value).expect("a valid entry");
+        }
+        assert_eq!(sheet.points(), vec![21, 11, 21]);
+        let results = sheet.results();
+        assert_eq!(results.iter().map(Vec::len).collect_vec(), vec![2, 2, 2]);
+        assert_eq!((results[0][1].bid, results[0][1].taken), (1, 0));
+    }
+
+    #[test]
+    fn everyone_antes_and_every_poche_pays_into_the_pot() {
+        let sheet = sheet(&["Ann", "Bob", "Cy"], RuleSet::default());
+        let ante = ANTE.value() as i64;
+        assert_eq!(sheet.ledger().pot_cents, 3 * ANTE.value());
+        assert_eq!(sheet.ledger().money_cents, vec![-ante; 3]);
+
+        // Nobody has won yet, Bob's poche only adds to the pot
+        let ledger = first_round().ledger();
+        let penalty = POCHE_PENALTY.value() as i64;
+        assert_eq!(ledger.pot_cents, 3 * ANTE.value() + POCHE_PENALTY.value());
+        assert_eq!(ledger.money_cents, vec![-ante, -ante - penalty, -ante]);
+    }
+
+    #[test]
+    fn a_poche_scores_nothing_and_is_marked() {
+        let sheet = first_round();
+        let round = &sheet.rounds[0];
+        assert_eq!(sheet.results()[1][0].points, 0);
+        assert!(!sheet.results()[1][0].made());
+        assert_eq!(Scoresheet::cell(round, 1), "*");
+        assert_eq!(Scoresheet::cell(round, 2), "10");
+        assert_eq!(Scoresheet::cell(round, 0), "21");
+        assert_eq!(Scoresheet::cell(&sheet.rounds[1], 2), "");
+    }
+
+    #[test]
+    fn the_winners_take_the_pot_when_the_sheet_is_finished() {
+        let sheet = finished_sheet();
+        let ledger = sheet.ledger();
+        let winners = winners(&sheet.points());
+        let results = sheet.results();
+        let poches = |seat: usize| results[seat].iter().filter(|result| !result.made()).count();
+        let pot = 2 * ANTE.value() + (poches(0) + poches(1)) * POCHE_PENALTY.value();
+        let share = pot / winners.len();
+        assert_eq!(ledger.pot_cents, pot - share * winners.len());
+        for seat in 0..2 {
+            let paid = ANTE.value() + poches(seat) * POCHE_PENALTY.value();
+            let won = match winners.contains(&seat) {
+                true => share,
+                false => 0,
+            };
+            assert_eq!(
+                ledger.money_cents[seat],
+                won as i64 - paid as i64,
+                "seat {seat}"
+            );
+        }
+        // Nothing is made or lost on the way
+        let net: i64 = ledger.money_cents.iter().sum();
+        assert_eq!(net + ledger.pot_cents as i64, 0);
+    }
+}
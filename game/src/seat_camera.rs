@@ -0,0 +1,236 @@
+use std::f32::consts::PI;
+
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy_rts_camera::RtsCamera;
+use bevy_rts_camera::RtsCameraControls;
+use itertools::Itertools;
+
+use crate::hotseat::HotSeat;
+use crate::play::TableGame;
+use crate::Eye;
+use crate::Player;
+use crate::Seat;
+use crate::SeatKind;
+use crate::Session;
+use crate::SessionRef;
+
+/// How long the camera takes to move between views, in seconds.
+const TRANSITION_SECS: f32 = 0.8;
+/// How far below the table centre the seat view looks, so the hand sits low in the view.
+const BASE_PITCH: f32 = -10.0 * PI / 180.0;
+/// How far the seat view can look left or right of the table centre.
+const MAX_YAW: f32 = 70.0 * PI / 180.0;
+/// How far the seat view can look down, to the hand, and up from its base pitch.
+const MIN_PITCH: f32 = -45.0 * PI / 180.0;
+const MAX_PITCH: f32 = 25.0 * PI / 180.0;
+/// Radians turned per pixel of mouse movement.
+const LOOK_SENSITIVITY: f32 = 0.003;
+
+/// A first-person view from a human's seat, as an alternative to the overview.
+///
+/// E switches between the two, and in the seat view holding the right mouse button looks around.
+pub struct SeatCameraPlugin;
+impl Plugin for SeatCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (handle_seat_view_key_press, look_around, move_seat_camera).chain(),
+        );
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum CameraView {
+    /// The RTS camera over the tables.
+    #[default]
+    Overview,
+    /// Sitting at a player's seat.
+    Seat { player_id: Entity },
+}
+
+/// On the camera, moves it to and from a seat view, taking over from the RTS camera meanwhile.
+#[derive(Component, Debug, Clone, Default)]
+pub struct SeatCamera {
+    pub view: CameraView,
+    /// Where the camera was when the view last changed.
+    from: Transform,
+    /// Where the RTS camera was when the seat view took over.
+    overview: Transform,
+    /// 0 at `from`, 1 once the view is reached.
+    progress: f32,
+    /// Mouse look, relative to facing the table centre.
+    yaw: f32,
+    pitch: f32,
+    /// The RTS camera's state, put aside while it isn't in control.
+    rts: Option<(RtsCamera, RtsCameraControls)>,
+}
+
+impl SeatCamera {
+    fn change_view(&mut self, view: CameraView, current: Transform) {
+        self.view = view;
+        self.from = current;
+        self.progress = 0.0;
+        self.yaw = 0.0;
+        self.pitch = 0.0;
+    }
+}
+
+/// The human whose turn it is, or else the first human, going by table then seat.
+fn human_to_sit_with(
+    game_query: &Query<&TableGame>,
+    player_query: &Query<(Entity, &SeatKind, &Seat, &SessionRef), With<Player>>,
+) -> Option<Entity> {
+    let humans = player_query
+        .iter()
+        .filter(|(_, kind, ..)| **kind == SeatKind::Human)
+        .map(|(player_id, _, seat, session_ref)| (**session_ref, seat.0, player_id))
+        .sorted()
+        .collect_vec();
+    let to_act = humans.iter().find(|(session_id, _, player_id)| {
+        game_query
+            .get(*session_id)
+            .is_ok_and(|table_game| table_game.to_act().map(|(_, id)| id) == Some(*player_id))
+    });
+    to_act
+        .or(humans.first())
+        .map(|(_, _, player_id)| *player_id)
+}
+
+fn handle_seat_view_key_press(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut camera_query: Query<(
+        Entity,
+        &mut SeatCamera,
+        &Transform,
+        Option<&RtsCamera>,
+        Option<&RtsCameraControls>,
+    )>,
+    game_query: Query<&TableGame>,
+    player_query: Query<(Entity, &SeatKind, &Seat, &SessionRef), With<Player>>,
+) {
+    if !input.just_pressed(KeyCode::KeyE) {
+        return;
+    }
+    for (camera_id, mut seat_camera, transform, rts_camera, rts_controls) in camera_query.iter_mut()
+    {
+        match seat_camera.view {
+            CameraView::Overview => {
+                let Some(player_id) = human_to_sit_with(&game_query, &player_query) else {
+                    info!("No human seat to sit at");
+                    continue;
+                };
+                if let (Some(rts_camera), Some(rts_controls)) = (rts_camera, rts_controls) {
+                    seat_camera.rts = Some((*rts_camera, rts_controls.clone()));
+                    seat_camera.overview = *transform;
+                    commands
+                        .entity(camera_id)
+                        .remove::<(RtsCamera, RtsCameraControls)>();
+                }
+                seat_camera.change_view(CameraView::Seat { player_id }, *transform);
+                info!("Sitting at {player_id:?}");
+            }
+            CameraView::Seat { .. } => {
+                seat_camera.change_view(CameraView::Overview, *transform);
+                info!("Back to the overview");
+            }
+        }
+    }
+}
+
+/// Turn the seat view with the right mouse button, within limits.
+fn look_around(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    mut camera_query: Query<&mut SeatCamera>,
+) {
+    let delta: Vec2 = mouse_motion_events.read().map(|event| event.delta).sum();
+    if !buttons.pressed(MouseButton::Right) || delta == Vec2::ZERO {
+        return;
+    }
+    for mut seat_camera in camera_query.iter_mut() {
+        if seat_camera.view == CameraView::Overview {
+            continue;
+        }
+        seat_camera.yaw = (seat_camera.yaw - delta.x * LOOK_SENSITIVITY).clamp(-MAX_YAW, MAX_YAW);
+        seat_camera.pitch =
+            (seat_camera.pitch - delta.y * LOOK_SENSITIVITY).clamp(MIN_PITCH, MAX_PITCH);
+    }
+}
+
+/// Ease the camera towards its view, handing back to the RTS camera once it's back overhead.
+fn move_seat_camera(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut camera_query: Query<(Entity, &mut SeatCamera, &mut Transform)>,
+    player_query: Query<(&GlobalTransform, &SessionRef, &Seat, &Children), With<Player>>,
+    eye_query: Query<&Eye>,
+    session_query: Query<(&Session, &TableGame, Option<&HotSeat>)>,
+    table_query: Query<&GlobalTransform>,
+) {
+    for (camera_id, mut seat_camera, mut transform) in camera_query.iter_mut() {
+        let target = match seat_camera.view {
+            CameraView::Overview => {
+                if seat_camera.rts.is_none() {
+                    continue;
+                }
+                seat_camera.overview
+            }
+            CameraView::Seat { player_id } => {
+                let Ok((player, session_ref, seat, children)) = player_query.get(player_id) else {
+                    // The table went away
+                    let current = *transform;
+                    seat_camera.change_view(CameraView::Overview, current);
+                    continue;
+                };
+                let Ok((session, table_game, hot_seat)) = session_query.get(**session_ref) else {
+                    continue;
+                };
+                // At a hot-seat table, sit with whoever is at the screen
+                if let Some(showing) = hot_seat.and_then(|hot_seat| hot_seat.showing)
+                    && showing != seat.0
+                {
+                    let player_id = table_game.seats[showing];
+                    let current = *transform;
+                    seat_camera.change_view(CameraView::Seat { player_id }, current);
+                    continue;
+                }
+                let Ok(table) = table_query.get(session.table_id) else {
+                    continue;
+                };
+
+                let rests = eye_query
+                    .iter_many(children)
+                    .map(|eye| eye.rest)
+                    .collect_vec();
+                let between_eyes = rests.iter().sum::<Vec3>() / rests.len().max(1) as f32;
+                let eyes = player.transform_point(between_eyes);
+                // Level with the eyes, so mouse look pitches from the horizon
+                let centre = table.translation() * Vec3::new(1.0, 0.0, 1.0) + Vec3::Y * eyes.y;
+                let facing = Transform::from_translation(eyes).looking_at(centre, Vec3::Y);
+                let look = Quat::from_rotation_y(seat_camera.yaw)
+                    * Quat::from_rotation_x(BASE_PITCH + seat_camera.pitch);
+                // A little in front of the eyes, clear of the body
+                let position = eyes + facing.forward() * 0.1;
+                Transform::from_translation(position).with_rotation(facing.rotation * look)
+            }
+        };
+
+        seat_camera.progress =
+            (seat_camera.progress + time.delta_seconds() / TRANSITION_SECS).min(1.0);
+        let t = seat_camera.progress;
+        let eased = t * t * (3.0 - 2.0 * t);
+        transform.translation = seat_camera.from.translation.lerp(target.translation, eased);
+        transform.rotation = seat_camera.from.rotation.slerp(target.rotation, eased);
+
+        if seat_camera.view == CameraView::Overview
+            && seat_camera.progress >= 1.0
+            && let Some((rts_camera, rts_controls)) = seat_camera.rts.take()
+        {
+            commands
+                .entity(camera_id)
+                .insert((rts_camera, rts_controls));
+        }
+    }
+}
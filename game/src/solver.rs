@@ -0,0 +1,764 @@
+use bevy::utils::HashMap;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::cli::LaunchConfig;
+use crate::game::Round;
+use crate::game::Trick;
+use crate::Card;
+use crate::Suit;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AnalysisConfig {
+    /// Every hand clockwise from seat 0, a random deal when not given.
+    pub hands: Option<Vec<Vec<Card>>>,
+    pub trump: Option<Suit>,
+    pub leader: usize,
+    /// Hand size of the random deal.
+    pub cards: usize,
+}
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            hands: None,
+            trump: None,
+            leader: 0,
+            cards: 7,
+        }
+    }
+}
+
+/// A deal with every hand face up, possibly part way through a trick.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Position {
+    pub hands: Vec<Vec<Card>>,
+    pub trump: Option<Suit>,
+    pub trick: Trick,
+}
+impl Position {
+    pub fn new(hands: Vec<Vec<Card>>, trump: Option<Suit>, leader: usize) -> Self {
+        Self {
+            hands,
+            trump,
+            trick: Trick {
+                leader,
+                cards: Vec::new(),
+            },
+        }
+    }
+
+    /// The rest of a round, as if everyone put their cards on the table.
+    pub fn from_round(round: &Round) -> Self {
+        Self {
+            hands: round.hands.clone(),
+            trump: round.trump(),
+            trick: round.trick.clone(),
+        }
+    }
+
+    /// The seat to play next.
+    pub fn to_act(&self) -> usize {
+        (self.trick.leader + self.trick.cards.len()) % self.hands.len()
+    }
+
+    /// The cards the seat to act may play.
+    pub fn legal_plays(&self) -> Vec<Card> {
+        let hand = &self.hands[self.to_act()];
+        let following = hand
+            .iter()
+            .copied()
+            .filter(|card| Some(card.suit) == self.trick.led_suit())
+            .collect_vec();
+        match following.is_empty() {
+            true => hand.clone(),
+            false => following,
+        }
+    }
+}
+
+/// The most tricks `seat` can be sure of taking from here, if everyone else plays to stop them.
+///
+/// With every hand known this is exact. A trick in progress counts if `seat` can still take it.
+pub fn max_tricks(position: &Position, seat: usize) -> usize {
+    Search::new(position, seat).max_tricks(position.trick.leader)
+}
+
+/// [`max_tricks`] for every seat.
+pub fn solve(position: &Position) -> Vec<usize> {
+    (0..position.hands.len())
+        .map(|seat| max_tricks(position, seat))
+        .collect()
+}
+
+/// For each legal play of the seat to act, the most tricks it can be sure of after playing it.
+pub fn evaluate_plays(position: &Position) -> Vec<(Card, usize)> {
+    let seat = position.to_act();
+    let mut search = Search::new(position, seat);
+    position
+        .legal_plays()
+        .into_iter()
+        .map(|card| {
+            search.play(seat, index(card));
+            let tricks = search.max_tricks(position.trick.leader);
+            search.take_back(seat);
+            (card, tricks)
+        })
+        .collect()
+}
+
+/// The card's bit in a hand, by suit then rank with aces high.
+///
+/// Unlike [`Card::index`], which keeps a new deck's order with aces low, a higher bit in the
+/// same suit always takes the trick, which [`Search::beats`] and [`above`] rely on.
+fn index(card: Card) -> u8 {
+    card.suit as u8 * 13 + card.rank.value() - 2
+}
+
+fn suit_mask(index: u8) -> u64 {
+    0x1fff << (index / 13 * 13)
+}
+
+/// How many cards in `hand` are above every card in `against`.
+fn above(hand: u64, against: u64) -> usize {
+    match against {
+        0 => hand.count_ones() as usize,
+        against => (hand >> (64 - against.leading_zeros())).count_ones() as usize,
+    }
+}
+
+/// How many cards in `hand` can each be met by a higher card from `against`, a card each.
+fn covered(hand: u64, against: u64) -> usize {
+    let mut cards = hand | against;
+    let mut spare = 0;
+    let mut covered = 0;
+    while cards != 0 {
+        let card = 63 - cards.leading_zeros();
+        cards &= !(1 << card);
+        if against & 1 << card != 0 {
+            spare += 1;
+        } else if spare > 0 {
+            spare -= 1;
+            covered += 1;
+        }
+    }
+    covered
+}
+
+/// The highest card in `cards` as a bit, none if there are none.
+fn highest(cards: u64) -> u64 {
+    match cards {
+        0 => 0,
+        cards => 1 << (63 - cards.leading_zeros()),
+    }
+}
+
+/// Legal plays in the order they're searched.
+struct Moves {
+    cards: [u8; 52],
+    len: usize,
+}
+
+impl std::ops::Deref for Moves {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.cards[..self.len]
+    }
+}
+
+/// Bounds on the target's tricks from the start of a trick, wherever the same seats hold the top
+/// cards they were worked out from.
+struct Bound {
+    /// For each suit, how many of its top cards the bounds rest on and who holds them, as in
+    /// [`Search::holders`].
+    tops: [(u32, u64); 4],
+    lower: usize,
+    upper: usize,
+}
+
+/// Alpha-beta over "can the target take at least this many more tricks", for one target seat.
+///
+/// Cards are bits in a `u64`. Each search also returns the cards whose rank decided it: every
+/// card that took a trick, or that a shortcut compared. Below the lowest of those in a suit,
+/// which seat holds which card made no difference, only how many each seat had. Results at the
+/// start of each trick are kept as bounds for every position that agrees on that much, so deals
+/// that differ only in cards already played or in low cards are searched once.
+struct Search {
+    num_players: usize,
+    hands: Vec<u64>,
+    /// Every card still in a hand.
+    remaining: u64,
+    /// Each suit's remaining cards from the top down, as the seat holding it plus one in three bits.
+    holders: [u64; 4],
+    /// How many of each suit every seat holds, four bits a seat.
+    lengths: [u32; 4],
+    /// The cards played so far, the trick in progress last.
+    played: Vec<u8>,
+    trump: Option<u8>,
+    target: usize,
+    /// Bounds on the target's tricks, by leader and how many of each suit every seat holds.
+    bounds: HashMap<(usize, [u32; 4]), Vec<Bound>>,
+}
+
+impl Search {
+    fn new(position: &Position, target: usize) -> Self {
+        let hands: Vec<u64> = position
+            .hands
+            .iter()
+            .map(|hand| hand.iter().fold(0, |mask, card| mask | 1 << index(*card)))
+            .collect();
+        let remaining = hands.iter().fold(0, |mask, hand| mask | hand);
+        let mut holders = [0; 4];
+        let mut lengths = [0; 4];
+        for (seat, hand) in hands.iter().enumerate() {
+            for (suit, lengths) in lengths.iter_mut().enumerate() {
+                *lengths += (hand & suit_mask(suit as u8 * 13)).count_ones() << (4 * seat);
+            }
+        }
+        for (suit, holders) in holders.iter_mut().enumerate() {
+            let mut cards = remaining & suit_mask(suit as u8 * 13);
+            while cards != 0 {
+                let card = 63 - cards.leading_zeros();
+                cards &= !(1 << card);
+                let seat = hands.iter().position(|hand| hand & 1 << card != 0);
+                *holders = *holders << 3 | (seat.expect("a remaining card") as u64 + 1);
+            }
+        }
+        Self {
+            num_players: position.hands.len(),
+            hands,
+            remaining,
+            holders,
+            lengths,
+            played: position
+                .trick
+                .cards
+                .iter()
+                .map(|(_, card)| index(*card))
+                .collect(),
+            trump: position.trump.map(|suit| suit as u8),
+            target,
+            bounds: HashMap::default(),
+        }
+    }
+
+    /// The most tricks the target can be sure of from the trick `leader` led, the cards played so
+    /// far.
+    fn max_tricks(&mut self, leader: usize) -> usize {
+        let mut tricks = 0;
+        while self.reaches(leader, 0, tricks + 1).0 {
+            tricks += 1;
+        }
+        tricks
+    }
+
+    fn beats(&self, card: u8, best: u8) -> bool {
+        match card / 13 == best / 13 {
+            true => card > best,
+            false => Some(card / 13) == self.trump,
+        }
+    }
+
+    /// The position in the trick of the card taking it so far.
+    fn best(&self, trick: &[u8]) -> usize {
+        (1..trick.len()).fold(0, |best, i| match self.beats(trick[i], trick[best]) {
+            true => i,
+            false => best,
+        })
+    }
+
+    /// Tricks the target is sure to take and the most it could take, without searching, and the
+    /// cards whose rank that rests on.
+    ///
+    /// A trump only loses to a higher trump in the same trick, so a seat takes a trick with
+    /// every trump it holds that the trumps against it can't each be set against. A seat on lead
+    /// can first cash the cards that top their suit, as long as the side against it has to
+    /// follow suit or has no trumps to take them with.
+    fn quick_bounds(&self, leader: usize, tricks_left: usize) -> (usize, usize, u64) {
+        let trumps = self.trump.map_or(0, |trump| suit_mask(trump * 13));
+        let target = self.hands[self.target];
+        let opponents = (0..self.num_players)
+            .filter(|seat| *seat != self.target)
+            .map(|seat| self.hands[seat])
+            .collect_vec();
+        let all_opponents = opponents.iter().fold(0, |mask, hand| mask | hand);
+        // Matching trumps against each other compares every one of them
+        let live_trumps = self.remaining & trumps;
+        let trump_tricks = |hand: u64, against: u64| {
+            let held = hand & trumps;
+            let tricks = held.count_ones() as usize - covered(held, against & trumps);
+            let relevant = match tricks {
+                0 => 0,
+                _ => live_trumps & live_trumps.wrapping_neg(),
+            };
+            (tricks, relevant)
+        };
+        // Side suit cards that take their tricks when led, while everyone in `against` follows
+        let cashable = |hand: u64, against: &[u64]| {
+            (0..4)
+                .map(|suit| suit_mask(suit * 13))
+                .filter(|suit| suit & trumps == 0)
+                .fold((0, 0), |(tricks, relevant), suit| {
+                    let rest = against.iter().fold(0, |mask, hand| mask | hand) & suit;
+                    let followers = against
+                        .iter()
+                        .filter(|hand| *hand & trumps != 0)
+                        .map(|hand| (hand & suit).count_ones() as usize)
+                        .min()
+                        .unwrap_or(usize::MAX);
+                    match above(hand & suit, rest).min(followers) {
+                        0 => (tricks, relevant),
+                        cashed => (tricks + cashed, relevant | highest(rest)),
+                    }
+                })
+        };
+
+        let (mut sure, mut relevant) = trump_tricks(target, all_opponents);
+        let mut lost = 0;
+        for hand in &opponents {
+            let (tricks, compared) = trump_tricks(*hand, target);
+            lost = lost.max(tricks);
+            relevant |= compared;
+        }
+        match leader == self.target {
+            true => {
+                let (tricks, compared) = cashable(target, &opponents);
+                sure += tricks;
+                relevant |= compared;
+            }
+            false => {
+                let others = (0..self.num_players)
+                    .filter(|seat| *seat != leader)
+                    .map(|seat| self.hands[seat])
+                    .collect_vec();
+                let hand = self.hands[leader];
+                let (trumped, compared_trumps) = trump_tricks(hand, target);
+                let (cashed, compared) = cashable(hand, &others);
+                lost = lost.max(trumped + cashed);
+                relevant |= compared_trumps | compared;
+
+                // Whatever's led, the target tops it and nobody can trump it instead
+                let led_suits = (0..4)
+                    .map(|suit| suit_mask(suit * 13))
+                    .filter(|suit| hand & suit != 0)
+                    .collect_vec();
+                let topped = led_suits.iter().all(|suit| {
+                    above(target & suit, all_opponents & suit) > 0
+                        && (suit & trumps != 0
+                            || opponents
+                                .iter()
+                                .all(|hand| hand & trumps == 0 || hand & suit != 0))
+                });
+                if topped {
+                    for suit in led_suits {
+                        relevant |= highest(all_opponents & suit);
+                    }
+                    sure = match hand & trumps {
+                        0 => sure + 1,
+                        _ => sure.max(1),
+                    };
+                }
+            }
+        }
+        (
+            sure.min(tricks_left),
+            tricks_left.saturating_sub(lost),
+            relevant,
+        )
+    }
+
+    /// Whether the target takes the trick in progress, once nobody left to play can change that.
+    fn settled(&self, leader: usize, trick: &[u8]) -> Option<bool> {
+        let best = self.best(trick);
+        let mut to_play = (trick.len()..self.num_players).map(|i| (leader + i) % self.num_players);
+        if (leader + best) % self.num_players != self.target {
+            return match to_play.any(|seat| seat == self.target) {
+                true => None,
+                false => Some(false),
+            };
+        }
+
+        let best = trick[best];
+        let trumps = self.trump.map_or(0, |trump| suit_mask(trump * 13));
+        let beaters = match trumps & 1 << best {
+            0 => suit_mask(best) & !((2 << best) - 1) | trumps,
+            _ => trumps & !((2 << best) - 1),
+        };
+        let beaten = to_play.any(|seat| {
+            let hand = self.hands[seat];
+            let playable = match hand & suit_mask(trick[0]) {
+                0 => hand,
+                following => following,
+            };
+            playable & beaters != 0
+        });
+        (!beaten).then_some(true)
+    }
+
+    /// Play `card` from `seat`'s hand to the trick.
+    fn play(&mut self, seat: usize, card: u8) {
+        let below = self.remaining & suit_mask(card) & ((1 << card) - 1);
+        let shift = below.count_ones() * 3;
+        let holders = &mut self.holders[card as usize / 13];
+        *holders = *holders >> (shift + 3) << shift | *holders & ((1 << shift) - 1);
+        self.lengths[card as usize / 13] -= 1 << (4 * seat);
+        self.remaining &= !(1 << card);
+        self.hands[seat] &= !(1 << card);
+        self.played.push(card);
+    }
+
+    /// Put the last card played back in `seat`'s hand.
+    fn take_back(&mut self, seat: usize) {
+        let card = self.played.pop().expect("a card was played");
+        let below = self.remaining & suit_mask(card) & ((1 << card) - 1);
+        let shift = below.count_ones() * 3;
+        let holders = &mut self.holders[card as usize / 13];
+        *holders =
+            (*holders >> shift << 3 | (seat as u64 + 1)) << shift | *holders & ((1 << shift) - 1);
+        self.lengths[card as usize / 13] += 1 << (4 * seat);
+        self.remaining |= 1 << card;
+        self.hands[seat] |= 1 << card;
+    }
+
+    /// Legal plays, the likeliest to cut the search short first.
+    ///
+    /// Cards equivalent to a higher card in the same hand are dropped. Cards that can't take the
+    /// trick are compared without the cards already in it, which are gone once it's over.
+    fn moves(&self, seat: usize, leader: usize, trick: &[u8]) -> Moves {
+        let hand = self.hands[seat];
+        let options = match trick.first() {
+            Some(led) if hand & suit_mask(*led) != 0 => hand & suit_mask(*led),
+            _ => hand,
+        };
+        let held = self.remaining;
+        let in_trick = trick.iter().fold(0, |mask, card| mask | 1 << card);
+        let best = (!trick.is_empty()).then(|| trick[self.best(trick)]);
+        let loses = |card: u8| best.is_some_and(|best| !self.beats(card, best));
+        let last = trick.len() + 1 == self.num_players;
+        let mut moves = Moves {
+            cards: [0; 52],
+            len: 0,
+        };
+        for card in (0..52u8).rev().filter(|card| options & 1 << card != 0) {
+            // The next card up that's still around, if the same player holds it they're interchangeable
+            let live = match loses(card) || last {
+                true => held,
+                false => held | in_trick,
+            };
+            let above = live & suit_mask(card) & !((2u64 << card) - 1);
+            let next = above.trailing_zeros() as u8;
+            if above == 0 || options & 1 << next == 0 || loses(card) != loses(next) {
+                moves.cards[moves.len] = card;
+                moves.len += 1;
+            }
+        }
+        let Some(best) = best else {
+            return moves;
+        };
+
+        // Cheapest card that takes the trick first when it's worth taking, otherwise the lowest
+        let target_winning = (leader + self.best(trick)) % self.num_players == self.target;
+        let wants_trick = (seat == self.target) != target_winning;
+        let strength = |card: &u8| (Some(card / 13) == self.trump, card % 13);
+        moves.cards[..moves.len].sort_by_key(|card| {
+            let takes = wants_trick && self.beats(*card, best);
+            (!takes, strength(card))
+        });
+        moves
+    }
+
+    /// How many of each suit's top cards reach down to the lowest of `relevant` in it, and who
+    /// holds them.
+    fn tops(&self, relevant: u64) -> [(u32, u64); 4] {
+        std::array::from_fn(|suit| {
+            let cards = self.remaining & suit_mask(suit as u8 * 13);
+            let depth = match relevant & cards {
+                0 => 0,
+                relevant => (cards >> relevant.trailing_zeros()).count_ones(),
+            };
+            let below = cards.count_ones() - depth;
+            (depth, self.holders[suit] >> (3 * below))
+        })
+    }
+
+    /// Whether a bound kept for the trick `leader` is about to lead settles `need`, and the cards
+    /// it rests on.
+    fn recall(&self, leader: usize, need: usize) -> Option<(bool, u64)> {
+        let bounds = self.bounds.get(&(leader, self.lengths))?;
+        let bound = bounds.iter().find(|bound| {
+            (need <= bound.lower || need > bound.upper)
+                && (0..4).all(|suit| {
+                    let (depth, holders) = bound.tops[suit];
+                    let cards = self.remaining & suit_mask(suit as u8 * 13);
+                    self.holders[suit] >> (3 * (cards.count_ones() - depth)) == holders
+                })
+        })?;
+        let relevant = (0..4)
+            .filter(|suit| bound.tops[*suit].0 > 0)
+            .fold(0, |relevant, suit| {
+                let mut cards = self.remaining & suit_mask(suit as u8 * 13);
+                for _ in 1..bound.tops[suit].0 {
+                    cards &= !highest(cards);
+                }
+                relevant | highest(cards)
+            });
+        Some((need <= bound.lower, relevant))
+    }
+
+    /// Keep bounds for the trick `leader` is about to lead, for wherever the same seats hold the
+    /// cards in `relevant` and those above them.
+    fn remember(&mut self, leader: usize, relevant: u64, lower: usize, upper: usize) {
+        let tops = self.tops(relevant);
+        let bounds = self.bounds.entry((leader, self.lengths)).or_default();
+        match bounds.iter_mut().find(|bound| bound.tops == tops) {
+            Some(bound) => {
+                bound.lower = bound.lower.max(lower);
+                bound.upper = bound.upper.min(upper);
+            }
+            None => bounds.push(Bound { tops, lower, upper }),
+        }
+    }
+
+    /// Whether the target can be sure of `need` more tricks, counting the trick in progress: the
+    /// cards played from `start` on, led by `leader`. Also the cards whose rank that rests on.
+    fn reaches(&mut self, leader: usize, start: usize, need: usize) -> (bool, u64) {
+        let trick_len = self.played.len() - start;
+        if trick_len == self.num_players {
+            let best = start + self.best(&self.played[start..]);
+            let winner = (leader + best - start) % self.num_players;
+            let won = (winner == self.target) as usize;
+            let (reached, relevant) =
+                self.reaches(winner, self.played.len(), need.saturating_sub(won));
+            // Whatever else was played, the card taking the trick has to stay above it
+            return (reached, relevant | 1 << self.played[best]);
+        }
+        if need == 0 {
+            return (true, 0);
+        }
+        let seat = (leader + trick_len) % self.num_players;
+        let tricks_left = self.hands[seat].count_ones() as usize;
+        if need > tricks_left {
+            return (false, 0);
+        }
+        if trick_len == 0 {
+            if let Some(recalled) = self.recall(leader, need) {
+                return recalled;
+            }
+            let (lower, upper, relevant) = self.quick_bounds(leader, tricks_left);
+            if need <= lower || need > upper {
+                self.remember(leader, relevant, lower, upper);
+                return (need <= lower, relevant);
+            }
+        } else {
+            let trick = &self.played[start..];
+            let taking = 1 << trick[self.best(trick)];
+            match self.settled(leader, trick) {
+                Some(true) if need == 1 => return (true, taking),
+                Some(false) if need == tricks_left => return (false, taking),
+                _ => {}
+            }
+        }
+
+        let maximising = seat == self.target;
+        let mut result = !maximising;
+        let mut relevant = 0;
+        let moves = self.moves(seat, leader, &self.played[start..]);
+        for card in moves.iter() {
+            self.play(seat, *card);
+            let (reached, compared) = self.reaches(leader, start, need);
+            self.take_back(seat);
+            if reached == maximising {
+                result = reached;
+                relevant = compared;
+                break;
+            }
+            relevant |= compared;
+        }
+        if result != maximising {
+            relevant =
+                self.with_dropped_moves(seat, self.played.get(start).copied(), &moves, relevant);
+        }
+
+        if trick_len == 0 {
+            match result {
+                true => self.remember(leader, relevant, need, tricks_left),
+                false => self.remember(leader, relevant, 0, need - 1),
+            }
+        }
+        (result, relevant)
+    }
+
+    /// `relevant` once every card `seat` could play has been tried, with the cards left out of
+    /// `moves` as equivalent to the next one up. Where that one's rank counted, theirs does too,
+    /// or elsewhere they might not be equivalent.
+    fn with_dropped_moves(
+        &self,
+        seat: usize,
+        led: Option<u8>,
+        moves: &[u8],
+        mut relevant: u64,
+    ) -> u64 {
+        let tried = moves.iter().fold(0u64, |mask, card| mask | 1 << card);
+        let hand = self.hands[seat];
+        let options = match led {
+            Some(led) if hand & suit_mask(led) != 0 => hand & suit_mask(led),
+            _ => hand,
+        };
+        let mut next_up = None;
+        for card in (0..52u8).rev().filter(|card| options & 1 << card != 0) {
+            let counted = relevant & suit_mask(card);
+            if tried & 1 << card == 0
+                && let Some(next_up) = next_up
+                && next_up / 13 == card / 13
+                && counted != 0
+                && next_up >= counted.trailing_zeros() as u8
+            {
+                relevant |= 1 << card;
+            }
+            next_up = Some(card);
+        }
+        relevant
+    }
+}
+
+/// Print the analysis of a deal for `poche analyse`.
+pub fn run(launch_config: &LaunchConfig) {
+    let config = &launch_config.analysis;
+    let position = match &config.hands {
+        Some(hands) => Position::new(hands.clone(), config.trump, config.leader),
+        None => {
+            let num_players = launch_config.seats.len();
+            let seed = launch_config.seed.unwrap_or_else(rand::random);
+            let mut deck = Card::get_new_deck();
+            deck.shuffle(&mut StdRng::seed_from_u64(seed));
+            let dealer = (config.leader + num_players - 1) % num_players;
+            println!("Random deal from seed {seed}\n");
+            Position::from_round(&Round::deal(&mut deck, num_players, config.cards, dealer))
+        }
+    };
+    print!("{}", report(&position));
+}
+
+fn report(position: &Position) -> String {
+    let mut report = match position.trump {
+        Some(trump) => format!("Trump: {trump:?}\n"),
+        None => "No trump\n".to_owned(),
+    };
+    for (seat, hand) in position.hands.iter().enumerate() {
+        let leads = match seat == position.trick.leader {
+            true => "  (leads)",
+            false => "",
+        };
+        report += &format!("Seat {}: {}{leads}\n", seat + 1, hand.iter().join(" "));
+    }
+
+    report += "\nMost tricks each seat can be sure of, with everyone else against them:\n";
+    for (seat, tricks) in solve(position).into_iter().enumerate() {
+        report += &format!("  Seat {}: {tricks}\n", seat + 1);
+    }
+
+    report += &format!("\nSeat {} to play:\n", position.to_act() + 1);
+    for (card, tricks) in evaluate_plays(position)
+        .into_iter()
+        .sorted_by_key(|(_, tricks)| std::cmp::Reverse(*tricks))
+    {
+        report += &format!("  {card}: {tricks}\n");
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// A few times the slowest of the deals below takes unoptimized.
+    const MAX_SOLVE_TIME: Duration = Duration::from_secs(10);
+
+    /// Deals small enough to search every line of play, with and without trumps.
+    fn small_deals() -> impl Strategy<Value = Position> {
+        (2usize..=4, 1usize..=3, any::<u64>(), any::<bool>()).prop_map(
+            |(num_players, hand_size, seed, no_trump)| {
+                let mut deck = Card::get_new_deck();
+                deck.shuffle(&mut StdRng::seed_from_u64(seed));
+                let dealer = seed as usize % num_players;
+                let mut position =
+                    Position::from_round(&Round::deal(&mut deck, num_players, hand_size, dealer));
+                if no_trump {
+                    position.trump = None;
+                }
+                position
+            },
+        )
+    }
+
+    /// Tricks `seat` takes from here against everyone else, trying every legal card at every turn.
+    fn minimax(position: &Position, seat: usize) -> usize {
+        let to_act = position.to_act();
+        if position.hands[to_act].is_empty() {
+            return 0;
+        }
+        let outcomes = position.legal_plays().into_iter().map(|card| {
+            let mut next = position.clone();
+            next.hands[to_act].retain(|held| *held != card);
+            next.trick.cards.push((to_act, card));
+            if next.trick.cards.len() < next.hands.len() {
+                return minimax(&next, seat);
+            }
+            let (winner, _) = next.trick.winner(next.trump).expect("a full trick");
+            next.trick = Trick {
+                leader: winner,
+                cards: Vec::new(),
+            };
+            (winner == seat) as usize + minimax(&next, seat)
+        });
+        match to_act == seat {
+            true => outcomes.max(),
+            false => outcomes.min(),
+        }
+        .expect("cards left to play")
+    }
+
+    /// A full deal at the largest table, as the analyse command solves by default.
+    #[test]
+    fn a_full_deal_for_seven_players_is_solved_in_bounded_time() {
+        for seed in 0..8 {
+            let mut deck = Card::get_new_deck();
+            deck.shuffle(&mut StdRng::seed_from_u64(seed));
+            let position = Position::from_round(&Round::deal(&mut deck, 7, 7, 0));
+            let start = Instant::now();
+            let tricks = solve(&position);
+            let elapsed = start.elapsed();
+            assert!(tricks.iter().sum::<usize>() <= 7, "{tricks:?}");
+            assert!(elapsed < MAX_SOLVE_TIME, "seed {seed} took {elapsed:?}");
+        }
+    }
+
+    #[test]
+    fn the_report_numbers_seats_from_one() {
+        let hands = vec![vec!["AS".parse().unwrap()], vec!["KS".parse().unwrap()]];
+        let report = report(&Position::new(hands, None, 1));
+        let lines = report.lines().collect_vec();
+        assert!(lines[2].starts_with("Seat 2: "), "{report}");
+        assert!(lines[2].ends_with("(leads)"), "{report}");
+        assert!(lines.contains(&"  Seat 1: 1"), "{report}");
+        assert!(lines.contains(&"  Seat 2: 0"), "{report}");
+        assert!(lines.contains(&"Seat 2 to play:"), "{report}");
+    }
+
+    proptest! {
+        #[test]
+        fn the_solver_agrees_with_minimax(position in small_deals()) {
+            for seat in 0..position.hands.len() {
+                prop_assert_eq!(max_tricks(&position, seat), minimax(&position, seat), "seat {}", seat);
+            }
+            let best_play = evaluate_plays(&position).into_iter().map(|(_, tricks)| tricks).max();
+            prop_assert_eq!(best_play, Some(minimax(&position, position.to_act())));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use bevy::prelude::*;
+use itertools::Itertools;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cli::LaunchConfig;
+use crate::profiles::ProfileId;
+use crate::profiles::Profiles;
+
+pub struct StatsPlugin;
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GameFinishedEvent>();
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            Update,
+            (
+                record_finished_games,
+                handle_stats_key_press,
+                update_stats_text.run_if(resource_changed::<StatsDb>),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// How one player did in one round.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RoundResult {
+    pub hand_size: usize,
+    pub bid: usize,
+    pub taken: usize,
+    pub points: usize,
+}
+impl RoundResult {
+    pub fn made(&self) -> bool {
+        self.bid == self.taken
+    }
+}
+
+/// How one player did over a whole game.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PlayerGameResult {
+    pub profile_id: ProfileId,
+    pub rounds: Vec<RoundResult>,
+    /// Coins won from the pot minus coins paid in, in cents.
+    pub money_cents: i64,
+}
+impl PlayerGameResult {
+    pub fn points(&self) -> usize {
+        self.rounds.iter().map(|round| round.points).sum()
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct GameFinishedEvent {
+    pub players: Vec<PlayerGameResult>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BidAccuracy {
+    pub made: usize,
+    pub total: usize,
+}
+
+/// Lifetime statistics for one profile.
+#[derive(Debug, Eq, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileStats {
+    pub games_played: usize,
+    pub wins: usize,
+    pub rounds_played: usize,
+    pub poches: usize,
+    pub total_points: usize,
+    pub money_cents: i64,
+    pub bid_accuracy_by_hand_size: BTreeMap<usize, BidAccuracy>,
+    pub current_streak: usize,
+    pub longest_streak: usize,
+}
+impl ProfileStats {
+    pub fn poche_rate(&self) -> f32 {
+        ratio(self.poches, self.rounds_played)
+    }
+    pub fn win_rate(&self) -> f32 {
+        ratio(self.wins, self.games_played)
+    }
+    pub fn average_points(&self) -> f32 {
+        ratio(self.total_points, self.games_played)
+    }
+}
+
+fn ratio(n: usize, d: usize) -> f32 {
+    match d {
+        0 => 0.0,
+        d => n as f32 / d as f32,
+    }
+}
+
+/// Stats for every profile, saved to a local file after each game.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsDb {
+    pub profiles: BTreeMap<u64, ProfileStats>,
+}
+
+impl StatsDb {
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Failed to parse stats file {path:?}, starting fresh: {e}");
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                warn!("Failed to read stats file {path:?}, starting fresh: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+
+    pub fn record_game(&mut self, players: &[PlayerGameResult]) {
+        let best = players.iter().map(|player| player.points()).max();
+        for player in players {
+            let stats = self.profiles.entry(player.profile_id.0).or_default();
+            stats.games_played += 1;
+            if Some(player.points()) == best {
+                stats.wins += 1;
+            }
+            stats.total_points += player.points();
+            stats.money_cents += player.money_cents;
+            for round in player.rounds.iter() {
+                stats.rounds_played += 1;
+                let accuracy = stats
+                    .bid_accuracy_by_hand_size
+                    .entry(round.hand_size)
+                    .or_default();
+                accuracy.total += 1;
+                if round.made() {
+                    accuracy.made += 1;
+                    stats.current_streak += 1;
+                    stats.longest_streak = stats.longest_streak.max(stats.current_streak);
+                } else {
+                    stats.poches += 1;
+                    stats.current_streak = 0;
+                }
+            }
+        }
+    }
+
+    /// Profiles ordered best first, by wins then average points.
+    pub fn leaderboard(&self) -> Vec<(ProfileId, &ProfileStats)> {
+        self.profiles
+            .iter()
+            .map(|(id, stats)| (ProfileId(*id), stats))
+            .sorted_by(|(_, a), (_, b)| {
+                b.wins
+                    .cmp(&a.wins)
+                    .then(b.average_points().total_cmp(&a.average_points()))
+            })
+            .collect()
+    }
+
+    /// A plain text leaderboard table.
+    pub fn leaderboard_table(&self, profiles: &Profiles) -> String {
+        let mut table = format!(
+            "{:<12} {:>5} {:>5} {:>6} {:>7} {:>8} {:>8} {:>6}\n",
+            "Player", "Games", "Wins", "Win%", "Poche%", "Avg pts", "Money", "Streak"
+        );
+        for (id, stats) in self.leaderboard() {
+            let name = profiles.name(id);
+            table += &format!(
+                "{:<12} {:>5} {:>5} {:>5.0}% {:>6.0}% {:>8.1} {:>8} {:>6}\n",
+                name,
+                stats.games_played,
+                stats.wins,
+                stats.win_rate() * 100.0,
+                stats.poche_rate() * 100.0,
+                stats.average_points(),
+                format!("${:.2}", stats.money_cents as f32 / 100.0),
+                stats.longest_streak,
+            );
+        }
+        table
+    }
+
+    /// The leaderboard followed by each player's bid accuracy.
+    pub fn report(&self, profiles: &Profiles) -> String {
+        let accuracy = self
+            .leaderboard()
+            .into_iter()
+            .map(|(id, stats)| {
+                let name = profiles.name(id);
+                format!("{name}\n{}", Self::accuracy_table(stats))
+            })
+            .join("\n\n");
+        format!(
+            "LEADERBOARD\n\n{}\nBID ACCURACY BY HAND SIZE\n\n{accuracy}",
+            self.leaderboard_table(profiles)
+        )
+    }
+
+    fn accuracy_table(stats: &ProfileStats) -> String {
+        stats
+            .bid_accuracy_by_hand_size
+            .iter()
+            .map(|(hand_size, accuracy)| {
+                format!(
+                    "  {hand_size} cards: {}/{} made ({:.0}%)",
+                    accuracy.made,
+                    accuracy.total,
+                    ratio(accuracy.made, accuracy.total) * 100.0
+                )
+            })
+            .join("\n")
+    }
+}
+
+#[derive(Component)]
+struct StatsText;
+
+fn setup(mut commands: Commands, launch_config: Res<LaunchConfig>) {
+    commands.insert_resource(StatsDb::load(&launch_config.stats));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(5.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+        StatsText,
+        Name::new("Stats Screen"),
+    ));
+}
+
+fn record_finished_games(
+    mut events: EventReader<GameFinishedEvent>,
+    mut stats: ResMut<StatsDb>,
+    launch_config: Res<LaunchConfig>,
+) {
+    for event in events.read() {
+        stats.record_game(&event.players);
+        match stats.save(&launch_config.stats) {
+            Ok(()) => info!("Recorded stats for {} players", event.players.len()),
+            Err(e) => warn!("Failed to save stats to {:?}: {e}", launch_config.stats),
+        }
+    }
+}
+
+fn handle_stats_key_press(
+    input: Res<ButtonInput<KeyCode>>,
+    mut stats_text_query: Query<&mut Visibility, With<StatsText>>,
+) {
+    if input.just_pressed(KeyCode::Tab) {
+        for mut visibility in stats_text_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+fn update_stats_text(
+    stats: Res<StatsDb>,
+    profiles: Option<Res<Profiles>>,
+    mut stats_text_query: Query<&mut Text, With<StatsText>>,
+) {
+    let Some(profiles) = profiles else {
+        return;
+    };
+    for mut text in stats_text_query.iter_mut() {
+        text.sections[0].value = stats.report(&profiles);
+    }
+}
@@ -0,0 +1,476 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Serialize;
+
+use crate::bots::strategy_by_name;
+use crate::bots::Strategy;
+use crate::cli::LaunchConfig;
+use crate::game::choose_dealer;
+use crate::game_log::GameLog;
+use crate::handle_spawn_session_events;
+use crate::model::Model;
+use crate::model::ModelBot;
+use crate::play::start_games;
+use crate::play::IllegalMoveEvent;
+use crate::play::PlayPlugin;
+use crate::play::SeatStrategy;
+use crate::play::TableGame;
+use crate::play::BOT_DELAY;
+use crate::profiles::Profiles;
+use crate::protocol::ProcessBot;
+use crate::rules::RuleSet;
+use crate::BotStyle;
+use crate::Dealer;
+use crate::HeadlessSessionPlugin;
+use crate::KillSessionEvent;
+use crate::Seat;
+use crate::SeatKind;
+use crate::Session;
+use crate::SpawnSessionEvent;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TournamentConfig {
+    pub strategies: Vec<String>,
+    pub games: usize,
+    /// How many tables play at once.
+    pub tables: usize,
+    pub out: Option<PathBuf>,
+    pub format: OutputFormat,
+}
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            strategies: vec!["heuristic".into(), "cautious".into(), "random".into()],
+            games: 1000,
+            tables: 64,
+            out: None,
+            format: OutputFormat::Csv,
+        }
+    }
+}
+
+/// Run a headless tournament to completion.
+pub fn run(launch_config: LaunchConfig) {
+    let mut app = tournament_app(launch_config);
+    // Every move is logged, far too many to follow across thousands of games
+    app.add_plugins(LogPlugin {
+        filter: "warn,poche::tournament=info".into(),
+        ..default()
+    });
+    app.run();
+}
+
+/// The tables are sessions like any other, played by the [`PlayPlugin`] with the entries
+/// sitting in as [`SeatStrategy`]s, only with nothing rendered and the clock going a bot's delay
+/// each frame so every bot acts straight away. A table is dealt the next game once its game is
+/// over.
+fn tournament_app(launch_config: LaunchConfig) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(BOT_DELAY));
+    app.insert_resource(Profiles::starter());
+    app.init_resource::<ButtonInput<KeyCode>>();
+    app.insert_resource(launch_config);
+    app.add_plugins(HeadlessSessionPlugin);
+    app.add_plugins(PlayPlugin);
+    app.add_systems(Startup, setup);
+    app.add_systems(
+        Update,
+        seat_new_tables
+            .after(handle_spawn_session_events)
+            .before(start_games),
+    );
+    app.add_systems(
+        PostUpdate,
+        (count_illegal_moves, collect_tournament_results).chain(),
+    );
+    app
+}
+
+#[derive(Resource)]
+struct Tournament {
+    config: TournamentConfig,
+    rules: RuleSet,
+    seed: u64,
+    /// How long external bots have to answer.
+    bot_timeout: Duration,
+    next_game: usize,
+    finished_games: usize,
+    tallies: Vec<Tally>,
+}
+impl Tournament {
+    /// Seat the strategies at a table for the next game, which [`start_games`] then deals.
+    ///
+    /// `players` are the table's, by seat.
+    fn seat_next_game(
+        &mut self,
+        commands: &mut Commands,
+        session: &mut Session,
+        table: &mut TournamentTable,
+        players: &[Entity],
+    ) {
+        let game_index = self.next_game;
+        self.next_game += 1;
+        let num_strategies = self.config.strategies.len();
+        let deal_seed = self.seed.wrapping_add((game_index / num_strategies) as u64);
+        let dealer = choose_dealer(num_strategies, &mut StdRng::seed_from_u64(deal_seed));
+        table.seating = seating(num_strategies, deal_seed, game_index % num_strategies);
+        session.seed = deal_seed;
+        for (seat, player_id) in players.iter().enumerate() {
+            let strategy = table.strategies[table.seating[seat]].clone();
+            let mut player = commands.entity(*player_id);
+            player.insert(SeatStrategy(strategy));
+            if seat == dealer {
+                player.insert(Dealer);
+            } else {
+                player.remove::<Dealer>();
+            }
+        }
+    }
+
+    /// A table's own copy of every strategy, external bots need a process per seat.
+    fn strategies(&self) -> Result<Vec<Arc<dyn Strategy>>, String> {
+        self.config
+            .strategies
+            .iter()
+            .map(|name| -> Result<Arc<dyn Strategy>, String> {
+                if let Some(command) = name.strip_prefix("exe:") {
+                    let bot = ProcessBot::spawn(command, self.bot_timeout)
+                        .map_err(|e| format!("failed to start {command:?}: {e}"))?;
+                    return Ok(Arc::new(bot));
+                }
+                if let Some(path) = name.strip_prefix("model:") {
+                    let model = Model::load(Path::new(path))
+                        .map_err(|e| format!("failed to load {path:?}: {e}"))?;
+                    return Ok(Arc::new(ModelBot { model }));
+                }
+                let strategy =
+                    strategy_by_name(name).expect("strategy names are checked by the cli");
+                Ok(Arc::from(strategy))
+            })
+            .collect()
+    }
+}
+
+/// Which strategy sits in each seat for one of the games of a deal.
+///
+/// Every deal is played `num_strategies` times, rotating one seeded shuffle of the strategies
+/// around the table, so each strategy gets each seat, and with it each position relative to
+/// the dealer, exactly once per deal. The shuffle changes from deal to deal so strategies
+/// don't always sit next to the same neighbours. Trying every permutation instead would need
+/// 5040 games per deal with 7 strategies before the rotation evened out.
+fn seating(num_strategies: usize, deal_seed: u64, rotation: usize) -> Vec<usize> {
+    let mut order = (0..num_strategies).collect_vec();
+    order.shuffle(&mut StdRng::seed_from_u64(deal_seed));
+    order.rotate_left(rotation);
+    order
+}
+
+/// A table's part in the tournament, on its session.
+#[derive(Component)]
+struct TournamentTable {
+    /// Strategy index by seat, for the game being played.
+    seating: Vec<usize>,
+    /// By strategy index, kept between games.
+    strategies: Vec<Arc<dyn Strategy>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Tally {
+    scores: Vec<f64>,
+    /// Split between tied winners.
+    wins: Vec<f64>,
+    rounds: usize,
+    poches: usize,
+    /// Moves the rules didn't allow, made legal by the table.
+    illegal_moves: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StrategyReport {
+    strategy: String,
+    games: usize,
+    mean_score: f64,
+    score_ci95: f64,
+    win_rate: f64,
+    win_rate_ci95: f64,
+    poche_rate: f64,
+    poche_rate_ci95: f64,
+    illegal_moves: usize,
+}
+
+impl Tally {
+    fn report(&self, strategy: &str) -> StrategyReport {
+        let (mean_score, score_ci95) = mean_and_ci95(&self.scores);
+        let (win_rate, win_rate_ci95) = mean_and_ci95(&self.wins);
+        let poche_rate = match self.rounds {
+            0 => 0.0,
+            rounds => self.poches as f64 / rounds as f64,
+        };
+        let poche_rate_ci95 = match self.rounds {
+            0 => 0.0,
+            rounds => 1.96 * (poche_rate * (1.0 - poche_rate) / rounds as f64).sqrt(),
+        };
+        StrategyReport {
+            strategy: strategy.to_owned(),
+            games: self.scores.len(),
+            mean_score,
+            score_ci95,
+            win_rate,
+            win_rate_ci95,
+            poche_rate,
+            poche_rate_ci95,
+            illegal_moves: self.illegal_moves,
+        }
+    }
+}
+
+/// The mean and the half width of its 95% confidence interval.
+fn mean_and_ci95(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    if samples.len() < 2 {
+        return (samples.first().copied().unwrap_or_default(), 0.0);
+    }
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, 1.96 * (variance / n).sqrt())
+}
+
+fn setup(
+    mut commands: Commands,
+    launch_config: Res<LaunchConfig>,
+    mut spawn_session_events: EventWriter<SpawnSessionEvent>,
+) {
+    let config = launch_config.tournament.clone();
+    let num_strategies = config.strategies.len();
+    if config.games % num_strategies != 0 {
+        warn!(
+            "{} games isn't a multiple of the {num_strategies} strategies, the last deal won't be \
+             played from every seat",
+            config.games
+        );
+    }
+    let tournament = Tournament {
+        rules: launch_config.rules.clone(),
+        seed: launch_config.seed.unwrap_or_default(),
+        bot_timeout: launch_config.bot_timeout,
+        tallies: vec![Tally::default(); num_strategies],
+        next_game: 0,
+        finished_games: 0,
+        config,
+    };
+
+    // The seed and dealer are set for each game once the table is up, see seat_new_tables
+    let tables = tournament.config.tables.min(tournament.config.games);
+    for _ in 0..tables {
+        spawn_session_events.send(SpawnSessionEvent {
+            seats: vec![SeatKind::Bot(BotStyle::default()); num_strategies],
+            rules: tournament.rules.clone(),
+            seed: Some(tournament.seed),
+            dealer: Some(0),
+            decks: Vec::new(),
+            moves: Vec::new(),
+        });
+    }
+    info!(
+        "Playing {} games of {} across {tables} tables",
+        tournament.config.games,
+        tournament.config.strategies.join(", ")
+    );
+    commands.insert_resource(tournament);
+}
+
+fn seat_new_tables(
+    mut commands: Commands,
+    mut tournament: ResMut<Tournament>,
+    mut session_query: Query<(Entity, &mut Session), Without<TournamentTable>>,
+    seat_query: Query<&Seat>,
+) {
+    for (session_id, mut session) in session_query.iter_mut() {
+        let strategies = match tournament.strategies() {
+            Ok(strategies) => strategies,
+            Err(e) => {
+                error!("Couldn't set up the strategies: {e}");
+                std::process::exit(1);
+            }
+        };
+        let mut table = TournamentTable {
+            seating: Vec::new(),
+            strategies,
+        };
+        let players = session
+            .player_ids
+            .iter()
+            .copied()
+            .sorted_by_key(|player_id| seat_query.get(*player_id).map(|seat| seat.0).ok())
+            .collect_vec();
+        tournament.seat_next_game(&mut commands, &mut session, &mut table, &players);
+        commands.entity(session_id).insert(table);
+    }
+}
+
+fn count_illegal_moves(
+    mut illegal_move_events: EventReader<IllegalMoveEvent>,
+    table_query: Query<&TournamentTable>,
+    mut tournament: ResMut<Tournament>,
+) {
+    for event in illegal_move_events.read() {
+        if let Ok(table) = table_query.get(event.session_id) {
+            tournament.tallies[table.seating[event.seat]].illegal_moves += 1;
+        }
+    }
+}
+
+fn collect_tournament_results(
+    mut commands: Commands,
+    mut table_query: Query<(Entity, &mut Session, &TableGame, &mut TournamentTable)>,
+    mut tournament: ResMut<Tournament>,
+    mut game_log: ResMut<GameLog>,
+    mut kill_session_events: EventWriter<KillSessionEvent>,
+    mut exit: EventWriter<AppExit>,
+) {
+    // Nobody reads it here, and it would keep every move of every game
+    game_log.entries.clear();
+
+    for (session_id, mut session, table_game, mut table) in table_query.iter_mut() {
+        let game = &table_game.game;
+        if !game.is_over() {
+            continue;
+        }
+        let winners = game.winners();
+        for (seat, strategy) in table.seating.iter().enumerate() {
+            let tally = &mut tournament.tallies[*strategy];
+            tally.scores.push(game.points(seat) as f64);
+            tally.wins.push(match winners.contains(&seat) {
+                true => 1.0 / winners.len() as f64,
+                false => 0.0,
+            });
+            tally.rounds += game.results[seat].len();
+            tally.poches += game.results[seat]
+                .iter()
+                .filter(|result| !result.made())
+                .count();
+        }
+        tournament.finished_games += 1;
+
+        commands.entity(session_id).remove::<TableGame>();
+        if tournament.next_game < tournament.config.games {
+            tournament.seat_next_game(&mut commands, &mut session, &mut table, &table_game.seats);
+        } else {
+            kill_session_events.send(KillSessionEvent { session_id });
+        }
+    }
+
+    if tournament.finished_games >= tournament.config.games {
+        let reports = tournament
+            .tallies
+            .iter()
+            .zip(tournament.config.strategies.iter())
+            .map(|(tally, strategy)| tally.report(strategy))
+            .collect_vec();
+        let output = match tournament.config.format {
+            OutputFormat::Csv => to_csv(&reports),
+            OutputFormat::Json => {
+                serde_json::to_string_pretty(&reports).expect("reports are serializable")
+            }
+        };
+        match &tournament.config.out {
+            Some(path) => match std::fs::write(path, output) {
+                Ok(()) => info!("Wrote tournament results to {path:?}"),
+                Err(e) => error!("Failed to write tournament results to {path:?}: {e}"),
+            },
+            None => println!("{output}"),
+        }
+        exit.send(AppExit);
+    }
+}
+
+fn to_csv(reports: &[StrategyReport]) -> String {
+    let header =
+        "strategy,games,mean_score,score_ci95,win_rate,win_rate_ci95,poche_rate,poche_rate_ci95,\
+         illegal_moves";
+    std::iter::once(header.to_owned())
+        .chain(reports.iter().map(|report| {
+            format!(
+                "{},{},{:.3},{:.3},{:.4},{:.4},{:.4},{:.4},{}",
+                report.strategy,
+                report.games,
+                report.mean_score,
+                report.score_ci95,
+                report.win_rate,
+                report.win_rate_ci95,
+                report.poche_rate,
+                report.poche_rate_ci95,
+                report.illegal_moves
+            )
+        }))
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_game_is_played_out_at_the_tables_and_reported() {
+        let out = std::env::temp_dir().join(format!("poche-tournament-{}.csv", std::process::id()));
+        let strategies = ["heuristic", "random", "greedy"];
+        let mut app = tournament_app(LaunchConfig {
+            seed: Some(1),
+            tournament: TournamentConfig {
+                strategies: strategies.iter().map(|name| name.to_string()).collect(),
+                games: 7,
+                tables: 2,
+                out: Some(out.clone()),
+                format: OutputFormat::Csv,
+            },
+            ..default()
+        });
+        while app.world.resource::<Events<AppExit>>().is_empty() {
+            app.update();
+        }
+
+        let csv = std::fs::read_to_string(&out).expect("the results were written");
+        std::fs::remove_file(&out).expect("the results can be removed");
+        let rows = csv.lines().skip(1).collect_vec();
+        assert_eq!(rows.len(), strategies.len(), "{csv}");
+        for (row, strategy) in rows.iter().zip(strategies) {
+            let fields = row.split(',').collect_vec();
+            assert_eq!(fields[..2], [strategy, "7"], "{csv}");
+            assert_eq!(fields.last(), Some(&"0"), "{csv}");
+        }
+    }
+
+    #[test]
+    fn every_strategy_gets_every_seat_once_per_deal() {
+        for num_strategies in 2..=7 {
+            for deal_seed in 0..20 {
+                let seatings = (0..num_strategies)
+                    .map(|rotation| seating(num_strategies, deal_seed, rotation))
+                    .collect_vec();
+                for seat in 0..num_strategies {
+                    let strategies = seatings.iter().map(|seating| seating[seat]).sorted();
+                    assert!(strategies.eq(0..num_strategies), "{seatings:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::cli::LaunchConfig;
+use crate::game::Action;
+use crate::game::Game;
+use crate::game::Phase;
+use crate::hotseat::HotSeat;
+use crate::play::ActionEvent;
+use crate::play::Scripted;
+use crate::play::TableGame;
+use crate::Eye;
+use crate::Handles;
+use crate::Player;
+use crate::SeatKind;
+
+/// How far eyes move off their rest to look at someone.
+const EYE_SHIFT: f32 = 0.03;
+
+/// Shows whose turn it is, and plays for seats that run out of time.
+///
+/// The active seat glows, everyone else looks at it, and with a time limit a ring around it
+/// runs down on the game clock.
+pub struct TurnPlugin;
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                tick_turn_clocks,
+                highlight_active_seats,
+                look_at_active_seats,
+                draw_turn_clocks,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Time left for the seat to act, on the session.
+#[derive(Component, Debug, Clone)]
+pub struct TurnClock {
+    /// The length of the game log when the turn started.
+    actions: usize,
+    pub timer: Timer,
+}
+
+/// The lowest bid, or the lowest card by rank.
+fn timeout_action(game: &Game) -> Option<Action> {
+    match game.phase() {
+        Phase::Bidding { .. } => game
+            .round
+            .legal_bids(&game.rules)
+            .into_iter()
+            .min()
+            .map(Action::Bid),
+        Phase::Playing { seat } => game
+            .round
+            .legal_plays(seat)
+            .into_iter()
+            .min_by_key(|card| (card.rank.value(), card.suit as u8))
+            .map(Action::Play),
+        Phase::RoundOver | Phase::GameOver => None,
+    }
+}
+
+fn tick_turn_clocks(
+    mut commands: Commands,
+    time: Res<Time>,
+    launch_config: Res<LaunchConfig>,
+    mut game_query: Query<
+        (Entity, &TableGame, Option<&mut TurnClock>, Option<&HotSeat>),
+        Without<Scripted>,
+    >,
+    seat_kind_query: Query<&SeatKind>,
+    mut action_events: EventWriter<ActionEvent>,
+) {
+    let Some(limit) = launch_config.turn_time else {
+        return;
+    };
+    for (session_id, table_game, clock, hot_seat) in game_query.iter_mut() {
+        let Some((seat, player_id)) = table_game.to_act() else {
+            continue;
+        };
+        let actions = table_game.game.log.len();
+        let Some(mut clock) = clock else {
+            commands.entity(session_id).insert(TurnClock {
+                actions,
+                timer: Timer::new(limit, TimerMode::Once),
+            });
+            continue;
+        };
+        if clock.actions != actions {
+            clock.actions = actions;
+            clock.timer.reset();
+        }
+        // The clock starts once the next player at a hot-seat table has clicked ready
+        if seat_kind_query.get(player_id) == Ok(&SeatKind::Human)
+            && !HotSeat::lets_act(hot_seat, seat)
+        {
+            continue;
+        }
+        if !clock.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        let Some(action) = timeout_action(&table_game.game) else {
+            continue;
+        };
+        info!("Seat {seat} ran out of time, playing {action:?}");
+        action_events.send(ActionEvent {
+            session_id,
+            seat,
+            action,
+            rationale: None,
+        });
+    }
+}
+
+/// Light up the player whose turn it is.
+fn highlight_active_seats(
+    game_query: Query<&TableGame, Changed<TableGame>>,
+    player_query: Query<&Handle<StandardMaterial>, With<Player>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for table_game in game_query.iter() {
+        let active = table_game.to_act().map(|(_, player_id)| player_id);
+        for player_id in table_game.seats.iter() {
+            let Ok(handle) = player_query.get(*player_id) else {
+                warn!("Player not found to highlight");
+                continue;
+            };
+            let Some(material) = materials.get_mut(handle) else {
+                continue;
+            };
+            material.emissive = match active == Some(*player_id) {
+                true => material.base_color * 0.6,
+                false => Color::BLACK,
+            };
+        }
+    }
+}
+
+/// Everyone but the active player turns their eyes towards them.
+fn look_at_active_seats(
+    time: Res<Time>,
+    game_query: Query<&TableGame>,
+    player_query: Query<(&GlobalTransform, &Children), With<Player>>,
+    mut eye_query: Query<(&Eye, &mut Transform)>,
+) {
+    let blend = (time.delta_seconds() * 10.0).min(1.0);
+    for table_game in game_query.iter() {
+        let target = table_game
+            .to_act()
+            .and_then(|(_, player_id)| player_query.get(player_id).ok())
+            .map(|(transform, _)| transform.translation());
+        for player_id in table_game.seats.iter() {
+            let Ok((transform, children)) = player_query.get(*player_id) else {
+                continue;
+            };
+            let (_, rotation, translation) = transform.to_scale_rotation_translation();
+            let look = match target {
+                Some(target) if target != translation => {
+                    let local = rotation.inverse() * (target - translation);
+                    Vec3::new(local.x, 0.0, local.z).normalize_or_zero() * EYE_SHIFT
+                }
+                _ => Vec3::ZERO,
+            };
+            for child in children.iter() {
+                if let Ok((eye, mut eye_transform)) = eye_query.get_mut(*child) {
+                    eye_transform.translation =
+                        eye_transform.translation.lerp(eye.rest + look, blend);
+                }
+            }
+        }
+    }
+}
+
+/// A ring around the active seat, shrinking as its time runs out.
+fn draw_turn_clocks(
+    mut gizmos: Gizmos,
+    handles: Res<Handles>,
+    game_query: Query<(&TableGame, &TurnClock)>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+) {
+    let radius = handles.player_body_shape.radius + 0.15;
+    for (table_game, clock) in game_query.iter() {
+        let Some(transform) = table_game
+            .to_act()
+            .and_then(|(_, player_id)| player_query.get(player_id).ok())
+        else {
+            continue;
+        };
+        let center =
+            transform.translation() - Vec3::Y * handles.player_body_shape.half_length / 2.0;
+        let left = clock.timer.fraction_remaining();
+        let segments = (48.0 * left).ceil() as usize;
+        let points = (0..=segments).map(|i| {
+            let angle = TAU * left * i as f32 / segments.max(1) as f32;
+            center + Vec3::new(angle.sin(), 0.0, angle.cos()) * radius
+        });
+        gizmos.linestrip(points, Color::rgb(1.0 - left, left, 0.0));
+    }
+}
@@ -0,0 +1,319 @@
+use bevy::prelude::*;
+
+use crate::game::stacked_deck;
+use crate::game::Action;
+use crate::game::Game;
+use crate::play::Scripted;
+use crate::play::TableGame;
+use crate::rules::RuleSet;
+use crate::BotDifficulty;
+use crate::BotStyle;
+use crate::Card;
+use crate::Personality;
+use crate::Rank;
+use crate::SeatKind;
+use crate::SpawnSessionEvent;
+use crate::Suit;
+
+/// Where the person learning sits.
+pub const YOU: usize = 0;
+
+/// Deals the first round, so you bid and lead first.
+pub const FIRST_DEALER: usize = 2;
+
+/// Walks a new player through the first rounds of a real game, one prompt at a time.
+pub struct TutorialPlugin;
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tutorial>();
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, run_tutorial);
+    }
+}
+
+/// How far along the tutorial is.
+#[derive(Resource, Debug, Default)]
+struct Tutorial {
+    step: usize,
+    /// How much of the game log has been checked for the actions steps wait for.
+    seen: usize,
+}
+
+enum Wait {
+    Space,
+    /// You have to take exactly this action.
+    Action(Action),
+}
+
+struct Step {
+    /// The step is shown once this holds.
+    ready: fn(&Game) -> bool,
+    text: &'static str,
+    wait: Wait,
+}
+
+fn your_turn(game: &Game) -> bool {
+    game.phase().seat() == Some(YOU)
+}
+
+fn steps() -> Vec<Step> {
+    vec![
+        Step {
+            ready: |_| true,
+            text: "Welcome to Poche!\nEveryone is dealt the same number of cards, 1 in the first round, up to 7 and back down to 1. Each round you bid how many tricks you'll take, then try to take exactly that many.",
+            wait: Wait::Space,
+        },
+        Step {
+            ready: |_| true,
+            text: "This is trump: the card turned up on the table. Its suit, spades this round, beats every other suit.",
+            wait: Wait::Space,
+        },
+        Step {
+            ready: your_turn,
+            text: "Bid how many tricks you'll take. Your ace of spades is the highest trump, nothing can beat it.",
+            wait: Wait::Action(Action::Bid(1)),
+        },
+        Step {
+            ready: your_turn,
+            text: "Sitting left of the dealer, you lead the first trick.",
+            wait: Wait::Action(Action::Play(Card::new(Suit::Spades, Rank::Ace))),
+        },
+        Step {
+            ready: |game| game.round_index >= 1,
+            text: "You took the trick you bid. Making your bid puts a 1 in front of it: 11 points. Taking every trick would have put a 2 in front instead.",
+            wait: Wait::Space,
+        },
+        Step {
+            ready: your_turn,
+            text: "You deal the next round, 2 cards each, and bid last. Diamonds are trump. Bid 1, hoping your queen of hearts takes a trick.",
+            wait: Wait::Action(Action::Bid(1)),
+        },
+        Step {
+            ready: your_turn,
+            text: "You must follow suit: hearts were led and you have a heart, so your queen has to go, even though the ace beats it.",
+            wait: Wait::Action(Action::Play(Card::new(Suit::Hearts, Rank::Queen))),
+        },
+        Step {
+            ready: |game| game.round_index >= 2,
+            text: "You poched: you bid 1 but took nothing. A poche scores no points and you pay 10¢ into the pot, ten cents a lesson. Whoever has the most points at the end wins the pot.",
+            wait: Wait::Space,
+        },
+        Step {
+            ready: |_| true,
+            text: "That's all there is to it. The rest of the game is dealt at random, good luck!",
+            wait: Wait::Space,
+        },
+    ]
+}
+
+/// Hands by seat and the trump card for the first rounds.
+fn deals() -> Vec<(Vec<Vec<Card>>, Card)> {
+    vec![
+        (
+            vec![
+                vec![Card::new(Suit::Spades, Rank::Ace)],
+                vec![Card::new(Suit::Hearts, Rank::King)],
+                vec![Card::new(Suit::Diamonds, Rank::Nine)],
+            ],
+            Card::new(Suit::Spades, Rank::Five),
+        ),
+        (
+            vec![
+                vec![
+                    Card::new(Suit::Hearts, Rank::Queen),
+                    Card::new(Suit::Clubs, Rank::Two),
+                ],
+                vec![
+                    Card::new(Suit::Hearts, Rank::Ace),
+                    Card::new(Suit::Clubs, Rank::Three),
+                ],
+                vec![
+                    Card::new(Suit::Hearts, Rank::Four),
+                    Card::new(Suit::Clubs, Rank::Ace),
+                ],
+            ],
+            Card::new(Suit::Diamonds, Rank::Five),
+        ),
+    ]
+}
+
+/// The tutorial's table: you and two bots, with the first rounds stacked.
+///
+/// The bots are experts, which never misbid or misplay, so the stacked rounds go as the text says.
+pub fn session_event() -> SpawnSessionEvent {
+    let deals = deals();
+    let num_players = deals[0].0.len();
+    let style = BotStyle {
+        difficulty: BotDifficulty::Expert,
+        personality: Personality::Balanced,
+    };
+    SpawnSessionEvent {
+        seats: SeatKind::default_seats(num_players, style),
+        rules: RuleSet::default(),
+        seed: Some(0),
+        dealer: Some(FIRST_DEALER),
+        decks: deals
+            .iter()
+            .enumerate()
+            .map(|(round, (hands, trump_card))| {
+                stacked_deck(hands, *trump_card, (FIRST_DEALER + round) % num_players)
+            })
+            .collect(),
+        moves: Vec::new(),
+    }
+}
+
+#[derive(Component)]
+struct TutorialText;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: default(),
+                font_size: 22.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.0),
+            left: Val::Percent(25.0),
+            width: Val::Percent(50.0),
+            padding: UiRect::all(Val::Px(12.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+        TutorialText,
+        Name::new("Tutorial Prompt"),
+    ));
+}
+
+/// Show the step you're on and hold the table to it until you do what it asks.
+fn run_tutorial(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut tutorial: ResMut<Tutorial>,
+    game_query: Query<(Entity, &TableGame, Option<&Scripted>)>,
+    mut tutorial_text_query: Query<(&mut Text, &mut Visibility), With<TutorialText>>,
+) {
+    let Some((session_id, table_game, scripted)) = game_query.iter().next() else {
+        return;
+    };
+    let game = &table_game.game;
+    let steps = steps();
+
+    let new_actions = &game.log[tutorial.seen..];
+    tutorial.seen = game.log.len();
+    if let Some(step) = steps.get(tutorial.step) {
+        let done = match step.wait {
+            Wait::Space => (step.ready)(game) && input.just_pressed(KeyCode::Space),
+            Wait::Action(action) => new_actions.contains(&(YOU, action)),
+        };
+        if done {
+            tutorial.step += 1;
+        }
+    }
+
+    let Some(step) = steps.get(tutorial.step) else {
+        if scripted.is_some() {
+            commands.entity(session_id).remove::<Scripted>();
+            for (_, mut visibility) in tutorial_text_query.iter_mut() {
+                *visibility = Visibility::Hidden;
+            }
+            info!("Tutorial finished");
+        }
+        return;
+    };
+    let showing = (step.ready)(game);
+    let wanted = match (showing, &step.wait) {
+        (false, _) => Scripted::default(),
+        (true, Wait::Space) => Scripted {
+            paused: true,
+            allowed: Some(Vec::new()),
+        },
+        (true, Wait::Action(action)) => Scripted {
+            paused: true,
+            allowed: Some(vec![*action]),
+        },
+    };
+    if scripted != Some(&wanted) {
+        commands.entity(session_id).insert(wanted);
+    }
+
+    let how = match step.wait {
+        Wait::Space => "Press Space to continue.".to_owned(),
+        Wait::Action(Action::Bid(bid)) => format!("Press {bid} to bid {bid}."),
+        Wait::Action(Action::Play(card)) => {
+            let key = table_game
+                .sorted_hand(YOU)
+                .iter()
+                .position(|held| *held == card)
+                .map_or(0, |i| i + 1);
+            format!("Press {key} to play {card}.")
+        }
+    };
+    let prompt = format!("{}\n\n{how}", step.text);
+    for (mut text, mut visibility) in tutorial_text_query.iter_mut() {
+        if text.sections[0].value != prompt {
+            text.sections[0].value = prompt.clone();
+        }
+        *visibility = match showing {
+            true => Visibility::Visible,
+            false => Visibility::Hidden,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::bots::choose_action;
+
+    #[test]
+    fn the_tutorial_plays_out_as_scripted() {
+        let event = session_event();
+        let mut game =
+            Game::with_decks(event.rules, event.seats.len(), FIRST_DEALER, 0, event.decks);
+        let mut yours = [
+            Action::Bid(1),
+            Action::Play(Card::new(Suit::Spades, Rank::Ace)),
+            Action::Bid(1),
+            Action::Play(Card::new(Suit::Hearts, Rank::Queen)),
+            Action::Play(Card::new(Suit::Clubs, Rank::Two)),
+        ]
+        .into_iter();
+        let mut rng = StdRng::seed_from_u64(0);
+        while game.round_index < 2 {
+            let (seat, action) = match game.phase().seat() {
+                Some(YOU) => (YOU, yours.next().expect("a scripted action")),
+                Some(seat) => {
+                    let SeatKind::Bot(style) = &event.seats[seat] else {
+                        panic!("seat {seat} isn't a bot");
+                    };
+                    let (seat, action, _) =
+                        choose_action(style, &game, &mut rng).expect("a bot to act");
+                    (seat, action)
+                }
+                None => unreachable!("the game goes on until the third round"),
+            };
+            game.apply(seat, action)
+                .unwrap_or_else(|e| panic!("seat {seat} can't {action:?}: {e}"));
+        }
+
+        assert!(game.results[YOU][0].made());
+        assert!(!game.results[YOU][1].made());
+        for (seat, results) in game.results.iter().enumerate() {
+            assert_eq!(
+                results[1].made(),
+                seat != YOU,
+                "seat {seat} in the second round"
+            );
+        }
+    }
+}